@@ -0,0 +1,385 @@
+use md5::Md5;
+use rand::Rng;
+use reqwest::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+
+use crate::client::{Client, SendError};
+
+#[derive(Debug)]
+pub enum AuthError {
+    InvalidCredentials(String),
+    InvalidApiKey(String),
+    AuthorizationSet,
+    Prompt(std::io::Error),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::InvalidCredentials(text) => {
+                write!(f, "Invalid credentials: '{}'. Use 'user:pass' format", text)
+            }
+            AuthError::InvalidApiKey(text) => {
+                write!(f, "Invalid API key: '{}'", text)
+            }
+            AuthError::AuthorizationSet => {
+                write!(f, "Digest authentication would replace the Authorization header another auth option set")
+            }
+            AuthError::Prompt(e) => {
+                write!(f, "Could not read password: {}", e)
+            }
+        }
+    }
+}
+
+impl Error for AuthError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AuthType {
+    Basic,
+    Bearer,
+    Digest,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    #[default]
+    None,
+    Basic { username: String, password: String },
+    Bearer { token: String },
+    Digest { username: String, password: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyLocation {
+    Header,
+    Query,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKey {
+    pub name: String,
+    pub value: String,
+    pub location: ApiKeyLocation,
+}
+
+/// Builds an `Auth` from `--auth` style credentials. When a username is given
+/// without a password, the password is read from the terminal.
+pub fn parse_auth(credentials: &str, auth_type: AuthType) -> Result<Auth, AuthError> {
+    if auth_type == AuthType::Bearer {
+        let token = if credentials.is_empty() {
+            rpassword::prompt_password("Token: ").map_err(AuthError::Prompt)?
+        } else {
+            credentials.to_string()
+        };
+        return Ok(Auth::Bearer { token });
+    }
+
+    let (username, password) = match credentials.split_once(':') {
+        Some((user, pass)) => (user.to_string(), pass.to_string()),
+        None => {
+            let prompt = format!("Password for user {}: ", credentials);
+            let pass = rpassword::prompt_password(prompt).map_err(AuthError::Prompt)?;
+            (credentials.to_string(), pass)
+        }
+    };
+
+    if username.is_empty() {
+        return Err(AuthError::InvalidCredentials(credentials.to_string()));
+    }
+
+    Ok(match auth_type {
+        AuthType::Digest => Auth::Digest { username, password },
+        _ => Auth::Basic { username, password },
+    })
+}
+
+/// Parses an API key given as `Name: value` (header) or `name=value` (query).
+pub fn parse_api_key(spec: &str, location: ApiKeyLocation) -> Result<ApiKey, AuthError> {
+    let separator = match location {
+        ApiKeyLocation::Header => ':',
+        ApiKeyLocation::Query => '=',
+    };
+
+    match spec.split_once(separator) {
+        Some((name, value)) if !name.trim().is_empty() => Ok(ApiKey {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+            location,
+        }),
+        _ => Err(AuthError::InvalidApiKey(spec.to_string())),
+    }
+}
+
+pub fn apply_auth(request: reqwest::RequestBuilder, auth: &Auth) -> reqwest::RequestBuilder {
+    match auth {
+        Auth::Basic { username, password } => request.basic_auth(username, Some(password)),
+        Auth::Bearer { token } => request.bearer_auth(token),
+        // Digest needs the server's challenge first, see `send_with_auth`.
+        Auth::Digest { .. } | Auth::None => request,
+    }
+}
+
+pub fn apply_api_key(request: reqwest::RequestBuilder, key: &ApiKey) -> reqwest::RequestBuilder {
+    match key.location {
+        ApiKeyLocation::Header => request.header(key.name.as_str(), key.value.as_str()),
+        ApiKeyLocation::Query => request.query(&[(key.name.as_str(), key.value.as_str())]),
+    }
+}
+
+/// Sends `request`, answering a Digest challenge with a second request when
+/// the server replies 401 and Digest credentials are configured.
+pub async fn send_with_auth(
+    client: &Client,
+    request: reqwest::Request,
+    auth: &Auth,
+) -> Result<reqwest::Response, SendError> {
+    let (username, password) = match auth {
+        Auth::Digest { username, password } => (username, password),
+        _ => return client.execute(request).await,
+    };
+
+    let retry = request.try_clone();
+    let response = client.execute(request).await?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Ok(response);
+    }
+
+    let challenge = response
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(DigestChallenge::parse)
+        .max_by_key(|challenge| challenge.algorithm == DigestAlgorithm::Sha256);
+
+    let (mut retry, challenge) = match (retry, challenge) {
+        (Some(retry), Some(challenge)) => (retry, challenge),
+        _ => return Ok(response),
+    };
+
+    let cnonce = format!("{:016x}", rand::thread_rng().r#gen::<u64>());
+    let header = challenge.authorization(username, password, retry.method().as_str(), retry.url(), &cnonce);
+    if let Ok(value) = HeaderValue::from_str(&header) {
+        retry.headers_mut().insert(AUTHORIZATION, value);
+    }
+    client.execute(retry).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestAlgorithm {
+    Md5,
+    Sha256,
+}
+
+#[derive(Debug)]
+struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: DigestAlgorithm,
+    session: bool,
+    qop_auth: bool,
+}
+
+impl DigestChallenge {
+    fn parse(header: &str) -> Option<DigestChallenge> {
+        let (scheme, params) = header.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+
+        let params = parse_auth_params(params);
+        let get = |key: &str| {
+            params
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.clone())
+        };
+
+        let algorithm = get("algorithm").unwrap_or_else(|| "MD5".to_string());
+        let (algorithm, session) = match algorithm.to_ascii_uppercase().as_str() {
+            "MD5" => (DigestAlgorithm::Md5, false),
+            "MD5-SESS" => (DigestAlgorithm::Md5, true),
+            "SHA-256" => (DigestAlgorithm::Sha256, false),
+            "SHA-256-SESS" => (DigestAlgorithm::Sha256, true),
+            _ => return None,
+        };
+
+        // Only qop=auth is supported; auth-int would require hashing the body.
+        let qop = get("qop");
+        let qop_auth = qop
+            .as_deref()
+            .map(|qop| qop.split(',').any(|q| q.trim().eq_ignore_ascii_case("auth")));
+        if qop_auth == Some(false) {
+            return None;
+        }
+
+        Some(DigestChallenge {
+            realm: get("realm").unwrap_or_default(),
+            nonce: get("nonce")?,
+            opaque: get("opaque"),
+            algorithm,
+            session,
+            qop_auth: qop_auth.unwrap_or(false),
+        })
+    }
+
+    fn hash(&self, data: &str) -> String {
+        match self.algorithm {
+            DigestAlgorithm::Md5 => format!("{:x}", Md5::digest(data.as_bytes())),
+            DigestAlgorithm::Sha256 => format!("{:x}", Sha256::digest(data.as_bytes())),
+        }
+    }
+
+    fn authorization(&self, username: &str, password: &str, method: &str, url: &reqwest::Url, cnonce: &str) -> String {
+        let uri = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let nc = "00000001";
+
+        let mut ha1 = self.hash(&format!("{}:{}:{}", username, self.realm, password));
+        if self.session {
+            ha1 = self.hash(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+        let ha2 = self.hash(&format!("{}:{}", method, uri));
+
+        let response = if self.qop_auth {
+            self.hash(&format!("{}:{}:{}:{}:auth:{}", ha1, self.nonce, nc, cnonce, ha2))
+        } else {
+            self.hash(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+
+        let algorithm = match (self.algorithm, self.session) {
+            (DigestAlgorithm::Md5, false) => "MD5",
+            (DigestAlgorithm::Md5, true) => "MD5-sess",
+            (DigestAlgorithm::Sha256, false) => "SHA-256",
+            (DigestAlgorithm::Sha256, true) => "SHA-256-sess",
+        };
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            username, self.realm, self.nonce, uri, algorithm, response
+        );
+        if self.qop_auth {
+            header.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce));
+        }
+        if let Some(opaque) = &self.opaque {
+            header.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        header
+    }
+}
+
+/// Splits `key=value, key="quoted, value"` pairs from an auth header.
+fn parse_auth_params(input: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while matches!(chars.peek(), Some(c) if *c == ',' || c.is_whitespace()) {
+            chars.next();
+        }
+        let key: String = chars.by_ref().take_while(|c| *c != '=').collect();
+        if key.trim().is_empty() {
+            break;
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    _ => value.push(c),
+                }
+            }
+        } else {
+            value = chars.by_ref().take_while(|c| *c != ',').collect();
+        }
+        params.push((key.trim().to_string(), value.trim().to_string()));
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The example exchange from RFC 7616, section 3.9.1.
+    const CHALLENGE: &str = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=ALGORITHM, \
+        nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    fn authorization(algorithm: &str) -> String {
+        let challenge = DigestChallenge::parse(&CHALLENGE.replace("ALGORITHM", algorithm)).unwrap();
+        let url = reqwest::Url::parse("http://www.example.org/dir/index.html").unwrap();
+        challenge.authorization("Mufasa", "Circle of Life", "GET", &url, CNONCE)
+    }
+
+    #[test]
+    fn parses_challenge() {
+        let challenge = DigestChallenge::parse(&CHALLENGE.replace("ALGORITHM", "SHA-256")).unwrap();
+        assert_eq!(challenge.realm, "http-auth@example.org");
+        assert_eq!(challenge.nonce, "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v");
+        assert_eq!(challenge.opaque.as_deref(), Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS"));
+        assert_eq!(challenge.algorithm, DigestAlgorithm::Sha256);
+        assert!(!challenge.session);
+        assert!(challenge.qop_auth);
+    }
+
+    #[test]
+    fn rejects_other_schemes_and_qops() {
+        assert!(DigestChallenge::parse("Basic realm=\"x\"").is_none());
+        assert!(DigestChallenge::parse("Digest realm=\"x\", nonce=\"n\", qop=\"auth-int\"").is_none());
+        assert!(DigestChallenge::parse("Digest realm=\"x\", nonce=\"n\", algorithm=SHA-512").is_none());
+        assert!(DigestChallenge::parse("Digest realm=\"x\"").is_none());
+    }
+
+    #[test]
+    fn answers_rfc_7616_md5_example() {
+        let header = authorization("MD5");
+        assert!(header.contains("response=\"8ca523f5e9506fed4657c9700eebdbec\""), "{}", header);
+        assert!(header.contains("uri=\"/dir/index.html\""));
+        assert!(header.contains("qop=auth, nc=00000001"));
+        assert!(header.contains("opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\""));
+    }
+
+    #[test]
+    fn answers_rfc_7616_sha256_example() {
+        let header = authorization("SHA-256");
+        assert!(header.contains("response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""), "{}", header);
+        assert!(header.contains("algorithm=SHA-256,"));
+    }
+
+    #[test]
+    fn splits_quoted_params() {
+        let params = parse_auth_params("a=1, b=\"x, \\\"y\\\"\", c=z");
+        assert_eq!(
+            params,
+            [("a".into(), "1".into()), ("b".into(), "x, \"y\"".into()), ("c".into(), "z".into())]
+        );
+    }
+
+    #[test]
+    fn parses_credentials_and_api_keys() {
+        assert!(matches!(
+            parse_auth("user:p:w", AuthType::Digest).unwrap(),
+            Auth::Digest { username, password } if username == "user" && password == "p:w"
+        ));
+        assert!(matches!(parse_auth(":pass", AuthType::Basic), Err(AuthError::InvalidCredentials(_))));
+
+        let key = parse_api_key("X-Api-Key: secret", ApiKeyLocation::Header).unwrap();
+        assert_eq!((key.name.as_str(), key.value.as_str()), ("X-Api-Key", "secret"));
+        let key = parse_api_key("api_key=a=b", ApiKeyLocation::Query).unwrap();
+        assert_eq!((key.name.as_str(), key.value.as_str()), ("api_key", "a=b"));
+        assert!(parse_api_key("=value", ApiKeyLocation::Query).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use reqwest::header::HeaderValue;
+use std::error::Error;
+use std::fmt;
+
+use crate::content;
+
+/// Bytes searched for an HTML `<meta>` charset declaration, as browsers do.
+const META_SCAN_LENGTH: usize = 1024;
+
+#[derive(Debug)]
+pub enum CharsetError {
+    Unknown(String),
+}
+
+impl fmt::Display for CharsetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CharsetError::Unknown(label) => {
+                write!(f, "Unknown charset: '{}'. Use a name such as utf-8, windows-1252, shift_jis or gb18030", label)
+            }
+        }
+    }
+}
+
+impl Error for CharsetError {}
+
+/// Looks up a charset by any of its WHATWG labels, such as `latin1` or `sjis`.
+pub fn lookup(label: &str) -> Result<&'static Encoding, CharsetError> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| CharsetError::Unknown(label.to_string()))
+}
+
+/// Where the charset of a body came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Override,
+    ByteOrderMark,
+    ContentType,
+    MetaTag,
+    /// Nothing was declared and the body is valid UTF-8.
+    Default,
+    /// Nothing was declared and the body is not valid UTF-8.
+    Fallback,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::Override => "--response-charset",
+            Source::ByteOrderMark => "byte order mark",
+            Source::ContentType => "Content-Type",
+            Source::MetaTag => "HTML meta tag",
+            Source::Default => "default",
+            Source::Fallback => "guess",
+        })
+    }
+}
+
+/// A body decoded to text.
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub text: String,
+    pub charset: &'static Encoding,
+    pub source: Source,
+    /// Some bytes were invalid in the charset and replaced.
+    pub replaced: bool,
+}
+
+impl Text {
+    /// `Shift_JIS (from Content-Type)`, or `None` for undeclared UTF-8.
+    pub fn summary(&self) -> Option<String> {
+        if self.source == Source::Default {
+            return None;
+        }
+        let mut summary = match self.source {
+            Source::Fallback => format!("{} (guessed, the body is not valid UTF-8)", self.charset.name()),
+            source => format!("{} (from {})", self.charset.name(), source),
+        };
+        if self.replaced {
+            summary.push_str(", some bytes could not be decoded");
+        }
+        Some(summary)
+    }
+}
+
+/// Decodes `bytes` with, in order: `forced`, a byte order mark, the
+/// Content-Type charset, an HTML `<meta>` declaration, and UTF-8. Bodies
+/// that declare nothing and are not UTF-8 are read as Windows-1252.
+pub fn decode(bytes: &[u8], content_type: &str, forced: Option<&'static Encoding>) -> Text {
+    let (charset, source) = match forced {
+        Some(charset) => (charset, Source::Override),
+        None => match Encoding::for_bom(bytes) {
+            Some((charset, _)) => (charset, Source::ByteOrderMark),
+            None => match declared(content_type, bytes) {
+                Some(found) => found,
+                None if std::str::from_utf8(bytes).is_ok() => (UTF_8, Source::Default),
+                None => (WINDOWS_1252, Source::Fallback),
+            },
+        },
+    };
+
+    // `decode` strips a byte order mark matching the charset.
+    let (text, _, replaced) = charset.decode(bytes);
+    Text {
+        text: text.into_owned(),
+        charset,
+        source,
+        replaced,
+    }
+}
+
+fn declared(content_type: &str, bytes: &[u8]) -> Option<(&'static Encoding, Source)> {
+    if let Some(charset) = content::parameter(content_type, "charset").and_then(|label| lookup(&label).ok()) {
+        return Some((charset, Source::ContentType));
+    }
+    if content::media_type(content_type) == "text/html" || content_type.is_empty() {
+        return meta_charset(&bytes[..bytes.len().min(META_SCAN_LENGTH)]).map(|charset| (charset, Source::MetaTag));
+    }
+    None
+}
+
+/// Finds `<meta charset="...">` or
+/// `<meta http-equiv="Content-Type" content="text/html; charset=...">`.
+fn meta_charset(head: &[u8]) -> Option<&'static Encoding> {
+    // Declarations are ASCII, so a lossy view of the bytes is enough.
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    for tag in head.split("<meta").skip(1) {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let Some(start) = tag.find("charset=") else {
+            continue;
+        };
+        let label: String = tag[start + "charset=".len()..]
+            .trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+            .collect();
+        // A page cannot meaningfully declare UTF-16 in ASCII; browsers use UTF-8.
+        return match lookup(&label).ok()? {
+            charset if charset.output_encoding() != charset => Some(UTF_8),
+            charset => Some(charset),
+        };
+    }
+    None
+}
+
+/// A header value for display: UTF-8 when it is valid, otherwise each byte
+/// as ISO-8859-1, the historical charset of HTTP header values.
+pub fn header_value(value: &HeaderValue) -> String {
+    match std::str::from_utf8(value.as_bytes()) {
+        Ok(text) => text.to_string(),
+        Err(_) => value.as_bytes().iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, UTF_16LE, WINDOWS_1251};
+
+    #[test]
+    fn looks_up_labels() {
+        assert_eq!(lookup(" latin1 ").unwrap(), WINDOWS_1252);
+        assert_eq!(lookup("sjis").unwrap(), SHIFT_JIS);
+        assert!(matches!(lookup("klingon"), Err(CharsetError::Unknown(label)) if label == "klingon"));
+    }
+
+    #[test]
+    fn prefers_the_override_then_the_byte_order_mark() {
+        let forced = decode(b"caf\xe9", "text/plain; charset=utf-8", Some(WINDOWS_1252));
+        assert_eq!((forced.text.as_str(), forced.source), ("caf\u{e9}", Source::Override));
+
+        let bom = decode(b"\xff\xfeh\0i\0", "text/plain; charset=iso-8859-1", None);
+        assert_eq!((bom.text.as_str(), bom.charset, bom.source), ("hi", UTF_16LE, Source::ByteOrderMark));
+        assert_eq!(bom.summary().unwrap(), "UTF-16LE (from byte order mark)");
+    }
+
+    #[test]
+    fn reads_the_content_type_charset() {
+        let text = decode(b"\x82\xb1\x82\xf1", "text/plain; charset=\"Shift_JIS\"", None);
+        assert_eq!(text.text, "\u{3053}\u{3093}");
+        assert_eq!(text.summary().unwrap(), "Shift_JIS (from Content-Type)");
+        // Unknown labels are ignored.
+        let unknown = decode(b"plain", "text/plain; charset=x-unknown", None);
+        assert_eq!(unknown.source, Source::Default);
+    }
+
+    #[test]
+    fn reads_html_meta_tags() {
+        let html = b"<html><head><META http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1251\"></head>\xcf\xf0\xe8";
+        let text = decode(html, "text/html", None);
+        assert_eq!((text.charset, text.source), (WINDOWS_1251, Source::MetaTag));
+        assert!(text.text.ends_with("\u{41f}\u{440}\u{438}"));
+
+        let short = decode(b"<meta charset='utf-16'>hi", "", None);
+        assert_eq!((short.charset, short.source), (UTF_8, Source::MetaTag));
+        // Only HTML, or bodies without a type, are searched.
+        assert_eq!(decode(b"<meta charset=windows-1251>", "text/plain", None).source, Source::Default);
+        // The declaration must be within the first 1024 bytes.
+        let mut late = vec![b' '; META_SCAN_LENGTH];
+        late.extend_from_slice(b"<meta charset=windows-1251>");
+        assert_eq!(decode(&late, "text/html", None).source, Source::Default);
+    }
+
+    #[test]
+    fn falls_back_for_undeclared_bodies() {
+        let utf8 = decode("na\u{ef}ve".as_bytes(), "text/plain", None);
+        assert_eq!(utf8.source, Source::Default);
+        assert!(utf8.summary().is_none());
+
+        let guessed = decode(b"na\xefve", "text/plain", None);
+        assert_eq!(guessed.text, "na\u{ef}ve");
+        assert_eq!(guessed.summary().unwrap(), "windows-1252 (guessed, the body is not valid UTF-8)");
+    }
+
+    #[test]
+    fn reports_replaced_bytes() {
+        let text = decode(b"ok \xff", "text/plain; charset=utf-8", None);
+        assert!(text.replaced);
+        assert_eq!(text.text, "ok \u{fffd}");
+        assert_eq!(text.summary().unwrap(), "UTF-8 (from Content-Type), some bytes could not be decoded");
+    }
+
+    #[test]
+    fn shows_latin1_header_values() {
+        assert_eq!(header_value(&HeaderValue::from_static("plain")), "plain");
+        assert_eq!(header_value(&HeaderValue::from_bytes(b"caf\xe9").unwrap()), "caf\u{e9}");
+        assert_eq!(header_value(&HeaderValue::from_bytes("caf\u{e9}".as_bytes()).unwrap()), "caf\u{e9}");
+    }
+}
@@ -0,0 +1,613 @@
+use hyper::client::HttpConnector;
+use hyper_rustls::HttpsConnector;
+use reqwest::cookie::CookieStore;
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, CONTENT_LENGTH, COOKIE, HOST, SET_COOKIE, USER_AGENT,
+};
+use reqwest::{Method, Request, RequestBuilder, Response, ResponseBuilderExt, Url};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::compression;
+use crate::cookies::CookieJar;
+use crate::dns::{DnsSettings, Resolution, Resolver};
+use crate::proxy::{ProxySettings, Route};
+use crate::retry::{Attempt, AttemptLog, RetryPolicy};
+use crate::timing::{Layer, TimedConnector, Timeline};
+use crate::tls::{CertificateLog, TlsConfig, TlsError};
+use crate::unix::{UnixError, UnixTransport};
+use crate::wire::{Message, WireLog};
+
+/// Opens the connections of requests that go neither through a proxy nor
+/// over a Unix socket, timing the TCP connect and the TLS handshake.
+type Connector = TimedConnector<HttpsConnector<TimedConnector<HttpConnector<Resolver>>>>;
+
+/// Sent unless the request sets its own User-Agent.
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug)]
+pub enum BuildError {
+    Tls(TlsError),
+    Client(reqwest::Error),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Tls(e) => write!(f, "{}", e),
+            BuildError::Client(e) => write!(f, "Could not create HTTP client: {}", e),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+/// Why a request could not be sent.
+#[derive(Debug)]
+pub enum SendError {
+    Request(reqwest::Error),
+    /// A request sent over a direct connection failed.
+    Connection(Url, hyper::Error),
+    /// No response headers arrived within the timeout.
+    Timeout(Url, Duration),
+    /// The URL cannot be written as an HTTP request target.
+    InvalidUrl(Url),
+    Unix(UnixError),
+    /// `--http2` was given but the server answered with another version.
+    NotHttp2(Url, reqwest::Version),
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Request(e) => write!(f, "{}", e),
+            SendError::Connection(url, e) => write!(f, "error sending request for url ({}): {}", url, e),
+            SendError::Timeout(url, timeout) => {
+                write!(f, "error sending request for url ({}): no response within {:?}", url, timeout)
+            }
+            SendError::InvalidUrl(url) => write!(f, "{} cannot be sent as an HTTP request", url),
+            SendError::Unix(e) => write!(f, "{}", e),
+            SendError::NotHttp2(url, version) => {
+                write!(f, "{} answered with {:?} instead of HTTP/2", url, version)
+            }
+        }
+    }
+}
+
+impl Error for SendError {}
+
+impl SendError {
+    /// Connection failures and timeouts, which may go away when retried.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SendError::Request(e) => e.is_connect() || e.is_timeout(),
+            SendError::Connection(_, e) => e.is_connect(),
+            SendError::Timeout(..) => true,
+            SendError::Unix(UnixError::Connect(..)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SendError {
+    fn from(error: reqwest::Error) -> Self {
+        SendError::Request(error)
+    }
+}
+
+/// Which HTTP versions clients may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpVersion {
+    /// HTTP/2 when the server offers it during the TLS handshake, else HTTP/1.1.
+    #[default]
+    Negotiate,
+    Http11,
+    /// HTTP/2 over TLS only; servers that do not offer it fail the handshake.
+    Http2,
+    /// HTTP/2 without negotiation, including cleartext h2c.
+    Http2PriorKnowledge,
+}
+
+impl HttpVersion {
+    /// ALPN protocols offered in the TLS handshake.
+    fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        match self {
+            HttpVersion::Negotiate => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            HttpVersion::Http11 => vec![b"http/1.1".to_vec()],
+            HttpVersion::Http2 | HttpVersion::Http2PriorKnowledge => vec![b"h2".to_vec()],
+        }
+    }
+}
+
+/// Connection settings shared by the CLI and the TUI.
+#[derive(Debug, Clone, Default)]
+pub struct ClientSettings {
+    pub proxy: ProxySettings,
+    pub dns: DnsSettings,
+    /// Send every request over this Unix socket instead of TCP.
+    pub unix_socket: Option<PathBuf>,
+    pub tls: TlsConfig,
+    pub http_version: HttpVersion,
+    /// Limit for each attempt, from connecting to the end of the body.
+    pub timeout: Option<Duration>,
+    pub retry: RetryPolicy,
+    /// Every attempt made by clients built from these settings.
+    pub attempts: Arc<AttemptLog>,
+    /// Server certificate chains seen by clients built from these settings.
+    pub certificates: Arc<CertificateLog>,
+    /// Connection events of requests sent by clients built from these settings.
+    pub timeline: Arc<Timeline>,
+    /// Where to record every request and response head, for `-v`.
+    pub wire: Option<Arc<WireLog>>,
+}
+
+impl ClientSettings {
+    /// Builds a client that stores cookies in `jar`. Redirects are left to
+    /// `redirect::follow` so every hop can be shown.
+    pub fn build(&self, jar: Arc<CookieJar>) -> Result<Client, BuildError> {
+        let mut tls = self.tls.rustls_config(self.certificates.clone()).map_err(BuildError::Tls)?;
+        tls.alpn_protocols = self.http_version.alpn_protocols();
+        let resolver = self.dns.resolver(self.timeline.clone());
+        let direct = direct_client(resolver.clone(), tls.clone(), self.http_version, self.timeline.clone());
+
+        let mut builder = reqwest::Client::builder()
+            .cookie_provider(jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .proxy(self.proxy.to_reqwest())
+            .dns_resolver(Arc::new(resolver))
+            .use_preconfigured_tls(tls);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder = match self.http_version {
+            HttpVersion::Http11 => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+            HttpVersion::Negotiate | HttpVersion::Http2 => builder,
+        };
+        let http = builder.build().map_err(BuildError::Client)?;
+
+        let http2 = self.http_version == HttpVersion::Http2PriorKnowledge;
+        Ok(Client {
+            http,
+            direct,
+            proxy: self.proxy.clone(),
+            timeout: self.timeout,
+            unix: UnixTransport::new(self.unix_socket.clone(), http2, jar.clone()),
+            require_http2: self.http_version == HttpVersion::Http2,
+            jar,
+            wire: self.wire.clone(),
+            retry: self.retry.clone(),
+            attempts: self.attempts.clone(),
+        })
+    }
+}
+
+/// The client for requests that go neither through a proxy nor over a Unix
+/// socket, with the TCP connect and the TLS handshake timed.
+fn direct_client(
+    resolver: Resolver,
+    tls: rustls::ClientConfig,
+    version: HttpVersion,
+    timeline: Arc<Timeline>,
+) -> hyper::Client<Connector> {
+    let mut tcp = HttpConnector::new_with_resolver(resolver);
+    tcp.enforce_http(false);
+    tcp.set_nodelay(true);
+    let tcp = TimedConnector::new(tcp, Layer::Tcp, timeline.clone());
+    let https = HttpsConnector::from((tcp, tls));
+    hyper::Client::builder()
+        .http2_only(version == HttpVersion::Http2PriorKnowledge)
+        .build(TimedConnector::new(https, Layer::Tls, timeline))
+}
+
+/// Sends requests over TCP, or over a Unix socket for `--unix-socket` and
+/// `http+unix://` URLs. Requests through a proxy go out with reqwest.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    direct: hyper::Client<Connector>,
+    proxy: ProxySettings,
+    timeout: Option<Duration>,
+    unix: UnixTransport,
+    require_http2: bool,
+    jar: Arc<CookieJar>,
+    wire: Option<Arc<WireLog>>,
+    retry: RetryPolicy,
+    attempts: Arc<AttemptLog>,
+}
+
+/// A client that trusts no certificates, for when settings fail to build.
+impl Default for Client {
+    fn default() -> Client {
+        let timeline = Arc::new(Timeline::default());
+        let tls = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        let resolver = DnsSettings::default().resolver(timeline.clone());
+        Client {
+            http: reqwest::Client::default(),
+            direct: direct_client(resolver, tls, HttpVersion::default(), timeline),
+            proxy: ProxySettings::default(),
+            timeout: None,
+            unix: UnixTransport::default(),
+            require_http2: false,
+            jar: Arc::default(),
+            wire: None,
+            retry: RetryPolicy::default(),
+            attempts: Arc::default(),
+        }
+    }
+}
+
+impl Client {
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.http.request(method, url)
+    }
+
+    /// The TCP client, for requests that never go over a Unix socket such as
+    /// OAuth token requests.
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// The Unix socket a request to `url` is sent over, if any.
+    pub fn unix_socket(&self, url: &Url) -> Option<PathBuf> {
+        self.unix.target(url).ok().flatten().map(|(socket, _)| socket)
+    }
+
+    /// Adds the headers every request carries unless it sets its own.
+    pub fn prepare(&self, request: &mut Request) {
+        let headers = request.headers_mut();
+        headers.entry(ACCEPT).or_insert(HeaderValue::from_static("*/*"));
+        headers.entry(ACCEPT_ENCODING).or_insert(HeaderValue::from_static(compression::ACCEPT_ENCODING));
+        headers.entry(USER_AGENT).or_insert(HeaderValue::from_static(DEFAULT_USER_AGENT));
+    }
+
+    /// A prepared `request` as it will be sent, including the Host,
+    /// Content-Length and Cookie headers that are only added while sending.
+    pub fn outgoing(&self, request: &Request) -> Message {
+        let url = match self.unix.target(request.url()) {
+            Ok(Some((_, url))) => url,
+            _ => request.url().clone(),
+        };
+        let mut wire = HeaderMap::new();
+        if !request.headers().contains_key(HOST)
+            && let Some(host) = url.host_str()
+        {
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            };
+            if let Ok(value) = HeaderValue::from_str(&host) {
+                wire.insert(HOST, value);
+            }
+        }
+        for (name, value) in request.headers() {
+            wire.append(name, value.clone());
+        }
+        let body = request.body().and_then(|body| body.as_bytes()).map(<[u8]>::to_vec);
+        if let Some(body) = &body
+            && !wire.contains_key(CONTENT_LENGTH)
+        {
+            wire.insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+        }
+        if !wire.contains_key(COOKIE)
+            && let Some(cookies) = self.jar.cookies(&url)
+        {
+            wire.insert(COOKIE, cookies);
+        }
+
+        let target = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        Message::Request {
+            method: request.method().clone(),
+            target,
+            headers: wire,
+            body,
+        }
+    }
+
+    /// Sends `request`, trying again after transient failures and retryable
+    /// statuses as the retry policy allows. Every attempt is logged.
+    pub async fn execute(&self, mut request: Request) -> Result<Response, SendError> {
+        let mut number = 1;
+        loop {
+            // Streaming bodies cannot be cloned, so such requests are sent once.
+            let retry = if number <= self.retry.retries && self.retry.allows(request.method()) {
+                request.try_clone()
+            } else {
+                None
+            };
+            let url = request.url().clone();
+            let start = Instant::now();
+            let result = self.send(request).await;
+            let duration = start.elapsed();
+
+            let retry_in = match (&retry, &result) {
+                (None, _) => None,
+                (Some(_), Ok(response)) if self.retry.retries_status(response) => {
+                    Some(self.retry.delay(number, Some(response)))
+                }
+                (Some(_), Err(e)) if e.is_transient() => Some(self.retry.delay(number, None)),
+                _ => None,
+            };
+            let outcome = match &result {
+                Ok(response) => format!("{:?} {}", response.version(), response.status()),
+                Err(e) => e.to_string(),
+            };
+            self.attempts.record(Attempt { url, number, outcome, duration, retry_in });
+
+            match (retry, retry_in) {
+                (Some(next), Some(delay)) => {
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                    number += 1;
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    async fn send(&self, mut request: Request) -> Result<Response, SendError> {
+        self.prepare(&mut request);
+        if let Some(wire) = &self.wire {
+            wire.record(self.outgoing(&request));
+        }
+
+        // Streaming bodies cannot be handed to hyper, so they go out with
+        // reqwest too.
+        let streaming = request.body().is_some_and(|body| body.as_bytes().is_none());
+        let response = match self.unix.target(request.url()).map_err(SendError::Unix)? {
+            Some((socket, url)) => self.unix.execute(&socket, url, request).await.map_err(SendError::Unix)?,
+            None if streaming || matches!(self.proxy.route(request.url()), Route::Proxy { .. }) => {
+                self.http.execute(request).await?
+            }
+            None => self.send_direct(request).await?,
+        };
+        if let Some(wire) = &self.wire {
+            wire.record(Message::Response {
+                version: response.version(),
+                status: response.status(),
+                headers: response.headers().clone(),
+            });
+        }
+        if self.require_http2 && response.version() != reqwest::Version::HTTP_2 {
+            return Err(SendError::NotHttp2(response.url().clone(), response.version()));
+        }
+        Ok(response)
+    }
+
+    /// Sends `request` with the client's own connector. Cookies and the
+    /// timeout are applied here the way reqwest applies them.
+    async fn send_direct(&self, mut request: Request) -> Result<Response, SendError> {
+        let url = request.url().clone();
+        let body = match request.body_mut().take().and_then(|body| body.as_bytes().map(<[u8]>::to_vec)) {
+            Some(body) => hyper::Body::from(body),
+            None => hyper::Body::empty(),
+        };
+        let mut outgoing = hyper::Request::new(body);
+        *outgoing.method_mut() = request.method().clone();
+        *outgoing.uri_mut() = url.as_str().parse().map_err(|_| SendError::InvalidUrl(url.clone()))?;
+        let headers = outgoing.headers_mut();
+        *headers = request.headers().clone();
+        if !headers.contains_key(COOKIE)
+            && let Some(cookies) = self.jar.cookies(&url)
+        {
+            headers.insert(COOKIE, cookies);
+        }
+
+        let deadline = self.timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        let sending = self.direct.request(outgoing);
+        let response = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, sending)
+                .await
+                .map_err(|_| SendError::Timeout(url.clone(), self.timeout.unwrap_or_default()))?,
+            None => sending.await,
+        }
+        .map_err(|e| SendError::Connection(url.clone(), e))?;
+
+        let mut set_cookies = response.headers().get_all(SET_COOKIE).iter();
+        self.jar.set_cookies(&mut set_cookies, &url);
+
+        let (mut parts, body) = response.into_parts();
+        let body = match deadline {
+            Some(deadline) => with_deadline(body, deadline),
+            None => body,
+        };
+        // reqwest reads the URL from the extensions, next to the remote
+        // address hyper left there.
+        if let Ok(marked) = hyper::Response::builder().url(url).body(()) {
+            parts.extensions.extend(marked.into_parts().0.extensions);
+        }
+        Ok(Response::from(hyper::Response::from_parts(parts, body)))
+    }
+}
+
+/// `body`, failing once `deadline` passes before it is read to the end.
+fn with_deadline(body: hyper::Body, deadline: tokio::time::Instant) -> hyper::Body {
+    use hyper::body::HttpBody;
+
+    let chunks = futures_util::stream::unfold(Some(body), move |body| async move {
+        let mut body = body?;
+        match tokio::time::timeout_at(deadline, body.data()).await {
+            Ok(Some(Ok(chunk))) => Some((Ok(chunk), Some(body))),
+            Ok(Some(Err(e))) => Some((Err(io::Error::other(e)), None)),
+            Ok(None) => None,
+            Err(_) => Some((Err(io::Error::new(io::ErrorKind::TimedOut, "operation timed out")), None)),
+        }
+    });
+    hyper::Body::wrap_stream(chunks)
+}
+
+/// Counts the requests carried by each connection. A connection is new when
+/// the request's host was just resolved, since resolution only happens when a
+/// connection is opened; IP literals are never resolved, so their connections
+/// cannot be told apart.
+#[derive(Debug, Default)]
+pub struct ConnectionTracker(HashMap<SocketAddr, usize>);
+
+impl ConnectionTracker {
+    /// Records a response received directly from `remote` and returns how
+    /// many requests its connection has carried, if known.
+    pub fn record(&mut self, url: &Url, remote: Option<SocketAddr>, resolutions: &[Resolution]) -> Option<usize> {
+        let remote = remote?;
+        let host = url.host_str()?.trim_matches(|c| c == '[' || c == ']').to_ascii_lowercase();
+        if host.parse::<IpAddr>().is_ok() {
+            return None;
+        }
+
+        let requests = self.0.entry(remote).or_default();
+        if resolutions.iter().any(|resolution| resolution.host == host) {
+            *requests = 0;
+        }
+        *requests += 1;
+        Some(*requests)
+    }
+}
+
+/// The id of the stream that most likely carried the `requests`th request on
+/// an HTTP/2 connection. Client-initiated streams have odd ids, one per
+/// request, so this only holds while requests on the connection go out in
+/// order.
+pub fn estimated_stream_id(requests: usize) -> usize {
+    requests.max(1) * 2 - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_request, reply, Server};
+    use tokio::io::AsyncWriteExt;
+
+    fn resolved(host: &str) -> Vec<Resolution> {
+        vec![Resolution { host: host.to_string(), addresses: vec!["10.0.0.1".parse().unwrap()], source: "system".to_string() }]
+    }
+
+    #[test]
+    fn counts_requests_per_connection() {
+        let mut tracker = ConnectionTracker::default();
+        let url = Url::parse("https://API.example.com/a").unwrap();
+        let remote: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        assert_eq!(tracker.record(&url, Some(remote), &resolved("api.example.com")), Some(1));
+        assert_eq!(tracker.record(&url, Some(remote), &[]), Some(2));
+        assert_eq!(tracker.record(&url, Some(remote), &[]), Some(3));
+        // A fresh resolution means a fresh connection.
+        assert_eq!(tracker.record(&url, Some(remote), &resolved("api.example.com")), Some(1));
+
+        let other: SocketAddr = "10.0.0.2:443".parse().unwrap();
+        assert_eq!(tracker.record(&url, Some(other), &[]), Some(1));
+        assert_eq!(tracker.record(&url, None, &[]), None);
+    }
+
+    #[test]
+    fn ignores_ip_literal_hosts() {
+        let mut tracker = ConnectionTracker::default();
+        let remote: SocketAddr = "[::1]:8080".parse().unwrap();
+        for url in ["http://127.0.0.1:8080/", "http://[::1]:8080/"] {
+            assert_eq!(tracker.record(&Url::parse(url).unwrap(), Some(remote), &[]), None);
+        }
+    }
+
+    #[test]
+    fn estimates_odd_stream_ids() {
+        assert_eq!(estimated_stream_id(1), 1);
+        assert_eq!(estimated_stream_id(2), 3);
+        assert_eq!(estimated_stream_id(10), 19);
+        assert_eq!(estimated_stream_id(0), 1);
+    }
+
+    /// Accepts connections, reads the request and sends `head` without ever
+    /// finishing the response.
+    async fn stalling_server(head: &'static [u8]) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    read_request(&mut stream).await;
+                    let _ = stream.write_all(head).await;
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                });
+            }
+        });
+        addr
+    }
+
+    fn client_with_timeout(timeout: Duration) -> Client {
+        let settings = ClientSettings { timeout: Some(timeout), ..ClientSettings::default() };
+        settings.build(Arc::new(CookieJar::new())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn times_out_waiting_for_headers() {
+        let addr = stalling_server(b"").await;
+        let client = client_with_timeout(Duration::from_millis(100));
+        let request = client.request(Method::GET, &format!("http://{}/", addr)).build().unwrap();
+        let error = client.execute(request).await.unwrap_err();
+        assert!(matches!(error, SendError::Timeout(_, timeout) if timeout == Duration::from_millis(100)));
+        assert!(error.is_transient());
+    }
+
+    #[tokio::test]
+    async fn times_out_reading_the_body() {
+        let addr = stalling_server(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\npart").await;
+        let client = client_with_timeout(Duration::from_millis(100));
+        let request = client.request(Method::GET, &format!("http://{}/", addr)).build().unwrap();
+        let response = client.execute(request).await.unwrap();
+        assert_eq!(response.status(), 200);
+        let error = response.bytes().await.unwrap_err();
+        let mut source = error.source();
+        while let Some(cause) = source.and_then(|e| e.source()) {
+            source = Some(cause);
+        }
+        assert_eq!(source.unwrap().to_string(), "operation timed out");
+    }
+
+    #[tokio::test]
+    async fn sends_directly_with_cookies() {
+        let server = Server::start(vec![
+            reply(200, &[("Set-Cookie", "session=abc; Path=/")], b"first"),
+            reply(200, &[], b"second"),
+        ])
+        .await;
+        let client = ClientSettings::default().build(Arc::new(CookieJar::new())).unwrap();
+        for expected in ["first", "second"] {
+            let request = client.request(Method::POST, &server.url("/items")).body("{}").build().unwrap();
+            let response = client.execute(request).await.unwrap();
+            assert_eq!(response.url().as_str(), server.url("/items"));
+            assert_eq!(response.remote_addr(), Some(server.addr));
+            assert_eq!(response.text().await.unwrap(), expected);
+        }
+
+        let received = server.received();
+        assert_eq!(received[0].request_line(), "POST /items HTTP/1.1");
+        assert_eq!(received[0].header("content-length"), Some("2"));
+        assert_eq!(received[0].header("user-agent"), Some(DEFAULT_USER_AGENT));
+        assert_eq!(received[0].body, b"{}");
+        assert_eq!(received[0].header("cookie"), None);
+        assert_eq!(received[1].header("cookie"), Some("session=abc"));
+    }
+
+    #[tokio::test]
+    async fn reports_refused_connections() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let client = ClientSettings::default().build(Arc::new(CookieJar::new())).unwrap();
+        let request = client.request(Method::GET, &format!("http://{}/", addr)).build().unwrap();
+        let error = client.execute(request).await.unwrap_err();
+        assert!(matches!(error, SendError::Connection(..)));
+        assert!(error.is_transient());
+        assert!(error.to_string().starts_with(&format!("error sending request for url (http://{}/): error trying to connect", addr)));
+    }
+}
@@ -0,0 +1,331 @@
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_ENCODING};
+use reqwest::Request;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// Sent with every request; responses are decoded here rather than by
+/// reqwest so the original encoding and size stay visible.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// Largest decoded body accepted, so a small compressed response cannot
+/// exhaust memory.
+const MAX_DECODED_SIZE: u64 = 1 << 30;
+
+#[derive(Debug)]
+pub enum CompressionError {
+    Unsupported(String),
+    Decode(Encoding, io::Error),
+    /// The body decodes to more than the given number of bytes.
+    TooLarge(Encoding, u64),
+    Encode(Encoding, io::Error),
+    StreamingBody,
+}
+
+impl fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressionError::Unsupported(name) => {
+                write!(f, "Unsupported Content-Encoding '{}'. Use --raw to keep the body as received", name)
+            }
+            CompressionError::Decode(encoding, e) => {
+                write!(f, "Could not decode {} response body: {}. Use --raw to keep the body as received", encoding, e)
+            }
+            CompressionError::TooLarge(encoding, limit) => {
+                write!(f, "The {} response body decodes to more than {} bytes. Use --raw to keep the body as received", encoding, limit)
+            }
+            CompressionError::Encode(encoding, e) => {
+                write!(f, "Could not compress the request body with {}: {}", encoding, e)
+            }
+            CompressionError::StreamingBody => {
+                write!(f, "Streaming request bodies cannot be compressed")
+            }
+        }
+    }
+}
+
+impl Error for CompressionError {}
+
+/// Content codings this client understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+    #[value(name = "br", alias = "brotli")]
+    Brotli,
+    Zstd,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+        })
+    }
+}
+
+impl Encoding {
+    fn parse(name: &str) -> Result<Option<Encoding>, CompressionError> {
+        match name.to_ascii_lowercase().as_str() {
+            "" | "identity" => Ok(None),
+            "gzip" | "x-gzip" => Ok(Some(Encoding::Gzip)),
+            "deflate" => Ok(Some(Encoding::Deflate)),
+            "br" => Ok(Some(Encoding::Brotli)),
+            "zstd" => Ok(Some(Encoding::Zstd)),
+            _ => Err(CompressionError::Unsupported(name.to_string())),
+        }
+    }
+
+    /// Decodes `data`, reading at most one byte past `limit` so oversized
+    /// bodies show up as longer than `limit` without being read in full.
+    fn decode(&self, data: &[u8], limit: u64) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        let limit = limit.saturating_add(1);
+        match self {
+            Encoding::Gzip => {
+                GzDecoder::new(data).take(limit).read_to_end(&mut decoded)?;
+            }
+            // Meant to be zlib-wrapped, but some servers send raw deflate.
+            Encoding::Deflate => {
+                if ZlibDecoder::new(data).take(limit).read_to_end(&mut decoded).is_err() {
+                    decoded.clear();
+                    DeflateDecoder::new(data).take(limit).read_to_end(&mut decoded)?;
+                }
+            }
+            Encoding::Brotli => {
+                brotli::Decompressor::new(data, 4096).take(limit).read_to_end(&mut decoded)?;
+            }
+            Encoding::Zstd => {
+                zstd::stream::read::Decoder::new(data)?.take(limit).read_to_end(&mut decoded)?;
+            }
+        }
+        Ok(decoded)
+    }
+
+    fn encode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Brotli => {
+                let mut encoded = Vec::new();
+                brotli::CompressorWriter::new(&mut encoded, 4096, 5, 22).write_all(data)?;
+                Ok(encoded)
+            }
+            Encoding::Zstd => zstd::encode_all(data, 0),
+        }
+    }
+}
+
+/// A response body after undoing its Content-Encoding.
+#[derive(Debug, Clone, Default)]
+pub struct Body {
+    pub bytes: Vec<u8>,
+    /// Codings in the order the server applied them; empty when the body
+    /// was sent as is or kept encoded.
+    pub encodings: Vec<Encoding>,
+    /// Size as received.
+    pub wire_size: usize,
+    /// Whether the bytes were kept with a Content-Encoding still applied,
+    /// which makes them binary whatever the Content-Type says.
+    pub encoded: bool,
+}
+
+impl Body {
+    /// Decodes `bytes` as described by the response's Content-Encoding, or
+    /// keeps them as received when `raw` is set.
+    pub fn decode(headers: &HeaderMap, bytes: Vec<u8>, raw: bool) -> Result<Body, CompressionError> {
+        Body::decode_within(headers, bytes, raw, MAX_DECODED_SIZE)
+    }
+
+    fn decode_within(headers: &HeaderMap, bytes: Vec<u8>, raw: bool, limit: u64) -> Result<Body, CompressionError> {
+        let wire_size = bytes.len();
+        if bytes.is_empty() {
+            return Ok(Body { bytes, wire_size, ..Body::default() });
+        }
+        if raw {
+            let encoded = headers.get_all(CONTENT_ENCODING).iter().any(|value| {
+                let names = value.to_str().unwrap_or("?");
+                names.split(',').any(|name| !matches!(Encoding::parse(name.trim()), Ok(None)))
+            });
+            return Ok(Body { bytes, encodings: Vec::new(), wire_size, encoded });
+        }
+
+        let mut encodings = Vec::new();
+        for value in headers.get_all(CONTENT_ENCODING) {
+            let value = value.to_str().map_err(|_| CompressionError::Unsupported(format!("{:?}", value)))?;
+            for name in value.split(',') {
+                encodings.extend(Encoding::parse(name.trim())?);
+            }
+        }
+
+        // The last coding listed was applied last, so it is undone first.
+        let mut bytes = bytes;
+        for encoding in encodings.iter().rev() {
+            bytes = encoding.decode(&bytes, limit).map_err(|e| CompressionError::Decode(*encoding, e))?;
+            if bytes.len() as u64 > limit {
+                return Err(CompressionError::TooLarge(*encoding, limit));
+            }
+        }
+        Ok(Body { bytes, encodings, wire_size, encoded: false })
+    }
+
+    /// `1234 bytes received, 5678 bytes decoded (gzip)`, or `None` when the
+    /// body was not encoded.
+    pub fn summary(&self) -> Option<String> {
+        if self.encodings.is_empty() {
+            return None;
+        }
+        let names: Vec<String> = self.encodings.iter().map(Encoding::to_string).collect();
+        Some(format!(
+            "{} bytes received, {} bytes decoded ({})",
+            self.wire_size,
+            self.bytes.len(),
+            names.join(", ")
+        ))
+    }
+}
+
+/// Compresses the body of `request` and labels it with Content-Encoding.
+/// Returns the sizes before and after, or `None` when there is no body.
+pub fn compress_body(request: &mut Request, encoding: Encoding) -> Result<Option<(usize, usize)>, CompressionError> {
+    let Some(body) = request.body() else {
+        return Ok(None);
+    };
+    let plain = body.as_bytes().ok_or(CompressionError::StreamingBody)?;
+    let compressed = encoding.encode(plain).map_err(|e| CompressionError::Encode(encoding, e))?;
+    let sizes = (plain.len(), compressed.len());
+
+    *request.body_mut() = Some(compressed.into());
+    if let Ok(value) = HeaderValue::from_str(&encoding.to_string()) {
+        request.headers_mut().insert(CONTENT_ENCODING, value);
+    }
+    Ok(Some(sizes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Method;
+
+    const TEXT: &[u8] = b"{\"message\": \"hello hello hello hello hello\"}";
+    const ALL: [Encoding; 4] = [Encoding::Gzip, Encoding::Deflate, Encoding::Brotli, Encoding::Zstd];
+
+    fn encoded(names: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_str(names).unwrap());
+        headers
+    }
+
+    #[test]
+    fn round_trips_every_encoding() {
+        for encoding in ALL {
+            let compressed = encoding.encode(TEXT).unwrap();
+            let body = Body::decode(&encoded(&encoding.to_string()), compressed.clone(), false).unwrap();
+            assert_eq!(body.bytes, TEXT, "{}", encoding);
+            assert_eq!(body.encodings, [encoding]);
+            assert_eq!(body.wire_size, compressed.len());
+            assert_eq!(
+                body.summary().unwrap(),
+                format!("{} bytes received, {} bytes decoded ({})", compressed.len(), TEXT.len(), encoding)
+            );
+        }
+    }
+
+    #[test]
+    fn undoes_stacked_encodings_in_reverse() {
+        let bytes = Encoding::Brotli.encode(&Encoding::Gzip.encode(TEXT).unwrap()).unwrap();
+        let body = Body::decode(&encoded("x-gzip, br"), bytes, false).unwrap();
+        assert_eq!(body.bytes, TEXT);
+        assert_eq!(body.encodings, [Encoding::Gzip, Encoding::Brotli]);
+    }
+
+    #[test]
+    fn accepts_raw_deflate() {
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(TEXT).unwrap();
+        let body = Body::decode(&encoded("deflate"), encoder.finish().unwrap(), false).unwrap();
+        assert_eq!(body.bytes, TEXT);
+    }
+
+    #[test]
+    fn keeps_raw_and_identity_bodies() {
+        let compressed = Encoding::Gzip.encode(TEXT).unwrap();
+        let raw = Body::decode(&encoded("gzip"), compressed.clone(), true).unwrap();
+        assert_eq!(raw.bytes, compressed);
+        assert!(raw.summary().is_none());
+        assert!(raw.encoded);
+        // Unknown codings are kept as received too.
+        assert!(Body::decode(&encoded("identity, compress"), TEXT.to_vec(), true).unwrap().encoded);
+        for raw in [true, false] {
+            let identity = Body::decode(&encoded("identity"), TEXT.to_vec(), raw).unwrap();
+            assert_eq!(identity.bytes, TEXT);
+            assert!(identity.encodings.is_empty());
+            assert!(!identity.encoded);
+        }
+    }
+
+    #[test]
+    fn reports_bad_bodies() {
+        assert!(matches!(
+            Body::decode(&encoded("compress"), TEXT.to_vec(), false),
+            Err(CompressionError::Unsupported(name)) if name == "compress"
+        ));
+        assert!(matches!(
+            Body::decode(&encoded("gzip"), TEXT.to_vec(), false),
+            Err(CompressionError::Decode(Encoding::Gzip, _))
+        ));
+    }
+
+    #[test]
+    fn refuses_bodies_past_the_limit() {
+        let bomb = vec![0u8; 64 * 1024];
+        for encoding in ALL {
+            let compressed = encoding.encode(&bomb).unwrap();
+            let headers = encoded(&encoding.to_string());
+            let error = Body::decode_within(&headers, compressed.clone(), false, 1024).unwrap_err();
+            assert!(matches!(error, CompressionError::TooLarge(e, 1024) if e == encoding), "{}", encoding);
+            let body = Body::decode_within(&headers, compressed, false, bomb.len() as u64).unwrap();
+            assert_eq!(body.bytes.len(), bomb.len());
+        }
+    }
+
+    #[test]
+    fn compresses_request_bodies() {
+        for encoding in ALL {
+            let mut request = Request::new(Method::POST, "http://example.com/".parse().unwrap());
+            *request.body_mut() = Some(TEXT.to_vec().into());
+            let (plain, compressed) = compress_body(&mut request, encoding).unwrap().unwrap();
+            assert_eq!(plain, TEXT.len());
+            let sent = request.body().unwrap().as_bytes().unwrap().to_vec();
+            assert_eq!(sent.len(), compressed);
+            assert_eq!(request.headers()[CONTENT_ENCODING], encoding.to_string().as_str());
+            assert_eq!(Body::decode(request.headers(), sent, false).unwrap().bytes, TEXT);
+        }
+
+        let mut empty = Request::new(Method::GET, "http://example.com/".parse().unwrap());
+        assert!(compress_body(&mut empty, Encoding::Gzip).unwrap().is_none());
+        assert!(!empty.headers().contains_key(CONTENT_ENCODING));
+    }
+
+    #[test]
+    fn names_encodings_on_the_command_line() {
+        use clap::ValueEnum;
+        let names: Vec<String> =
+            Encoding::value_variants().iter().map(|e| e.to_possible_value().unwrap().get_name().to_string()).collect();
+        assert_eq!(names, ["gzip", "deflate", "br", "zstd"]);
+        assert_eq!(Encoding::from_str("brotli", false).unwrap(), Encoding::Brotli);
+    }
+}
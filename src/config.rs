@@ -0,0 +1,99 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::dns::DnsConfig;
+use crate::oauth::OAuthConfig;
+use crate::proxy::ProxyConfig;
+use crate::signing::{HmacConfig, JwtConfig, RequestSigner};
+use crate::tls::TlsConfig;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_json::Error),
+    UnknownEnvironment(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => {
+                write!(f, "Could not read '{}': {}", path.display(), e)
+            }
+            ConfigError::Parse(path, e) => {
+                write!(f, "Invalid config file '{}': {}", path.display(), e)
+            }
+            ConfigError::UnknownEnvironment(name) => {
+                write!(f, "Unknown environment: '{}'", name)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Settings applied to every request made with `--env <name>`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Environment {
+    pub oauth: Option<OAuthConfig>,
+    pub hmac: Option<HmacConfig>,
+    pub jwt: Option<JwtConfig>,
+    pub proxy: Option<ProxyConfig>,
+    pub dns: Option<DnsConfig>,
+    pub tls: Option<TlsConfig>,
+}
+
+impl Environment {
+    /// The HMAC and JWT signing steps, in the order they are applied.
+    pub fn signers(&self) -> Vec<RequestSigner> {
+        let hmac = self.hmac.clone().map(RequestSigner::Hmac);
+        let jwt = self.jwt.clone().map(RequestSigner::Jwt);
+        hmac.into_iter().chain(jwt).collect()
+    }
+}
+
+/// Directory holding `environments.json` and cached state. Defaults to the
+/// platform config dir and can be moved with `HTTP_CLIENT_HOME`.
+pub fn config_dir() -> PathBuf {
+    if let Some(home) = std::env::var_os("HTTP_CLIENT_HOME") {
+        return PathBuf::from(home);
+    }
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("http-client")
+}
+
+/// Writes `bytes` to `path`, creating its directory, in a file only the
+/// owner can read. Used for files holding credentials or tokens.
+pub fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // `mode` only applies when the file is created.
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(bytes)
+}
+
+pub fn load_environment(name: &str) -> Result<Environment, ConfigError> {
+    let path = config_dir().join("environments.json");
+    let text = std::fs::read_to_string(&path).map_err(|e| ConfigError::Io(path.clone(), e))?;
+    let mut environments: HashMap<String, Environment> =
+        serde_json::from_str(&text).map_err(|e| ConfigError::Parse(path, e))?;
+
+    environments
+        .remove(name)
+        .ok_or_else(|| ConfigError::UnknownEnvironment(name.to_string()))
+}
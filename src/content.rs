@@ -0,0 +1,195 @@
+/// Bytes looked at when sniffing a body.
+const SNIFF_LENGTH: usize = 1024;
+
+/// Well-known file signatures, for naming bodies without a useful
+/// Content-Type.
+const SIGNATURES: [(&[u8], &str); 11] = [
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"%PDF-", "PDF document"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"\x1f\x8b", "gzip data"),
+    (b"\x28\xb5\x2f\xfd", "zstd data"),
+    (b"\0asm", "WebAssembly module"),
+    (b"SQLite format 3\0", "SQLite database"),
+    (b"\x7fELF", "ELF executable"),
+];
+
+/// The media type of a Content-Type value, lowercased and without parameters.
+pub fn media_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase()
+}
+
+/// The value of `name` in a header such as `text/html; charset="utf-8"`.
+pub fn parameter(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|parameter| {
+        let (key, value) = parameter.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn is_text_type(media_type: &str) -> bool {
+    media_type.starts_with("text/")
+        || media_type.ends_with("+json")
+        || media_type.ends_with("+xml")
+        || matches!(
+            media_type,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/ecmascript"
+                | "application/x-www-form-urlencoded"
+                | "application/yaml"
+                | "application/x-yaml"
+                | "application/graphql"
+                | "application/x-ndjson"
+        )
+}
+
+fn is_binary_type(media_type: &str) -> bool {
+    ["image/", "audio/", "video/", "font/"].iter().any(|prefix| media_type.starts_with(prefix))
+        || matches!(
+            media_type,
+            "application/pdf"
+                | "application/zip"
+                | "application/gzip"
+                | "application/zstd"
+                | "application/wasm"
+                | "application/protobuf"
+                | "application/x-protobuf"
+                | "application/vnd.google.protobuf"
+                | "application/grpc"
+                | "application/msgpack"
+                | "application/x-msgpack"
+                | "application/cbor"
+        )
+}
+
+/// The file type `bytes` start with, if recognised.
+pub fn signature(bytes: &[u8]) -> Option<&'static str> {
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map(|(_, name)| *name)
+}
+
+/// Whether a body is binary: a textual Content-Type is trusted, as is a
+/// known binary one; anything else, including the catch-all
+/// `application/octet-stream`, is sniffed for file signatures, NUL bytes and
+/// invalid UTF-8.
+pub fn is_binary(content_type: &str, bytes: &[u8]) -> bool {
+    let media_type = media_type(content_type);
+    if is_text_type(&media_type) {
+        return false;
+    }
+    if is_binary_type(&media_type) {
+        return true;
+    }
+
+    let sample = &bytes[..bytes.len().min(SNIFF_LENGTH)];
+    if signature(sample).is_some() || sample.contains(&0) {
+        return true;
+    }
+    // A character cut off at the end of the sample is not an error.
+    matches!(std::str::from_utf8(sample), Err(e) if e.error_len().is_some())
+}
+
+/// `12345 bytes, image/png (PNG image)`, for binary bodies that are not shown.
+pub fn describe(content_type: &str, bytes: &[u8]) -> String {
+    let media_type = media_type(content_type);
+    let mut description = format!("{} bytes", bytes.len());
+    if !media_type.is_empty() {
+        description.push_str(&format!(", {}", media_type));
+    }
+    if let Some(name) = signature(bytes) {
+        description.push_str(&format!(" ({})", name));
+    }
+    description
+}
+
+/// `xxd`-style dump: offset, `per_line` bytes in hex, grouped by eight, and
+/// their printable ASCII.
+pub fn hex_dump(bytes: &[u8], per_line: usize) -> String {
+    let mut dump = String::new();
+    for (index, chunk) in bytes.chunks(per_line).enumerate() {
+        let mut hex = String::new();
+        for position in 0..per_line {
+            match chunk.get(position) {
+                Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+                None => hex.push_str("   "),
+            }
+            if position % 8 == 7 && position + 1 < per_line {
+                hex.push(' ');
+            }
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+            .collect();
+        dump.push_str(&format!("{:08x}  {} |{}|\n", index * per_line, hex, ascii));
+    }
+    dump
+}
+
+/// Width of a `hex_dump` line.
+pub fn hex_line_width(per_line: usize) -> usize {
+    8 + 2 + per_line * 3 + (per_line - 1) / 8 + 1 + per_line + 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_media_types_and_parameters() {
+        assert_eq!(media_type("Application/JSON; charset=UTF-8"), "application/json");
+        assert_eq!(media_type(""), "");
+        assert_eq!(parameter("text/html; Charset=\"ISO-8859-1\"", "charset").as_deref(), Some("ISO-8859-1"));
+        assert_eq!(parameter("text/html", "charset"), None);
+    }
+
+    #[test]
+    fn trusts_telling_content_types() {
+        assert!(!is_binary("application/json", b"\x1f\x8b\0\0"));
+        assert!(!is_binary("text/plain; charset=latin1", b"caf\xe9"));
+        assert!(!is_binary("application/vnd.api+json", b"{}"));
+        assert!(is_binary("image/svg", b"<svg/>"));
+        assert!(is_binary("application/x-protobuf", b"plain"));
+    }
+
+    #[test]
+    fn sniffs_everything_else() {
+        assert!(is_binary("application/octet-stream", b"\x89PNG\r\n\x1a\nrest"));
+        assert!(is_binary("", b"text\0with a NUL"));
+        assert!(is_binary("", b"caf\xe9 au lait"));
+        assert!(!is_binary("application/octet-stream", "plain text, caf\u{e9}".as_bytes()));
+        // A multi-byte character cut off by the sample is still text.
+        let mut cut = vec![b'a'; SNIFF_LENGTH - 1];
+        cut.extend_from_slice("\u{e9}".as_bytes());
+        assert!(!is_binary("", &cut));
+    }
+
+    #[test]
+    fn names_binary_bodies() {
+        assert_eq!(signature(b"\x28\xb5\x2f\xfd..."), Some("zstd data"));
+        assert_eq!(signature(b"{}"), None);
+        assert_eq!(describe("image/png", b"\x89PNG\r\n\x1a\n"), "8 bytes, image/png (PNG image)");
+        assert_eq!(describe("", b"\x1f\x8b\x08"), "3 bytes (gzip data)");
+    }
+
+    #[test]
+    fn dumps_hex_like_xxd() {
+        let dump = hex_dump(b"Hello, world!\n\x00\xff\x10tail", 16);
+        assert_eq!(
+            dump,
+            "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 0a 00 ff  |Hello, world!...|\n\
+             00000010  10 74 61 69 6c                                    |.tail|\n"
+        );
+        assert!(dump.lines().next().unwrap().len() <= hex_line_width(16));
+        assert_eq!(hex_dump(b"", 16), "");
+    }
+}
@@ -0,0 +1,379 @@
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore, RawCookie};
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::RwLock;
+
+#[derive(Debug)]
+pub enum CookieError {
+    Io(std::io::Error),
+    InvalidLine(usize, String),
+    InvalidCookie(String),
+}
+
+impl fmt::Display for CookieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieError::Io(e) => {
+                write!(f, "Cookie file error: {}", e)
+            }
+            CookieError::InvalidLine(line, text) => {
+                write!(f, "Invalid cookies.txt line {}: '{}'", line, text)
+            }
+            CookieError::InvalidCookie(text) => {
+                write!(f, "Invalid cookie: '{}'", text)
+            }
+        }
+    }
+}
+
+impl Error for CookieError {}
+
+impl From<std::io::Error> for CookieError {
+    fn from(error: std::io::Error) -> Self {
+        CookieError::Io(error)
+    }
+}
+
+/// A stored cookie in a form that can be listed, edited and persisted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CookieEntry {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    /// Sent only to `domain` itself, not its subdomains.
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// Unix timestamp; `None` for session cookies.
+    pub expires: Option<i64>,
+}
+
+impl CookieEntry {
+    /// Parses `name=value; Domain=example.com; Path=/; Secure; HttpOnly`.
+    /// Without a Domain attribute the cookie is bound to `default_domain`.
+    pub fn parse(spec: &str, default_domain: Option<&str>) -> Result<CookieEntry, CookieError> {
+        let raw = RawCookie::parse(spec.trim()).map_err(|_| CookieError::InvalidCookie(spec.to_string()))?;
+        let (domain, host_only) = match (raw.domain(), default_domain) {
+            (Some(domain), _) => (domain.to_string(), false),
+            (None, Some(domain)) => (domain.to_string(), true),
+            (None, None) => return Err(CookieError::InvalidCookie(spec.to_string())),
+        };
+
+        Ok(CookieEntry {
+            name: raw.name().to_string(),
+            value: raw.value().to_string(),
+            domain,
+            host_only,
+            path: raw.path().unwrap_or("/").to_string(),
+            secure: raw.secure().unwrap_or(false),
+            http_only: raw.http_only().unwrap_or(false),
+            expires: raw.expires_datetime().map(|at| at.unix_timestamp()),
+        })
+    }
+
+    /// The inverse of `parse`, used to pre-fill the TUI cookie editor.
+    pub fn to_spec(&self) -> String {
+        let mut spec = format!("{}={}", self.name, self.value);
+        if !self.host_only {
+            spec.push_str(&format!("; Domain={}", self.domain));
+        }
+        spec.push_str(&format!("; Path={}", self.path));
+        if self.secure {
+            spec.push_str("; Secure");
+        }
+        if self.http_only {
+            spec.push_str("; HttpOnly");
+        }
+        spec
+    }
+
+    fn to_raw(&self) -> Result<RawCookie<'static>, CookieError> {
+        let mut builder = RawCookie::build(self.name.clone(), self.value.clone())
+            .path(self.path.clone())
+            .secure(self.secure)
+            .http_only(self.http_only);
+        if !self.host_only {
+            builder = builder.domain(self.domain.clone());
+        }
+        if let Some(expires) = self.expires {
+            let at = time::OffsetDateTime::from_unix_timestamp(expires)
+                .map_err(|_| CookieError::InvalidCookie(self.to_spec()))?;
+            builder = builder.expires(at);
+        }
+        Ok(builder.finish())
+    }
+
+    /// A URL the cookie could have been set from, as the store requires one.
+    fn origin(&self) -> Result<Url, CookieError> {
+        let host = self.domain.trim_start_matches('.');
+        Url::parse(&format!("https://{}{}", host, self.path))
+            .map_err(|_| CookieError::InvalidCookie(self.to_spec()))
+    }
+}
+
+/// Cookie jar shared by every request of a CLI invocation or TUI session.
+#[derive(Debug, Default)]
+pub struct CookieJar(RwLock<CookieStore>);
+
+impl CookieJar {
+    pub fn new() -> Self {
+        CookieJar::default()
+    }
+
+    pub fn with_entries(entries: &[CookieEntry]) -> Result<Self, CookieError> {
+        let jar = CookieJar::new();
+        for entry in entries {
+            jar.insert(entry)?;
+        }
+        Ok(jar)
+    }
+
+    /// Unexpired cookies, sorted by domain, path and name.
+    pub fn entries(&self) -> Vec<CookieEntry> {
+        let store = self.0.read().unwrap();
+        let mut entries: Vec<CookieEntry> = store
+            .iter_unexpired()
+            .map(|cookie| {
+                let (domain, host_only) = match &cookie.domain {
+                    CookieDomain::HostOnly(domain) => (domain.clone(), true),
+                    CookieDomain::Suffix(domain) => (domain.clone(), false),
+                    _ => (String::new(), true),
+                };
+                CookieEntry {
+                    name: cookie.name().to_string(),
+                    value: cookie.value().to_string(),
+                    domain,
+                    host_only,
+                    path: cookie.path.to_string(),
+                    secure: cookie.secure().unwrap_or(false),
+                    http_only: cookie.http_only().unwrap_or(false),
+                    expires: match &cookie.expires {
+                        CookieExpiration::AtUtc(at) => Some(at.unix_timestamp()),
+                        CookieExpiration::SessionEnd => None,
+                    },
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+        entries
+    }
+
+    pub fn insert(&self, entry: &CookieEntry) -> Result<(), CookieError> {
+        let raw = entry.to_raw()?;
+        let url = entry.origin()?;
+        self.0
+            .write()
+            .unwrap()
+            .insert_raw(&raw, &url)
+            .map_err(|_| CookieError::InvalidCookie(entry.to_spec()))?;
+        Ok(())
+    }
+
+    pub fn remove(&self, entry: &CookieEntry) {
+        self.0
+            .write()
+            .unwrap()
+            .remove(&entry.domain, &entry.path, &entry.name);
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| RawCookie::parse(value.to_string()).ok());
+        self.0.write().unwrap().store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .0
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&header).ok()
+        }
+    }
+}
+
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// Reads a Netscape/curl `cookies.txt` file.
+pub fn read_netscape(path: &Path) -> Result<Vec<CookieEntry>, CookieError> {
+    let text = std::fs::read_to_string(path)?;
+    let mut entries = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(CookieError::InvalidLine(index + 1, line.to_string()));
+        }
+        let expires: i64 = fields[4]
+            .trim()
+            .parse()
+            .map_err(|_| CookieError::InvalidLine(index + 1, line.to_string()))?;
+
+        entries.push(CookieEntry {
+            name: fields[5].to_string(),
+            value: fields[6].to_string(),
+            domain: fields[0].trim_start_matches('.').to_string(),
+            host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+            path: fields[2].to_string(),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            http_only,
+            expires: (expires != 0).then_some(expires),
+        });
+    }
+    Ok(entries)
+}
+
+/// Writes cookies in Netscape/curl `cookies.txt` format.
+pub fn write_netscape(path: &Path, entries: &[CookieEntry]) -> Result<(), CookieError> {
+    let mut text = String::from("# Netscape HTTP Cookie File\n");
+    for entry in entries {
+        let domain = if entry.host_only {
+            entry.domain.clone()
+        } else {
+            format!(".{}", entry.domain)
+        };
+        let bool_field = |value: bool| if value { "TRUE" } else { "FALSE" };
+        text.push_str(&format!(
+            "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            if entry.http_only { HTTP_ONLY_PREFIX } else { "" },
+            domain,
+            bool_field(!entry.host_only),
+            entry.path,
+            bool_field(entry.secure),
+            entry.expires.unwrap_or(0),
+            entry.name,
+            entry.value
+        ));
+    }
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore as _;
+
+    /// Far enough ahead that the cookie stays unexpired.
+    const EXPIRES: i64 = 4_102_444_800;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("http-client-cookies-{}-{}.txt", std::process::id(), name))
+    }
+
+    fn entry(name: &str, domain: &str, host_only: bool) -> CookieEntry {
+        CookieEntry {
+            name: name.to_string(),
+            value: format!("{}-value", name),
+            domain: domain.to_string(),
+            host_only,
+            path: "/".to_string(),
+            secure: false,
+            http_only: false,
+            expires: Some(EXPIRES),
+        }
+    }
+
+    #[test]
+    fn cookies_txt_round_trip() {
+        let entries = vec![
+            entry("session", "example.com", true),
+            CookieEntry { http_only: true, secure: true, path: "/api".to_string(), ..entry("token", "example.com", false) },
+            CookieEntry { expires: None, ..entry("temp", "other.org", false) },
+        ];
+        let path = temp_path("round-trip");
+        write_netscape(&path, &entries).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let read = read_netscape(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read, entries);
+        assert!(text.starts_with("# Netscape HTTP Cookie File\n"));
+        assert!(text.contains("example.com\tFALSE\t/\tFALSE\t4102444800\tsession\tsession-value\n"));
+        assert!(text.contains("#HttpOnly_.example.com\tTRUE\t/api\tTRUE\t4102444800\ttoken\ttoken-value\n"));
+        assert!(text.contains(".other.org\tTRUE\t/\tFALSE\t0\ttemp\ttemp-value\n"));
+    }
+
+    #[test]
+    fn reads_curl_files_and_reports_bad_lines() {
+        let path = temp_path("curl");
+        std::fs::write(&path, "# comment\n\n.example.com\tTRUE\t/\tTRUE\t0\tid\tabc\n").unwrap();
+        let read = read_netscape(&path).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!((read[0].domain.as_str(), read[0].host_only, read[0].secure), ("example.com", false, true));
+
+        std::fs::write(&path, "example.com\tFALSE\t/\tFALSE\tsoon\tid\tabc\n").unwrap();
+        let error = read_netscape(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(error, CookieError::InvalidLine(1, _)));
+    }
+
+    #[test]
+    fn parses_cookie_specs() {
+        let entry = CookieEntry::parse("id=1; Domain=example.com; Path=/api; Secure; HttpOnly", None).unwrap();
+        assert_eq!((entry.domain.as_str(), entry.host_only, entry.path.as_str()), ("example.com", false, "/api"));
+        assert!(entry.secure && entry.http_only);
+        assert_eq!(entry.to_spec(), "id=1; Domain=example.com; Path=/api; Secure; HttpOnly");
+
+        let entry = CookieEntry::parse("id=1", Some("host.example.com")).unwrap();
+        assert!(entry.host_only);
+        assert_eq!(entry.to_spec(), "id=1; Path=/");
+        assert!(CookieEntry::parse("id=1", None).is_err());
+    }
+
+    #[test]
+    fn jar_sends_matching_cookies() {
+        let jar = CookieJar::with_entries(&[
+            entry("host", "example.com", true),
+            entry("suffix", "example.com", false),
+            CookieEntry { secure: true, ..entry("secure", "example.com", true) },
+        ])
+        .unwrap();
+        let cookies = |url: &str| jar.cookies(&url.parse().unwrap()).map(|value| value.to_str().unwrap().to_string());
+
+        let https = cookies("https://example.com/").unwrap();
+        assert!(https.contains("host=host-value") && https.contains("suffix=suffix-value") && https.contains("secure="));
+        assert!(!cookies("http://example.com/").unwrap().contains("secure="));
+        assert_eq!(cookies("https://api.example.com/").unwrap(), "suffix=suffix-value");
+        assert_eq!(cookies("https://other.org/"), None);
+    }
+
+    #[test]
+    fn jar_stores_response_cookies() {
+        let jar = CookieJar::new();
+        let url: Url = "https://example.com/login".parse().unwrap();
+        let headers = [HeaderValue::from_static("sid=42; Path=/; HttpOnly"), HeaderValue::from_static("gone=1; Max-Age=0")];
+        jar.set_cookies(&mut headers.iter(), &url);
+
+        let entries = jar.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].name.as_str(), entries[0].value.as_str()), ("sid", "42"));
+        assert!(entries[0].http_only && entries[0].host_only);
+
+        jar.remove(&entries[0]);
+        assert!(jar.entries().is_empty());
+    }
+}
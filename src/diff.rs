@@ -0,0 +1,636 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::charset;
+use crate::content;
+use crate::format::{self, Format};
+use crate::protobuf::ProtobufSettings;
+use crate::serialization::Serialization;
+use crate::tree::{self, Segment};
+
+/// Headers that differ on every response, left out of comparisons unless
+/// asked for.
+pub const VOLATILE_HEADERS: [&str; 1] = ["date"];
+
+/// Line pairs compared when diffing text; larger middles are shown as
+/// replaced as a whole.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Unchanged lines shown around changed ones.
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Debug)]
+pub enum DiffError {
+    Read(PathBuf, io::Error),
+    Write(PathBuf, io::Error),
+    Json(PathBuf, serde_json::Error),
+}
+
+impl fmt::Display for DiffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffError::Read(path, e) => write!(f, "Could not read '{}': {}", path.display(), e),
+            DiffError::Write(path, e) => write!(f, "Could not write '{}': {}", path.display(), e),
+            DiffError::Json(path, e) => write!(f, "Could not save '{}': {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for DiffError {}
+
+/// A response reduced to what is compared: status, headers with lowercase
+/// names and repeated values joined, and the decoded body. Saved with
+/// `--save` as pretty JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    #[serde(flatten)]
+    pub body: SnapshotBody,
+}
+
+/// JSON bodies, including decoded MessagePack, CBOR and Protobuf, are kept
+/// as JSON and other text pretty-printed. Binary bodies are described with
+/// a hash of their bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", content = "body", rename_all = "lowercase")]
+pub enum SnapshotBody {
+    Json(Value),
+    Text(String),
+    Binary(String),
+}
+
+impl Snapshot {
+    pub fn from_response(
+        status: u16,
+        headers: &[(String, String)],
+        bytes: &[u8],
+        protobuf: &ProtobufSettings,
+    ) -> Snapshot {
+        let mut map: BTreeMap<String, String> = BTreeMap::new();
+        for (name, value) in headers {
+            map.entry(name.to_ascii_lowercase())
+                .and_modify(|joined| {
+                    joined.push_str(", ");
+                    joined.push_str(value);
+                })
+                .or_insert_with(|| value.clone());
+        }
+        let content_type = map.get("content-type").cloned().unwrap_or_default();
+        Snapshot { status: Some(status), headers: Some(map), body: SnapshotBody::decode(&content_type, bytes, protobuf) }
+    }
+
+    /// Reads a file saved with `--save`. Any other file is a body on its own,
+    /// with no status or headers to compare.
+    pub fn load(path: &Path, protobuf: &ProtobufSettings) -> Result<Snapshot, DiffError> {
+        let bytes = fs::read(path).map_err(|e| DiffError::Read(path.to_path_buf(), e))?;
+        if let Ok(snapshot) = serde_json::from_slice::<Snapshot>(&bytes)
+            && snapshot.status.is_some()
+        {
+            return Ok(snapshot);
+        }
+        let content_type = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => "application/json",
+            Some("msgpack") => "application/msgpack",
+            Some("cbor") => "application/cbor",
+            Some("pb") => "application/x-protobuf",
+            _ => "",
+        };
+        Ok(Snapshot { status: None, headers: None, body: SnapshotBody::decode(content_type, &bytes, protobuf) })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), DiffError> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| DiffError::Json(path.to_path_buf(), e))?;
+        fs::write(path, text + "\n").map_err(|e| DiffError::Write(path.to_path_buf(), e))
+    }
+
+    pub fn without_headers(mut self, ignored: &[String]) -> Snapshot {
+        if let Some(headers) = &mut self.headers {
+            headers.retain(|name, _| !ignored.iter().any(|ignored| ignored.eq_ignore_ascii_case(name)));
+        }
+        self
+    }
+
+    /// Status line, headers and body as text, with object keys sorted so
+    /// that their order does not show up as a difference.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        if let Some(status) = self.status {
+            text.push_str(&format!("HTTP {}\n", status));
+        }
+        for (name, value) in self.headers.iter().flatten() {
+            text.push_str(&format!("{}: {}\n", name, value));
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&self.body.render());
+        text
+    }
+}
+
+impl SnapshotBody {
+    fn decode(content_type: &str, bytes: &[u8], protobuf: &ProtobufSettings) -> SnapshotBody {
+        if let Some(Ok(tree)) = Serialization::from_content_type(content_type)
+            .map(|format| format.decode(bytes, content_type, protobuf))
+        {
+            return SnapshotBody::Json(tree.value);
+        }
+        if content::is_binary(content_type, bytes) {
+            let hash = Sha256::digest(bytes);
+            return SnapshotBody::Binary(format!("{}, sha256 {:x}", content::describe(content_type, bytes), hash));
+        }
+        let text = charset::decode(bytes, content_type, None).text;
+        match Format::detect(content_type, &text) {
+            Format::Json => match serde_json::from_str(&text) {
+                Ok(value) => SnapshotBody::Json(value),
+                Err(_) => SnapshotBody::Text(text),
+            },
+            format => SnapshotBody::Text(format::pretty_print(format, &text)),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        match self {
+            SnapshotBody::Json(value) => {
+                serde_json::to_string_pretty(&sorted(value)).unwrap_or_else(|_| value.to_string())
+            }
+            SnapshotBody::Text(text) => text.clone(),
+            SnapshotBody::Binary(description) => format!("(binary body: {})", description),
+        }
+    }
+}
+
+/// The value with the keys of every object in alphabetical order.
+pub fn sorted(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(entries.into_iter().map(|(key, value)| (key.clone(), sorted(value))).collect::<Map<_, _>>())
+        }
+        Value::Array(values) => Value::Array(values.iter().map(sorted).collect()),
+        value => value.clone(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(Value),
+    Removed(Value),
+    Changed(Value, Value),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added(value) => write!(f, "{}", value),
+            Change::Removed(value) => write!(f, "{}", value),
+            Change::Changed(old, new) => write!(f, "{} -> {}", old, new),
+        }
+    }
+}
+
+impl Change {
+    fn sign(&self) -> char {
+        match self {
+            Change::Added(_) => '+',
+            Change::Removed(_) => '-',
+            Change::Changed(..) => '~',
+        }
+    }
+}
+
+/// Added, removed and changed values by path. Object keys are matched by
+/// name, so their order does not matter; array items by position.
+pub fn diff_json(old: &Value, new: &Value) -> Vec<(Vec<Segment>, Change)> {
+    let mut changes = Vec::new();
+    walk(old, new, &mut Vec::new(), &mut changes);
+    changes
+}
+
+fn walk(old: &Value, new: &Value, path: &mut Vec<Segment>, changes: &mut Vec<(Vec<Segment>, Change)>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                path.push(Segment::Key(key.clone()));
+                match new.get(key) {
+                    Some(new_value) => walk(old_value, new_value, path, changes),
+                    None => changes.push((path.clone(), Change::Removed(old_value.clone()))),
+                }
+                path.pop();
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                path.push(Segment::Key(key.clone()));
+                changes.push((path.clone(), Change::Added(new_value.clone())));
+                path.pop();
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                path.push(Segment::Index(index));
+                match (old.get(index), new.get(index)) {
+                    (Some(old_value), Some(new_value)) => walk(old_value, new_value, path, changes),
+                    (Some(old_value), None) => changes.push((path.clone(), Change::Removed(old_value.clone()))),
+                    (None, Some(new_value)) => changes.push((path.clone(), Change::Added(new_value.clone()))),
+                    (None, None) => {}
+                }
+                path.pop();
+            }
+        }
+        (old, new) if old != new => changes.push((path.clone(), Change::Changed(old.clone(), new.clone()))),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The lines of `new` compared with `old`: a longest common subsequence
+/// after the lines both start and end with.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<LineChange<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut lines: Vec<LineChange> = old[..prefix].iter().map(|line| LineChange::Same(line)).collect();
+    if old_middle.len().saturating_mul(new_middle.len()) > MAX_DIFF_CELLS {
+        lines.extend(old_middle.iter().map(|line| LineChange::Removed(line)));
+        lines.extend(new_middle.iter().map(|line| LineChange::Added(line)));
+    } else {
+        lines.extend(common_subsequence(old_middle, new_middle));
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|line| LineChange::Same(line)));
+    lines
+}
+
+fn common_subsequence<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<LineChange<'a>> {
+    // lengths[i][j]: longest common subsequence of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(LineChange::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            lines.push(LineChange::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(LineChange::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Rows for showing two texts next to each other. Runs of removed and added
+/// lines are paired up; unchanged rows have the same line on both sides.
+pub fn side_by_side<'a>(lines: &[LineChange<'a>]) -> Vec<(Option<&'a str>, Option<&'a str>)> {
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let flush = |removed: &mut Vec<&'a str>, added: &mut Vec<&'a str>, rows: &mut Vec<_>| {
+        for index in 0..removed.len().max(added.len()) {
+            rows.push((removed.get(index).copied(), added.get(index).copied()));
+        }
+        removed.clear();
+        added.clear();
+    };
+    for line in lines {
+        match line {
+            LineChange::Removed(line) => removed.push(*line),
+            LineChange::Added(line) => added.push(*line),
+            LineChange::Same(line) => {
+                flush(&mut removed, &mut added, &mut rows);
+                rows.push((Some(*line), Some(*line)));
+            }
+        }
+    }
+    flush(&mut removed, &mut added, &mut rows);
+    rows
+}
+
+/// How two responses differ.
+#[derive(Debug)]
+pub struct Difference {
+    pub status: Option<(u16, u16)>,
+    pub headers: Vec<(String, Change)>,
+    pub body: BodyDifference,
+}
+
+#[derive(Debug)]
+pub enum BodyDifference {
+    /// Both bodies are JSON.
+    Json(Vec<(Vec<Segment>, Change)>),
+    /// Lines of the bodies as text, for other bodies.
+    Text { old: String, new: String },
+}
+
+impl Difference {
+    /// Status and headers are only compared when both sides have them.
+    pub fn new(old: &Snapshot, new: &Snapshot) -> Difference {
+        let status = match (old.status, new.status) {
+            (Some(old), Some(new)) if old != new => Some((old, new)),
+            _ => None,
+        };
+        let mut headers = Vec::new();
+        if let (Some(old), Some(new)) = (&old.headers, &new.headers) {
+            let names: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for name in names {
+                let change = match (old.get(name), new.get(name)) {
+                    (Some(old), Some(new)) if old != new => Change::Changed(old.as_str().into(), new.as_str().into()),
+                    (Some(old), None) => Change::Removed(old.as_str().into()),
+                    (None, Some(new)) => Change::Added(new.as_str().into()),
+                    _ => continue,
+                };
+                headers.push((name.clone(), change));
+            }
+        }
+        let body = match (&old.body, &new.body) {
+            (SnapshotBody::Json(old), SnapshotBody::Json(new)) => BodyDifference::Json(diff_json(old, new)),
+            (old, new) => BodyDifference::Text { old: old.render(), new: new.render() },
+        };
+        Difference { status, headers, body }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none()
+            && self.headers.is_empty()
+            && match &self.body {
+                BodyDifference::Json(changes) => changes.is_empty(),
+                BodyDifference::Text { old, new } => old == new,
+            }
+    }
+
+    /// `status, 2 headers and 3 body values`, for titles.
+    pub fn summary(&self) -> String {
+        let count = |count: usize, noun: &str| format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" });
+        let mut parts = Vec::new();
+        if self.status.is_some() {
+            parts.push("status".to_string());
+        }
+        if !self.headers.is_empty() {
+            parts.push(count(self.headers.len(), "header"));
+        }
+        match &self.body {
+            BodyDifference::Json(changes) if !changes.is_empty() => parts.push(count(changes.len(), "body value")),
+            BodyDifference::Text { old, new } if old != new => {
+                let changed = diff_lines(old, new).iter().filter(|line| !matches!(line, LineChange::Same(_))).count();
+                parts.push(count(changed, "body line"));
+            }
+            _ => {}
+        }
+        match parts.as_slice() {
+            [] => "no differences".to_string(),
+            [part] if part == "status" || part.starts_with("1 ") => format!("{} differs", part),
+            _ => format!("{} differ", parts.join(", ")),
+        }
+    }
+}
+
+/// One line per change, `+` added, `-` removed and `~` changed; text bodies
+/// as changed lines with a little context.
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        if let Some((old, new)) = self.status {
+            writeln!(f, "Status: {} -> {}", old, new)?;
+        }
+        if !self.headers.is_empty() {
+            writeln!(f, "Headers:")?;
+            for (name, change) in &self.headers {
+                let value = match change {
+                    Change::Changed(Value::String(old), Value::String(new)) => format!("{} -> {}", old, new),
+                    Change::Added(Value::String(value)) | Change::Removed(Value::String(value)) => value.clone(),
+                    change => change.to_string(),
+                };
+                writeln!(f, " {} {}: {}", change.sign(), name, value)?;
+            }
+        }
+        match &self.body {
+            BodyDifference::Json(changes) if !changes.is_empty() => {
+                writeln!(f, "Body:")?;
+                for (path, change) in changes {
+                    writeln!(f, " {} {}: {}", change.sign(), tree::json_path(path), change)?;
+                }
+            }
+            BodyDifference::Text { old, new } if old != new => {
+                writeln!(f, "Body:")?;
+                write_hunks(f, &diff_lines(old, new))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Changed lines with `CONTEXT_LINES` around them, each run headed by the
+/// line numbers it starts at.
+fn write_hunks(f: &mut fmt::Formatter<'_>, lines: &[LineChange]) -> fmt::Result {
+    let changed: Vec<usize> =
+        lines.iter().enumerate().filter(|(_, line)| !matches!(line, LineChange::Same(_))).map(|(index, _)| index).collect();
+    let shown = |index: usize| {
+        changed.iter().any(|changed| index + CONTEXT_LINES >= *changed && index <= changed + CONTEXT_LINES)
+    };
+    let (mut old_line, mut new_line) = (1, 1);
+    let mut in_hunk = false;
+    for (index, line) in lines.iter().enumerate() {
+        if shown(index) {
+            if !in_hunk {
+                writeln!(f, " @@ -{} +{} @@", old_line, new_line)?;
+                in_hunk = true;
+            }
+            match line {
+                LineChange::Same(text) => writeln!(f, "   {}", text)?,
+                LineChange::Removed(text) => writeln!(f, " - {}", text)?,
+                LineChange::Added(text) => writeln!(f, " + {}", text)?,
+            }
+        } else {
+            in_hunk = false;
+        }
+        match line {
+            LineChange::Same(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            LineChange::Removed(_) => old_line += 1,
+            LineChange::Added(_) => new_line += 1,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn changes(old: Value, new: Value) -> Vec<(String, Change)> {
+        diff_json(&old, &new).into_iter().map(|(path, change)| (tree::json_path(&path), change)).collect()
+    }
+
+    fn snapshot(status: u16, headers: &[(&str, &str)], body: &str) -> Snapshot {
+        let headers: Vec<(String, String)> =
+            headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        Snapshot::from_response(status, &headers, body.as_bytes(), &ProtobufSettings::default())
+    }
+
+    #[test]
+    fn diffs_json_by_path() {
+        assert_eq!(
+            changes(json!({"a": 1, "b": {"c": [1, 2]}, "gone": true}), json!({"b": {"c": [1, 3, 4]}, "a": 1, "new": null})),
+            [
+                ("$.b.c[1]".to_string(), Change::Changed(json!(2), json!(3))),
+                ("$.b.c[2]".to_string(), Change::Added(json!(4))),
+                ("$.gone".to_string(), Change::Removed(json!(true))),
+                ("$.new".to_string(), Change::Added(json!(null))),
+            ]
+        );
+        assert_eq!(changes(json!([1, 2]), json!([1])), [("$[1]".to_string(), Change::Removed(json!(2)))]);
+        assert_eq!(
+            changes(json!({"a": [1]}), json!({"a": {"0": 1}})),
+            [("$.a".to_string(), Change::Changed(json!([1]), json!({"0": 1})))]
+        );
+        assert_eq!(changes(json!(1), json!(1.0)), [("$".to_string(), Change::Changed(json!(1), json!(1.0)))]);
+        assert!(changes(json!({"x": {"y": [null]}}), json!({"x": {"y": [null]}})).is_empty());
+        assert_eq!(Change::Changed(json!("a"), json!(2)).to_string(), r#""a" -> 2"#);
+    }
+
+    #[test]
+    fn diffs_lines_around_a_common_start_and_end() {
+        use LineChange::*;
+        assert_eq!(
+            diff_lines("a\nb\nc\nd", "a\nx\nc\nd\ne"),
+            [Same("a"), Removed("b"), Added("x"), Same("c"), Same("d"), Added("e")]
+        );
+        assert_eq!(diff_lines("a\nb", "a\nb"), [Same("a"), Same("b")]);
+        assert_eq!(diff_lines("", "a"), [Added("a")]);
+        assert_eq!(diff_lines("a\nb\nc", "c\na"), [Removed("a"), Removed("b"), Same("c"), Added("a")]);
+        // Repeated lines keep the longest common run.
+        assert_eq!(
+            diff_lines("x\ny\nx\ny", "y\nx\ny"),
+            [Removed("x"), Same("y"), Same("x"), Same("y")]
+        );
+    }
+
+    #[test]
+    fn replaces_large_middles_as_a_whole() {
+        let old = (0..2100).map(|line| format!("old {}", line)).collect::<Vec<_>>().join("\n");
+        let new = (0..2100).map(|line| format!("new {}", line)).collect::<Vec<_>>().join("\n");
+        let lines = diff_lines(&old, &new);
+        assert_eq!(lines.len(), 4200);
+        assert!(lines[..2100].iter().all(|line| matches!(line, LineChange::Removed(_))));
+        assert!(lines[2100..].iter().all(|line| matches!(line, LineChange::Added(_))));
+    }
+
+    #[test]
+    fn pairs_changed_lines_side_by_side() {
+        let lines = diff_lines("a\nb\nc\nd", "a\nx\ny\nd");
+        assert_eq!(
+            side_by_side(&lines),
+            [(Some("a"), Some("a")), (Some("b"), Some("x")), (Some("c"), Some("y")), (Some("d"), Some("d"))]
+        );
+        let lines = diff_lines("a", "a\nb\nc");
+        assert_eq!(side_by_side(&lines), [(Some("a"), Some("a")), (None, Some("b")), (None, Some("c"))]);
+    }
+
+    #[test]
+    fn reduces_responses_to_snapshots() {
+        let response = snapshot(
+            200,
+            &[("Content-Type", "application/json"), ("Set-Cookie", "a=1"), ("set-cookie", "b=2")],
+            r#"{"b": 1, "a": [true]}"#,
+        );
+        assert_eq!(response.headers.as_ref().unwrap()["set-cookie"], "a=1, b=2");
+        assert_eq!(response.body, SnapshotBody::Json(json!({"a": [true], "b": 1})));
+        assert_eq!(
+            response.render(),
+            "HTTP 200\ncontent-type: application/json\nset-cookie: a=1, b=2\n\n{\n  \"a\": [\n    true\n  ],\n  \"b\": 1\n}"
+        );
+        let response = response.without_headers(&["Set-Cookie".to_string()]);
+        assert_eq!(response.headers.unwrap().len(), 1);
+
+        let text = snapshot(200, &[("Content-Type", "text/plain")], "hello\n");
+        assert_eq!(text.body, SnapshotBody::Text("hello\n".to_string()));
+        let binary = snapshot(200, &[("Content-Type", "image/png")], "\u{89}PNG\r\n\u{1a}\n");
+        assert!(matches!(&binary.body, SnapshotBody::Binary(description) if description.contains("sha256 ")));
+    }
+
+    #[test]
+    fn saves_and_loads_snapshots() {
+        let directory = std::env::temp_dir().join(format!("http-client-diff-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let saved = directory.join("saved.json");
+        let response = snapshot(201, &[("Content-Type", "application/json")], r#"{"id": 7}"#);
+        response.save(&saved).unwrap();
+        assert_eq!(Snapshot::load(&saved, &ProtobufSettings::default()).unwrap(), response);
+
+        // Other files are a body alone.
+        let plain = directory.join("body.json");
+        fs::write(&plain, r#"{"id": 8}"#).unwrap();
+        let loaded = Snapshot::load(&plain, &ProtobufSettings::default()).unwrap();
+        assert_eq!((loaded.status, loaded.headers, loaded.body), (None, None, SnapshotBody::Json(json!({"id": 8}))));
+        assert!(matches!(
+            Snapshot::load(&directory.join("missing.json"), &ProtobufSettings::default()),
+            Err(DiffError::Read(..))
+        ));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn describes_differences() {
+        let old = snapshot(200, &[("Content-Type", "application/json"), ("ETag", "1")], r#"{"a": 1, "b": 2}"#);
+        let new = snapshot(404, &[("Content-Type", "application/json"), ("X-Cache", "HIT")], r#"{"a": 3, "b": 2}"#);
+        let difference = Difference::new(&old, &new);
+        assert_eq!(difference.summary(), "status, 2 headers, 1 body value differ");
+        assert_eq!(
+            difference.to_string(),
+            "Status: 200 -> 404\nHeaders:\n - etag: 1\n + x-cache: HIT\nBody:\n ~ $.a: 1 -> 3\n"
+        );
+
+        let same = Difference::new(&old, &old);
+        assert!(same.is_empty());
+        assert_eq!((same.summary().as_str(), same.to_string().as_str()), ("no differences", "No differences\n"));
+
+        // A body on its own compares only the body.
+        let body = Snapshot { status: None, headers: None, body: old.body.clone() };
+        assert!(Difference::new(&body, &old).is_empty());
+    }
+
+    #[test]
+    fn shows_changed_text_lines_with_context() {
+        let old = (1..=10).map(|line| line.to_string()).collect::<Vec<_>>().join("\n");
+        let new = old.replace("5", "five");
+        let text = |body: &str| Snapshot { status: None, headers: None, body: SnapshotBody::Text(body.to_string()) };
+        let difference = Difference::new(&text(&old), &text(&new));
+        assert_eq!(difference.summary(), "2 body lines differ");
+        assert_eq!(difference.to_string(), "Body:\n @@ -3 +3 @@\n   3\n   4\n - 5\n + five\n   6\n   7\n");
+    }
+}
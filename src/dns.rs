@@ -0,0 +1,346 @@
+use hickory_resolver::config::{LookupIpStrategy, NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use crate::timing::{Event, Timeline};
+
+const DNS_PORT: u16 = 53;
+
+#[derive(Debug)]
+pub enum DnsError {
+    InvalidResolve(String),
+    InvalidServer(String),
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsError::InvalidResolve(spec) => {
+                write!(f, "Invalid --resolve value: '{}'. Use 'host:port:address[,address...]' format", spec)
+            }
+            DnsError::InvalidServer(server) => {
+                write!(f, "Invalid DNS server: '{}'. Use an IP address with an optional port", server)
+            }
+        }
+    }
+}
+
+impl Error for DnsError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl IpFamily {
+    fn matches(&self, address: &IpAddr) -> bool {
+        match self {
+            IpFamily::Ipv4 => address.is_ipv4(),
+            IpFamily::Ipv6 => address.is_ipv6(),
+        }
+    }
+}
+
+/// DNS settings of a named environment.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DnsConfig {
+    /// curl-style `host:port:address` overrides.
+    #[serde(default)]
+    pub resolve: Vec<String>,
+    pub family: Option<IpFamily>,
+    /// DNS server queried instead of the system resolver, as `ip[:port]`.
+    pub server: Option<String>,
+}
+
+/// How a host name was resolved, for verbose output.
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    pub host: String,
+    pub addresses: Vec<IpAddr>,
+    pub source: String,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addresses: Vec<String> = self.addresses.iter().map(IpAddr::to_string).collect();
+        write!(f, "{} resolved to {} ({})", self.host, addresses.join(", "), self.source)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DnsSettings {
+    overrides: HashMap<String, Vec<IpAddr>>,
+    family: Option<IpFamily>,
+    server: Option<SocketAddr>,
+    log: Arc<Mutex<Vec<Resolution>>>,
+}
+
+impl DnsSettings {
+    pub fn new(config: &DnsConfig) -> Result<DnsSettings, DnsError> {
+        let mut overrides: HashMap<String, Vec<IpAddr>> = HashMap::new();
+        for spec in &config.resolve {
+            let (host, addresses) = parse_resolve(spec)?;
+            overrides.entry(host).or_default().extend(addresses);
+        }
+
+        let server = config.server.as_deref().map(parse_server).transpose()?;
+        Ok(DnsSettings {
+            overrides,
+            family: config.family,
+            server,
+            log: Arc::default(),
+        })
+    }
+
+    /// Resolutions made since the last call.
+    pub fn take_resolutions(&self) -> Vec<Resolution> {
+        std::mem::take(&mut *self.log.lock().unwrap())
+    }
+
+    /// A resolver that reports lookup times to `timeline`.
+    pub fn resolver(&self, timeline: Arc<Timeline>) -> Resolver {
+        let lookup = self.server.map(|server| {
+            let servers = NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true);
+            let mut options = ResolverOpts::default();
+            options.ip_strategy = match self.family {
+                Some(IpFamily::Ipv4) => LookupIpStrategy::Ipv4Only,
+                Some(IpFamily::Ipv6) => LookupIpStrategy::Ipv6Only,
+                None => LookupIpStrategy::Ipv4AndIpv6,
+            };
+            TokioAsyncResolver::tokio(ResolverConfig::from_parts(None, vec![], servers), options)
+        });
+        Resolver {
+            settings: self.clone(),
+            lookup,
+            timeline,
+        }
+    }
+}
+
+/// Parses `host:port:address[,address...]`; IPv6 addresses may be bracketed.
+/// The port is accepted for curl compatibility, but overrides apply to the
+/// host on every port because name resolution never sees the port.
+fn parse_resolve(spec: &str) -> Result<(String, Vec<IpAddr>), DnsError> {
+    let invalid = || DnsError::InvalidResolve(spec.to_string());
+    let mut parts = spec.splitn(3, ':');
+    let (Some(host), Some(port), Some(addresses)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    if host.is_empty() || (port != "*" && port.parse::<u16>().is_err()) {
+        return Err(invalid());
+    }
+
+    let addresses = addresses
+        .split(',')
+        .map(|address| address.trim().trim_matches(|c| c == '[' || c == ']').parse())
+        .collect::<Result<Vec<IpAddr>, _>>()
+        .map_err(|_| invalid())?;
+    Ok((host.to_ascii_lowercase(), addresses))
+}
+
+fn parse_server(server: &str) -> Result<SocketAddr, DnsError> {
+    if let Ok(address) = server.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    let address: IpAddr = server
+        .trim_matches(|c| c == '[' || c == ']')
+        .parse()
+        .map_err(|_| DnsError::InvalidServer(server.to_string()))?;
+    Ok(SocketAddr::new(address, DNS_PORT))
+}
+
+/// Name resolution for clients: `--resolve` overrides first, then the
+/// configured DNS server or the system resolver, filtered by `-4`/`-6`.
+#[derive(Clone)]
+pub struct Resolver {
+    settings: DnsSettings,
+    lookup: Option<TokioAsyncResolver>,
+    timeline: Arc<Timeline>,
+}
+
+impl Resolve for Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+        let settings = self.settings.clone();
+        let lookup = self.lookup.clone();
+        let timeline = self.timeline.clone();
+
+        Box::pin(async move {
+            timeline.record(Event::DnsStart);
+            let (addresses, source): (Vec<IpAddr>, String) = match (settings.overrides.get(&host), &lookup) {
+                (Some(addresses), _) => (addresses.clone(), "--resolve".to_string()),
+                (None, Some(lookup)) => {
+                    let found = lookup.lookup_ip(host.as_str()).await?;
+                    let server = settings.server.map(|s| s.to_string()).unwrap_or_default();
+                    (found.iter().collect(), format!("DNS server {}", server))
+                }
+                (None, None) => {
+                    let found = tokio::net::lookup_host((host.as_str(), 0)).await?;
+                    (found.map(|address| address.ip()).collect(), "system resolver".to_string())
+                }
+            };
+
+            let addresses: Vec<IpAddr> = addresses
+                .into_iter()
+                .filter(|address| settings.family.is_none_or(|family| family.matches(address)))
+                .collect();
+            if addresses.is_empty() {
+                let family = match settings.family {
+                    Some(IpFamily::Ipv4) => "IPv4 ",
+                    Some(IpFamily::Ipv6) => "IPv6 ",
+                    None => "",
+                };
+                return Err(format!("no {}address found for {}", family, host).into());
+            }
+
+            timeline.record(Event::DnsEnd);
+            settings.log.lock().unwrap().push(Resolution {
+                host,
+                addresses: addresses.clone(),
+                source,
+            });
+            let addrs: Addrs = Box::new(addresses.into_iter().map(|address| SocketAddr::new(address, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Lets hyper's own connector resolve names the same way.
+impl Service<Name> for Resolver {
+    type Response = Addrs;
+    type Error = Box<dyn Error + Send + Sync>;
+    type Future = Resolving;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Resolving {
+        Resolve::resolve(self, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientSettings;
+    use crate::cookies::CookieJar;
+    use crate::testing::{reply, Server};
+    use std::str::FromStr;
+    use tokio::net::UdpSocket;
+
+    fn settings(resolve: &[&str], family: Option<IpFamily>, server: Option<String>) -> DnsSettings {
+        let config = DnsConfig { resolve: resolve.iter().map(|spec| spec.to_string()).collect(), family, server };
+        DnsSettings::new(&config).unwrap()
+    }
+
+    async fn resolve(settings: &DnsSettings, host: &str) -> Result<Vec<IpAddr>, String> {
+        let resolver = settings.resolver(Arc::default());
+        match resolver.resolve(Name::from_str(host).unwrap()).await {
+            Ok(addrs) => Ok(addrs.map(|addr| addr.ip()).collect()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_resolve_overrides() {
+        assert_eq!(parse_resolve("API.example.com:443:10.0.0.1").unwrap(), ("api.example.com".to_string(), vec![ip("10.0.0.1")]));
+        assert_eq!(
+            parse_resolve("example.com:*:[::1], 10.0.0.2").unwrap(),
+            ("example.com".to_string(), vec![ip("::1"), ip("10.0.0.2")])
+        );
+        assert_eq!(parse_resolve("h:80:2001:db8::1").unwrap().1, vec![ip("2001:db8::1")]);
+        for spec in ["example.com", "example.com:443", ":443:10.0.0.1", "h:http:10.0.0.1", "h:80:not-an-ip", "h:80:"] {
+            assert!(matches!(parse_resolve(spec), Err(DnsError::InvalidResolve(_))), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_dns_servers() {
+        assert_eq!(parse_server("1.1.1.1").unwrap(), "1.1.1.1:53".parse().unwrap());
+        assert_eq!(parse_server("10.0.0.1:5353").unwrap(), "10.0.0.1:5353".parse().unwrap());
+        assert_eq!(parse_server("[2606:4700::1111]:853").unwrap(), "[2606:4700::1111]:853".parse().unwrap());
+        assert_eq!(parse_server("2606:4700::1111").unwrap(), "[2606:4700::1111]:53".parse().unwrap());
+        assert!(matches!(parse_server("dns.example"), Err(DnsError::InvalidServer(_))));
+    }
+
+    #[tokio::test]
+    async fn overrides_win_and_families_filter() {
+        let dns = settings(&["api.test:443:10.0.0.1", "API.test:80:::1"], None, None);
+        assert_eq!(resolve(&dns, "api.test").await.unwrap(), [ip("10.0.0.1"), ip("::1")]);
+        let resolutions = dns.take_resolutions();
+        assert_eq!(resolutions[0].to_string(), "api.test resolved to 10.0.0.1, ::1 (--resolve)");
+        assert!(dns.take_resolutions().is_empty());
+
+        let dns = settings(&["api.test:443:10.0.0.1", "api.test:443:::1"], Some(IpFamily::Ipv6), None);
+        assert_eq!(resolve(&dns, "api.test").await.unwrap(), [ip("::1")]);
+        let dns = settings(&["api.test:443:10.0.0.1"], Some(IpFamily::Ipv6), None);
+        assert_eq!(resolve(&dns, "api.test").await.unwrap_err(), "no IPv6 address found for api.test");
+    }
+
+    /// Answers A queries with `address` and every other query with no records.
+    async fn dns_server(address: [u8; 4]) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = [0u8; 512];
+            while let Ok((length, peer)) = socket.recv_from(&mut buffer).await {
+                let query = &buffer[..length];
+                // The question: labels up to the root, then type and class.
+                let mut end = 12;
+                while query[end] != 0 {
+                    end += query[end] as usize + 1;
+                }
+                end += 5;
+                let is_a = query[end - 4..end - 2] == [0, 1];
+
+                let mut answer = query[..2].to_vec();
+                answer.extend_from_slice(&[0x81, 0x80, 0, 1, 0, is_a as u8, 0, 0, 0, 0]);
+                answer.extend_from_slice(&query[12..end]);
+                if is_a {
+                    answer.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+                    answer.extend_from_slice(&address);
+                }
+                let _ = socket.send_to(&answer, peer).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn queries_configured_server() {
+        let server = dns_server([127, 0, 0, 7]).await;
+        let dns = settings(&[], Some(IpFamily::Ipv4), Some(server.to_string()));
+        assert_eq!(resolve(&dns, "service.test").await.unwrap(), [ip("127.0.0.7")]);
+        assert_eq!(dns.take_resolutions()[0].source, format!("DNS server {}", server));
+    }
+
+    #[tokio::test]
+    async fn connects_to_overridden_address() {
+        let origin = Server::start(vec![reply(200, &[], b"ok")]).await;
+        let port = origin.addr.port();
+        let settings = ClientSettings {
+            dns: settings(&[&format!("api.test:{}:127.0.0.1", port)], None, None),
+            ..ClientSettings::default()
+        };
+        let client = settings.build(Arc::new(CookieJar::new())).unwrap();
+        let request = client.request(reqwest::Method::GET, &format!("http://api.test:{}/", port)).build().unwrap();
+        assert_eq!(client.execute(request).await.unwrap().text().await.unwrap(), "ok");
+        assert_eq!(origin.received()[0].header("host"), Some(format!("api.test:{}", port).as_str()));
+    }
+}
@@ -0,0 +1,1094 @@
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+
+use crate::json;
+
+#[derive(Debug)]
+pub enum FilterError {
+    Syntax { position: usize, message: String },
+    Type(String),
+    NotJson(String),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::Syntax { position, message } => {
+                write!(f, "Invalid filter at column {}: {}", position + 1, message)
+            }
+            FilterError::Type(message) => write!(f, "{}", message),
+            FilterError::NotJson(reason) => write!(f, "Only JSON bodies can be filtered; {}", reason),
+        }
+    }
+}
+
+impl Error for FilterError {}
+
+/// What a step does when the value does not have what it asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// jq: a missing key or index is `null`, a wrong type is an error.
+    Strict,
+    /// jq `?`: a missing key or index is `null`, a wrong type yields nothing.
+    Try,
+    /// JSONPath: anything missing yields nothing.
+    Match,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Field(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Iterate,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Select,
+    Map,
+    Has,
+    Keys,
+    Length,
+    Type,
+    First,
+    Last,
+    Sort,
+    Reverse,
+}
+
+/// A parsed filter. Like jq, every expression turns one input into any
+/// number of outputs.
+#[derive(Debug, Clone)]
+enum Expr {
+    Identity,
+    /// The whole document, `$` inside JSONPath filters.
+    Root,
+    /// The input and everything below it.
+    Recurse,
+    Literal(Value),
+    Step(Box<Expr>, Step, Access),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Comparison, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// JSONPath existence test: true when the path matches anything.
+    Exists(Box<Expr>),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(String, Expr)>),
+    Call(Function, Option<Box<Expr>>),
+}
+
+/// Pipes nest to the right, so a `..` segment is always the first half of
+/// a pipe and its descendants are read without being copied.
+fn pipe(first: Expr, second: Expr) -> Expr {
+    match first {
+        Expr::Identity => second,
+        Expr::Pipe(first, rest) => Expr::Pipe(first, Box::new(pipe(*rest, second))),
+        first => Expr::Pipe(Box::new(first), Box::new(second)),
+    }
+}
+
+/// A JSONPath expression (`$.items[?(@.price < 10)].name`) or a jq filter
+/// (`.items[] | select(.price < 10) | .name`).
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    /// Parses `text` as JSONPath when it starts with `$`, and as jq otherwise.
+    pub fn parse(text: &str) -> Result<Filter, FilterError> {
+        let mut parser = Parser { chars: text.chars().collect(), position: 0 };
+        parser.skip_whitespace();
+        let expr = if parser.peek() == Some('$') {
+            parser.json_path()?
+        } else {
+            parser.pipe()?
+        };
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(parser.error(format!("unexpected '{}'", parser.chars[parser.position])));
+        }
+        Ok(Filter { expr })
+    }
+
+    /// Every result of the filter, in order.
+    pub fn apply(&self, value: &Value) -> Result<Vec<Value>, FilterError> {
+        evaluate(&self.expr, value, value)
+    }
+}
+
+/// Results pretty printed like a JSON body, one after another.
+pub fn render(results: &[Value]) -> String {
+    results
+        .iter()
+        .map(|result| json::pretty_print_json_safe(&result.to_string()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> FilterError {
+        FilterError::Syntax { position: self.position, message: message.into() }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes `token` if the input continues with it.
+    fn eat(&mut self, token: &str) -> bool {
+        let matches = token.chars().enumerate().all(|(offset, c)| self.peek_at(offset) == Some(c));
+        if matches {
+            self.position += token.chars().count();
+        }
+        matches
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), FilterError> {
+        self.skip_whitespace();
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", token)))
+        }
+    }
+
+    /// Consumes the keyword `word` when it is not the start of a longer name.
+    fn eat_word(&mut self, word: &str) -> bool {
+        let next = self.peek_at(word.chars().count());
+        if next.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            return false;
+        }
+        self.eat(word)
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_' || (c == '-' && self.position > start)) {
+            self.position += 1;
+        }
+        (self.position > start).then(|| self.chars[start..self.position].iter().collect())
+    }
+
+    fn number(&mut self) -> Result<Value, FilterError> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+')) {
+            // A `-` only belongs to the number right after an exponent.
+            self.position += 1;
+            if matches!(self.chars[self.position - 1], 'e' | 'E') && self.peek() == Some('-') {
+                self.position += 1;
+            }
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        if text.is_empty() || text == "-" {
+            return Err(self.error("expected a number"));
+        }
+        serde_json::from_str::<Value>(&text).map_err(|_| FilterError::Syntax {
+            position: start,
+            message: format!("invalid number '{}'", text),
+        })
+    }
+
+    fn integer(&mut self) -> Result<i64, FilterError> {
+        let start = self.position;
+        match self.number()?.as_i64() {
+            Some(integer) => Ok(integer),
+            None => Err(FilterError::Syntax { position: start, message: "expected an integer".to_string() }),
+        }
+    }
+
+    /// A string in double quotes, or in single quotes as JSONPath allows,
+    /// with JSON escapes and `\'`.
+    fn string(&mut self) -> Result<String, FilterError> {
+        let start = self.position;
+        let Some(quote) = self.peek().filter(|c| matches!(c, '"' | '\'')) else {
+            return Err(self.error("expected a string"));
+        };
+        self.position += 1;
+        let mut raw = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(FilterError::Syntax { position: start, message: "unterminated string".to_string() });
+                }
+                Some(c) if c == quote => break,
+                Some('\\') => {
+                    self.position += 1;
+                    match self.peek() {
+                        Some('\'') => raw.push('\''),
+                        Some(c) => {
+                            raw.push('\\');
+                            raw.push(c);
+                        }
+                        None => continue,
+                    }
+                }
+                Some('"') => raw.push_str("\\\""),
+                Some(c) => raw.push(c),
+            }
+            self.position += 1;
+        }
+        self.position += 1;
+        serde_json::from_str(&format!("\"{}\"", raw)).map_err(|e| FilterError::Syntax {
+            position: start,
+            message: format!("invalid string: {}", e),
+        })
+    }
+
+    // jq: pipe < comma < or < and < comparison < postfix < term.
+
+    fn pipe(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.comma()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat("|") {
+                return Ok(expr);
+            }
+            expr = Expr::Pipe(Box::new(expr), Box::new(self.comma()?));
+        }
+    }
+
+    fn comma(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.or()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat(",") {
+                return Ok(expr);
+            }
+            expr = Expr::Comma(Box::new(expr), Box::new(self.or()?));
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.and()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat_word("or") {
+                return Ok(expr);
+            }
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.comparison()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat_word("and") {
+                return Ok(expr);
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.comparison()?));
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, FilterError> {
+        let left = self.postfix()?;
+        self.skip_whitespace();
+        match self.comparison_operator() {
+            Some(operator) => Ok(Expr::Compare(Box::new(left), operator, Box::new(self.postfix()?))),
+            None => Ok(left),
+        }
+    }
+
+    fn comparison_operator(&mut self) -> Option<Comparison> {
+        [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ]
+        .into_iter()
+        .find(|(token, _)| self.eat(token))
+        .map(|(_, operator)| operator)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.term()?;
+        loop {
+            // Suffixes follow without whitespace, as `.a.b` or `.a[0]`.
+            let next = self.peek_at(1);
+            if self.peek() == Some('.') && next.is_some_and(|c| c.is_alphabetic() || matches!(c, '_' | '"' | '[')) {
+                self.position += 1;
+                if self.peek() == Some('[') {
+                    continue;
+                }
+                let name = match self.peek() {
+                    Some('"') => self.string()?,
+                    _ => self.identifier().unwrap_or_default(),
+                };
+                expr = Expr::Step(Box::new(expr), Step::Field(name), Access::Strict);
+            } else if self.peek() == Some('[') {
+                expr = self.jq_bracket(expr)?;
+            } else if self.peek() == Some('?') {
+                self.position += 1;
+                if let Expr::Step(_, _, access) = &mut expr {
+                    *access = Access::Try;
+                }
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// `[]`, `[2]`, `[-1]`, `["key"]` or `[1:3]` after `expr`.
+    fn jq_bracket(&mut self, expr: Expr) -> Result<Expr, FilterError> {
+        self.position += 1;
+        self.skip_whitespace();
+        let step = match self.peek() {
+            Some(']') => Step::Iterate,
+            Some('"') => Step::Field(self.string()?),
+            Some(':') => {
+                self.position += 1;
+                self.skip_whitespace();
+                Step::Slice(None, Some(self.integer()?))
+            }
+            _ => {
+                let start = self.integer()?;
+                self.skip_whitespace();
+                if self.eat(":") {
+                    self.skip_whitespace();
+                    let end = if self.peek() == Some(']') { None } else { Some(self.integer()?) };
+                    Step::Slice(Some(start), end)
+                } else {
+                    Step::Index(start)
+                }
+            }
+        };
+        self.expect("]")?;
+        Ok(Expr::Step(Box::new(expr), step, Access::Strict))
+    }
+
+    fn term(&mut self) -> Result<Expr, FilterError> {
+        self.skip_whitespace();
+        let Some(c) = self.peek() else {
+            return Err(self.error("unexpected end of filter"));
+        };
+        match c {
+            '.' if self.peek_at(1) == Some('.') => {
+                self.position += 2;
+                Ok(Expr::Recurse)
+            }
+            '.' => {
+                // The suffix loop reads `.name`, `."name"` and `.[...]`.
+                if !self.peek_at(1).is_some_and(|c| c.is_alphabetic() || matches!(c, '_' | '"' | '[')) {
+                    self.position += 1;
+                }
+                Ok(Expr::Identity)
+            }
+            '"' => Ok(Expr::Literal(Value::String(self.string()?))),
+            '-' | '0'..='9' => Ok(Expr::Literal(self.number()?)),
+            '(' => {
+                self.position += 1;
+                let expr = self.pipe()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            '[' => {
+                self.position += 1;
+                self.skip_whitespace();
+                if self.eat("]") {
+                    return Ok(Expr::Array(None));
+                }
+                let expr = self.pipe()?;
+                self.expect("]")?;
+                Ok(Expr::Array(Some(Box::new(expr))))
+            }
+            '{' => self.object(),
+            c if c.is_alphabetic() || c == '_' => self.function(),
+            c => Err(self.error(format!("unexpected '{}'", c))),
+        }
+    }
+
+    /// `{id, name: .user.name, "full name": .name}`
+    fn object(&mut self) -> Result<Expr, FilterError> {
+        self.position += 1;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.eat("}") {
+                return Ok(Expr::Object(entries));
+            }
+            if !entries.is_empty() {
+                self.expect(",")?;
+                self.skip_whitespace();
+            }
+            let key = match self.peek() {
+                Some('"') => self.string()?,
+                _ => self.identifier().ok_or_else(|| self.error("expected a key"))?,
+            };
+            self.skip_whitespace();
+            let value = if self.eat(":") {
+                self.or()?
+            } else {
+                Expr::Step(Box::new(Expr::Identity), Step::Field(key.clone()), Access::Strict)
+            };
+            entries.push((key, value));
+        }
+    }
+
+    fn function(&mut self) -> Result<Expr, FilterError> {
+        let start = self.position;
+        let name = self.identifier().unwrap_or_default();
+        let function = match name.as_str() {
+            "true" => return Ok(Expr::Literal(Value::Bool(true))),
+            "false" => return Ok(Expr::Literal(Value::Bool(false))),
+            "null" => return Ok(Expr::Literal(Value::Null)),
+            "not" => return Ok(Expr::Not(Box::new(Expr::Identity))),
+            "select" => Function::Select,
+            "map" => Function::Map,
+            "has" => Function::Has,
+            "keys" => Function::Keys,
+            "length" => Function::Length,
+            "type" => Function::Type,
+            "first" => Function::First,
+            "last" => Function::Last,
+            "sort" => Function::Sort,
+            "reverse" => Function::Reverse,
+            _ => {
+                return Err(FilterError::Syntax { position: start, message: format!("unknown function '{}'", name) });
+            }
+        };
+        if !matches!(function, Function::Select | Function::Map | Function::Has) {
+            return Ok(Expr::Call(function, None));
+        }
+        self.expect("(")?;
+        let argument = self.pipe()?;
+        self.expect(")")?;
+        Ok(Expr::Call(function, Some(Box::new(argument))))
+    }
+
+    /// `$` followed by segments; see `segments`.
+    fn json_path(&mut self) -> Result<Expr, FilterError> {
+        self.position += 1;
+        self.segments(Expr::Identity)
+    }
+
+    /// JSONPath segments after `$` or `@`: `.name`, `.*`, `..name`, `..*`,
+    /// and brackets with names, indexes, slices, `*`, `?(...)` filters and
+    /// comma-separated unions of those.
+    fn segments(&mut self, mut expr: Expr) -> Result<Expr, FilterError> {
+        loop {
+            let descendants = self.eat("..");
+            if descendants {
+                expr = pipe(expr, Expr::Recurse);
+            } else if !self.eat(".") {
+                if self.peek() == Some('[') {
+                    expr = pipe(expr, self.path_bracket()?);
+                    continue;
+                }
+                return Ok(expr);
+            }
+
+            let selector = if self.eat("*") {
+                Expr::Step(Box::new(Expr::Identity), Step::Iterate, Access::Match)
+            } else if descendants && self.peek() == Some('[') {
+                self.path_bracket()?
+            } else {
+                let name = self.identifier().ok_or_else(|| self.error("expected a name"))?;
+                Expr::Step(Box::new(Expr::Identity), Step::Field(name), Access::Match)
+            };
+            expr = pipe(expr, selector);
+        }
+    }
+
+    fn path_bracket(&mut self) -> Result<Expr, FilterError> {
+        self.position += 1;
+        let mut union: Option<Expr> = None;
+        loop {
+            self.skip_whitespace();
+            let selector = self.path_selector()?;
+            union = Some(match union {
+                Some(union) => Expr::Comma(Box::new(union), Box::new(selector)),
+                None => selector,
+            });
+            self.skip_whitespace();
+            if !self.eat(",") {
+                break;
+            }
+        }
+        self.expect("]")?;
+        Ok(union.unwrap_or(Expr::Identity))
+    }
+
+    fn path_selector(&mut self) -> Result<Expr, FilterError> {
+        let step = |step| Expr::Step(Box::new(Expr::Identity), step, Access::Match);
+        match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Ok(step(Step::Iterate))
+            }
+            Some('\'' | '"') => Ok(step(Step::Field(self.string()?))),
+            Some('?') => {
+                self.position += 1;
+                self.skip_whitespace();
+                let parenthesized = self.eat("(");
+                let condition = self.path_or()?;
+                if parenthesized {
+                    self.expect(")")?;
+                }
+                Ok(Expr::Pipe(
+                    Box::new(step(Step::Iterate)),
+                    Box::new(Expr::Call(Function::Select, Some(Box::new(condition)))),
+                ))
+            }
+            _ => {
+                let start = if self.peek() == Some(':') { None } else { Some(self.integer()?) };
+                self.skip_whitespace();
+                if !self.eat(":") {
+                    return Ok(step(Step::Index(start.unwrap_or_default())));
+                }
+                self.skip_whitespace();
+                let end = if matches!(self.peek(), Some(']' | ',')) { None } else { Some(self.integer()?) };
+                // Each element of the slice is a match of its own.
+                Ok(Expr::Pipe(Box::new(step(Step::Slice(start, end))), Box::new(step(Step::Iterate))))
+            }
+        }
+    }
+
+    fn path_or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.path_and()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat("||") {
+                return Ok(expr);
+            }
+            expr = Expr::Or(Box::new(expr), Box::new(self.path_and()?));
+        }
+    }
+
+    fn path_and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.path_unary()?;
+        loop {
+            self.skip_whitespace();
+            if !self.eat("&&") {
+                return Ok(expr);
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.path_unary()?));
+        }
+    }
+
+    fn path_unary(&mut self) -> Result<Expr, FilterError> {
+        self.skip_whitespace();
+        if self.peek() == Some('!') && self.peek_at(1) != Some('=') {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.path_unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.path_or()?;
+            self.expect(")")?;
+            return Ok(expr);
+        }
+        let left = self.path_operand()?;
+        self.skip_whitespace();
+        match self.comparison_operator() {
+            Some(operator) => {
+                self.skip_whitespace();
+                Ok(Expr::Compare(Box::new(left), operator, Box::new(self.path_operand()?)))
+            }
+            None if matches!(left, Expr::Literal(_)) => Ok(left),
+            None => Ok(Expr::Exists(Box::new(left))),
+        }
+    }
+
+    fn path_operand(&mut self) -> Result<Expr, FilterError> {
+        match self.peek() {
+            Some('@') => {
+                self.position += 1;
+                self.segments(Expr::Identity)
+            }
+            Some('$') => {
+                self.position += 1;
+                self.segments(Expr::Root)
+            }
+            Some('\'' | '"') => Ok(Expr::Literal(Value::String(self.string()?))),
+            Some('-' | '0'..='9') => Ok(Expr::Literal(self.number()?)),
+            _ if self.eat_word("true") => Ok(Expr::Literal(Value::Bool(true))),
+            _ if self.eat_word("false") => Ok(Expr::Literal(Value::Bool(false))),
+            _ if self.eat_word("null") => Ok(Expr::Literal(Value::Null)),
+            _ => Err(self.error("expected @, $ or a value")),
+        }
+    }
+}
+
+fn evaluate(expr: &Expr, input: &Value, root: &Value) -> Result<Vec<Value>, FilterError> {
+    Ok(match expr {
+        Expr::Identity => vec![input.clone()],
+        Expr::Root => vec![root.clone()],
+        Expr::Recurse => descendants(input).into_iter().cloned().collect(),
+        Expr::Literal(value) => vec![value.clone()],
+        Expr::Step(base, step, access) if matches!(**base, Expr::Recurse) => {
+            let mut values = Vec::new();
+            for value in descendants(input) {
+                values.extend(apply_step(value, step, *access)?);
+            }
+            values
+        }
+        Expr::Step(base, step, access) => {
+            let mut values = Vec::new();
+            for value in evaluate(base, input, root)? {
+                values.extend(apply_step(&value, step, *access)?);
+            }
+            values
+        }
+        Expr::Pipe(first, second) if matches!(**first, Expr::Recurse) => {
+            let mut values = Vec::new();
+            for value in descendants(input) {
+                values.extend(evaluate(second, value, root)?);
+            }
+            values
+        }
+        Expr::Pipe(first, second) => {
+            let mut values = Vec::new();
+            for value in evaluate(first, input, root)? {
+                values.extend(evaluate(second, &value, root)?);
+            }
+            values
+        }
+        Expr::Comma(first, second) => {
+            let mut values = evaluate(first, input, root)?;
+            values.extend(evaluate(second, input, root)?);
+            values
+        }
+        Expr::Compare(left, operator, right) => {
+            let rights = evaluate(right, input, root)?;
+            let mut values = Vec::new();
+            for left in evaluate(left, input, root)? {
+                for right in &rights {
+                    let ordering = compare(&left, right);
+                    values.push(Value::Bool(match operator {
+                        Comparison::Equal => ordering == Ordering::Equal,
+                        Comparison::NotEqual => ordering != Ordering::Equal,
+                        Comparison::Less => ordering == Ordering::Less,
+                        Comparison::LessOrEqual => ordering != Ordering::Greater,
+                        Comparison::Greater => ordering == Ordering::Greater,
+                        Comparison::GreaterOrEqual => ordering != Ordering::Less,
+                    }));
+                }
+            }
+            values
+        }
+        Expr::And(left, right) | Expr::Or(left, right) => {
+            let is_and = matches!(expr, Expr::And(..));
+            let mut values = Vec::new();
+            for left in evaluate(left, input, root)? {
+                // `and` stops at a false left side, `or` at a true one.
+                if truthy(&left) != is_and {
+                    values.push(Value::Bool(!is_and));
+                    continue;
+                }
+                for right in evaluate(right, input, root)? {
+                    values.push(Value::Bool(truthy(&right)));
+                }
+            }
+            values
+        }
+        Expr::Not(operand) => evaluate(operand, input, root)?
+            .iter()
+            .map(|value| Value::Bool(!truthy(value)))
+            .collect(),
+        Expr::Exists(path) => vec![Value::Bool(!evaluate(path, input, root)?.is_empty())],
+        Expr::Array(None) => vec![Value::Array(Vec::new())],
+        Expr::Array(Some(items)) => vec![Value::Array(evaluate(items, input, root)?)],
+        Expr::Object(entries) => {
+            // One object per combination of entry values.
+            let mut objects = vec![Map::new()];
+            for (key, value) in entries {
+                let values = evaluate(value, input, root)?;
+                let mut combined = Vec::new();
+                for object in &objects {
+                    for value in &values {
+                        let mut object = object.clone();
+                        object.insert(key.clone(), value.clone());
+                        combined.push(object);
+                    }
+                }
+                objects = combined;
+            }
+            objects.into_iter().map(Value::Object).collect()
+        }
+        Expr::Call(function, argument) => call(*function, argument.as_deref(), input, root)?,
+    })
+}
+
+fn apply_step(value: &Value, step: &Step, access: Access) -> Result<Vec<Value>, FilterError> {
+    let missing = || if access == Access::Match { Vec::new() } else { vec![Value::Null] };
+    Ok(match (step, value) {
+        (Step::Field(name), Value::Object(map)) => map.get(name).cloned().map_or_else(missing, |value| vec![value]),
+        (Step::Index(index), Value::Array(items)) => {
+            let position = if *index < 0 { items.len() as i64 + index } else { *index };
+            usize::try_from(position)
+                .ok()
+                .and_then(|position| items.get(position))
+                .cloned()
+                .map_or_else(missing, |value| vec![value])
+        }
+        (Step::Slice(start, end), Value::Array(items)) => {
+            let (start, end) = slice_bounds(*start, *end, items.len());
+            vec![Value::Array(items[start..end].to_vec())]
+        }
+        (Step::Slice(start, end), Value::String(text)) => {
+            let chars: Vec<char> = text.chars().collect();
+            let (start, end) = slice_bounds(*start, *end, chars.len());
+            vec![Value::String(chars[start..end].iter().collect())]
+        }
+        (Step::Iterate, Value::Array(items)) => items.clone(),
+        (Step::Iterate, Value::Object(map)) => map.values().cloned().collect(),
+        (Step::Field(_) | Step::Index(_) | Step::Slice(..), Value::Null) => missing(),
+        _ if access != Access::Strict => Vec::new(),
+        (Step::Field(name), value) => {
+            return Err(FilterError::Type(format!("Cannot index {} with \"{}\"", type_name(value), name)));
+        }
+        (Step::Index(_) | Step::Slice(..), value) => {
+            return Err(FilterError::Type(format!("Cannot index {} with a number", type_name(value))));
+        }
+        (Step::Iterate, value) => {
+            return Err(FilterError::Type(format!("Cannot iterate over {}", type_name(value))));
+        }
+    })
+}
+
+/// Python-style bounds: negative values count from the end, and anything
+/// out of range is clamped.
+fn slice_bounds(start: Option<i64>, end: Option<i64>, length: usize) -> (usize, usize) {
+    let resolve = |bound: i64| {
+        let bound = if bound < 0 { length as i64 + bound } else { bound };
+        bound.clamp(0, length as i64) as usize
+    };
+    let start = start.map_or(0, resolve);
+    let end = end.map_or(length, resolve);
+    (start, end.max(start))
+}
+
+fn call(function: Function, argument: Option<&Expr>, input: &Value, root: &Value) -> Result<Vec<Value>, FilterError> {
+    let argument = |input: &Value| match argument {
+        Some(argument) => evaluate(argument, input, root),
+        None => Ok(Vec::new()),
+    };
+    let expect_array = |name: &str| match input {
+        Value::Array(items) => Ok(items.clone()),
+        value => Err(FilterError::Type(format!("{} needs an array, not {}", name, type_name(value)))),
+    };
+    Ok(match function {
+        Function::Select => {
+            let keep = argument(input)?.iter().any(truthy);
+            if keep { vec![input.clone()] } else { Vec::new() }
+        }
+        Function::Map => {
+            let items = match input {
+                Value::Object(map) => map.values().cloned().collect(),
+                _ => expect_array("map")?,
+            };
+            let mut mapped = Vec::new();
+            for item in &items {
+                mapped.extend(argument(item)?);
+            }
+            vec![Value::Array(mapped)]
+        }
+        Function::Has => {
+            let mut values = Vec::new();
+            for key in argument(input)? {
+                values.push(Value::Bool(match (input, &key) {
+                    (Value::Object(map), Value::String(key)) => map.contains_key(key),
+                    (Value::Array(items), Value::Number(index)) => {
+                        index.as_u64().is_some_and(|index| (index as usize) < items.len())
+                    }
+                    (input, key) => {
+                        return Err(FilterError::Type(format!(
+                            "Cannot check whether {} has a {} key",
+                            type_name(input),
+                            type_name(key)
+                        )));
+                    }
+                }));
+            }
+            values
+        }
+        Function::Keys => match input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                vec![Value::Array(keys.into_iter().map(|key| Value::String(key.clone())).collect())]
+            }
+            Value::Array(items) => vec![Value::Array((0..items.len()).map(Value::from).collect())],
+            value => return Err(FilterError::Type(format!("{} has no keys", type_name(value)))),
+        },
+        Function::Length => vec![match input {
+            Value::Null => Value::from(0),
+            Value::Bool(_) => return Err(FilterError::Type("boolean has no length".to_string())),
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => Value::from(integer.unsigned_abs()),
+                None => number.as_f64().map_or(Value::Null, |float| Value::from(float.abs())),
+            },
+            Value::String(text) => Value::from(text.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(map) => Value::from(map.len()),
+        }],
+        Function::Type => vec![Value::String(type_name(input).to_string())],
+        Function::First => vec![expect_array("first")?.first().cloned().unwrap_or(Value::Null)],
+        Function::Last => vec![expect_array("last")?.last().cloned().unwrap_or(Value::Null)],
+        Function::Sort => {
+            let mut items = expect_array("sort")?;
+            items.sort_by(compare);
+            vec![Value::Array(items)]
+        }
+        Function::Reverse => match input {
+            Value::String(text) => vec![Value::String(text.chars().rev().collect())],
+            Value::Null => vec![Value::Array(Vec::new())],
+            _ => {
+                let mut items = expect_array("reverse")?;
+                items.reverse();
+                vec![Value::Array(items)]
+            }
+        },
+    })
+}
+
+/// `value` and everything below it, parents before their children.
+fn descendants(value: &Value) -> Vec<&Value> {
+    fn collect<'a>(value: &'a Value, values: &mut Vec<&'a Value>) {
+        values.push(value);
+        match value {
+            Value::Array(items) => items.iter().for_each(|item| collect(item, values)),
+            Value::Object(map) => map.values().for_each(|item| collect(item, values)),
+            _ => {}
+        }
+    }
+    let mut values = Vec::new();
+    collect(value, &mut values);
+    values
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// jq's order: null, false, true, numbers, strings, arrays, objects. Numbers
+/// compare by value, so `1 == 1.0`.
+fn compare(left: &Value, right: &Value) -> Ordering {
+    let rank = |value: &Value| match value {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    };
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => {
+            left.as_f64().partial_cmp(&right.as_f64()).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(left), Value::String(right)) => left.cmp(right),
+        (Value::Array(left), Value::Array(right)) => left
+            .iter()
+            .zip(right)
+            .map(|(left, right)| compare(left, right))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| left.len().cmp(&right.len())),
+        (Value::Object(left), Value::Object(right)) => {
+            let mut left_keys: Vec<&String> = left.keys().collect();
+            let mut right_keys: Vec<&String> = right.keys().collect();
+            left_keys.sort();
+            right_keys.sort();
+            left_keys.cmp(&right_keys).then_with(|| {
+                left_keys
+                    .iter()
+                    .map(|key| compare(&left[*key], &right[*key]))
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        (left, right) => rank(left).cmp(&rank(right)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store() -> Value {
+        json!({
+            "store": {
+                "books": [
+                    {"title": "Sayings", "price": 8.95, "tags": ["old"]},
+                    {"title": "Sword", "price": 12.99, "isbn": "0-553"},
+                    {"title": "Moby Dick", "price": 8.99, "isbn": "0-395"}
+                ],
+                "bicycle": {"color": "red", "price": 19.95}
+            }
+        })
+    }
+
+    fn run(filter: &str, value: &Value) -> Vec<Value> {
+        Filter::parse(filter).unwrap_or_else(|e| panic!("{}: {}", filter, e)).apply(value).unwrap()
+    }
+
+    fn error(filter: &str, value: &Value) -> String {
+        match Filter::parse(filter) {
+            Ok(parsed) => parsed.apply(value).unwrap_err().to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn follows_jq_paths() {
+        let store = store();
+        assert_eq!(run(".", &json!(1)), [json!(1)]);
+        assert_eq!(run(".store.bicycle.color", &store), [json!("red")]);
+        assert_eq!(run(r#".store."bicycle"["color"]"#, &store), [json!("red")]);
+        assert_eq!(run(".store.books[0].title", &store), [json!("Sayings")]);
+        assert_eq!(run(".store.books[-1].title", &store), [json!("Moby Dick")]);
+        assert_eq!(run(".store.books[].isbn", &store), [json!(null), json!("0-553"), json!("0-395")]);
+        assert_eq!(run(".store.missing.deeper", &store), [json!(null)]);
+        assert_eq!(run(".store.books[7]", &store), [json!(null)]);
+    }
+
+    #[test]
+    fn slices_arrays_and_strings() {
+        let items = json!([0, 1, 2, 3, 4]);
+        assert_eq!(run(".[1:3]", &items), [json!([1, 2])]);
+        assert_eq!(run(".[:2]", &items), [json!([0, 1])]);
+        assert_eq!(run(".[-2:]", &items), [json!([3, 4])]);
+        assert_eq!(run(".[3:1]", &items), [json!([])]);
+        assert_eq!(run(".[-10:10]", &items), [json!([0, 1, 2, 3, 4])]);
+        assert_eq!(run(".[1:4]", &json!("héllo")), [json!("éll")]);
+        assert_eq!(run("$[1:3]", &items), [json!(1), json!(2)]);
+        assert_eq!(run("$[-1:]", &items), [json!(4)]);
+    }
+
+    #[test]
+    fn pipes_and_builds_values() {
+        let store = store();
+        assert_eq!(
+            run(".store.books[] | select(.price < 9) | .title", &store),
+            [json!("Sayings"), json!("Moby Dick")]
+        );
+        assert_eq!(run(".store.books | map(.price > 10)", &store), [json!([false, true, false])]);
+        assert_eq!(run(".store.books | length, (first | .title)", &store), [json!(3), json!("Sayings")]);
+        assert_eq!(run("[.store.books[].title] | sort | reverse | first", &store), [json!("Sword")]);
+        assert_eq!(
+            run(".store.books[1] | {title, cost: .price}", &store),
+            [json!({"title": "Sword", "cost": 12.99})]
+        );
+        assert_eq!(run("{a: (1, 2)}", &json!(null)), [json!({"a": 1}), json!({"a": 2})]);
+        assert_eq!(run(".store.bicycle | keys", &store), [json!(["color", "price"])]);
+        assert_eq!(run(".store.books[] | has(\"isbn\") and .price > 9", &store), [json!(false), json!(true), json!(false)]);
+        assert_eq!(run(".store.books[0].tags | type, (.[0] | not)", &store), [json!("array"), json!(false)]);
+        assert_eq!(run(".[] | select(. == 1.0)", &json!([1, "1", true])), [json!(1)]);
+    }
+
+    #[test]
+    fn filters_with_json_path() {
+        let store = store();
+        assert_eq!(run("$.store.books[*].title", &store).len(), 3);
+        assert_eq!(run("$.store.books[?(@.price < 9)].title", &store), [json!("Sayings"), json!("Moby Dick")]);
+        assert_eq!(run("$.store.books[?(@.isbn)].title", &store), [json!("Sword"), json!("Moby Dick")]);
+        assert_eq!(run("$.store.books[?(!@.isbn)].title", &store), [json!("Sayings")]);
+        assert_eq!(
+            run("$.store.books[?(@.price > 9 && @.title != 'Sword' || @.tags)].title", &store),
+            [json!("Sayings")]
+        );
+        assert_eq!(
+            run("$.store.books[?(@.price < $.store.bicycle.price)]", &store).len(),
+            3,
+            "$ inside a filter is the whole document"
+        );
+        assert_eq!(run("$.store.books[0,2].title", &store), [json!("Sayings"), json!("Moby Dick")]);
+        assert_eq!(run("$.store['bicycle'].color", &store), [json!("red")]);
+        // Anything missing yields nothing rather than null.
+        assert!(run("$.store.books[*].isbn.none", &store).is_empty());
+    }
+
+    #[test]
+    fn recurses_with_dot_dot() {
+        let store = store();
+        // Object keys come in sorted order, so the bicycle is first.
+        let prices = [json!(19.95), json!(8.95), json!(12.99), json!(8.99)];
+        assert_eq!(run("$..price", &store), prices);
+        assert_eq!(run(".. | .price?", &store).len(), 6, "one per object, null without a price");
+        assert_eq!(run("$.store..isbn", &store), [json!("0-553"), json!("0-395")]);
+        assert_eq!(run("$..books[1].title", &store), [json!("Sword")]);
+        assert_eq!(run("$..[0]", &store), [json!({"title": "Sayings", "price": 8.95, "tags": ["old"]}), json!("old")]);
+        assert_eq!(run("$..tags.*", &store), [json!("old")]);
+        assert_eq!(run("..", &json!({"a": [1]})), [json!({"a": [1]}), json!([1]), json!(1)]);
+        assert_eq!(run("[..[]?]", &json!([[1], 2])), [json!([[1], 2, 1])]);
+        assert_eq!(run(".. | select(type == \"string\")", &store).len(), 7);
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_column() {
+        let value = json!({});
+        assert_eq!(error(".a |", &value), "Invalid filter at column 5: unexpected end of filter");
+        assert_eq!(error(".a]", &value), "Invalid filter at column 3: unexpected ']'");
+        assert_eq!(error(".[1", &value), "Invalid filter at column 4: expected ']'");
+        assert_eq!(error(".[1.5]", &value), "Invalid filter at column 3: expected an integer");
+        assert_eq!(error("\"abc", &value), "Invalid filter at column 1: unterminated string");
+        assert_eq!(error("frobnicate(.)", &value), "Invalid filter at column 1: unknown function 'frobnicate'");
+        assert_eq!(error("select .a", &value), "Invalid filter at column 8: expected '('");
+        assert_eq!(error("$.", &value), "Invalid filter at column 3: expected a name");
+        assert_eq!(error("$[?(@.a <)]", &value), "Invalid filter at column 10: expected @, $ or a value");
+    }
+
+    #[test]
+    fn reports_type_errors() {
+        let value = json!({"a": [1], "n": 1});
+        assert_eq!(error(".a.b", &value), "Cannot index array with \"b\"");
+        assert_eq!(error(".n[0]", &value), "Cannot index number with a number");
+        assert_eq!(error(".n[]", &value), "Cannot iterate over number");
+        assert_eq!(error(".n | keys", &value), "number has no keys");
+        assert_eq!(error(".a[0] | sort", &value), "sort needs an array, not number");
+        assert_eq!(error("true | length", &value), "boolean has no length");
+        assert_eq!(error("has(0)", &value), "Cannot check whether object has a number key");
+        // `?` turns a wrong type into no result.
+        assert!(run(".n[]?", &value).is_empty());
+        assert_eq!(run(".a.b?, .n", &value), [json!(1)]);
+    }
+}
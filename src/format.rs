@@ -0,0 +1,107 @@
+use std::fmt;
+
+use crate::content;
+use crate::html;
+use crate::json;
+use crate::xml;
+use crate::yaml;
+
+/// Body formats with a pretty printer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Xml,
+    Html,
+    Yaml,
+    Text,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json => "JSON",
+            Format::Xml => "XML",
+            Format::Html => "HTML",
+            Format::Yaml => "YAML",
+            Format::Text => "text",
+        })
+    }
+}
+
+impl Format {
+    /// The format named by `content_type`, or guessed from the start of
+    /// `body` when the type is missing or generic.
+    pub fn detect(content_type: &str, body: &str) -> Format {
+        let media_type = content::media_type(content_type);
+        match media_type.as_str() {
+            "application/json" | "application/x-ndjson" => return Format::Json,
+            "text/html" | "application/xhtml+xml" => return Format::Html,
+            "application/xml" | "text/xml" => return Format::Xml,
+            "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => return Format::Yaml,
+            media_type if media_type.ends_with("+json") => return Format::Json,
+            media_type if media_type.ends_with("+xml") => return Format::Xml,
+            media_type if media_type.ends_with("+yaml") => return Format::Yaml,
+            _ => {}
+        }
+
+        let start = body.trim_start().get(..15).unwrap_or(body.trim_start()).to_ascii_lowercase();
+        if json::is_json_like(body) {
+            Format::Json
+        } else if start.starts_with("<!doctype html") || start.starts_with("<html") {
+            Format::Html
+        } else if xml::is_xml_like(body) {
+            Format::Xml
+        } else {
+            Format::Text
+        }
+    }
+}
+
+/// Pretty prints `body` as `format`. Bodies that do not parse are returned
+/// unchanged, as are plain text bodies.
+pub fn pretty_print(format: Format, body: &str) -> String {
+    match format {
+        Format::Json => json::pretty_print_json_safe(body),
+        Format::Xml => xml::pretty_print_xml_safe(body),
+        Format::Html => html::pretty_print_html(body),
+        Format::Yaml => yaml::pretty_print_yaml_safe(body),
+        Format::Text => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_from_the_content_type() {
+        for (content_type, format) in [
+            ("application/json; charset=utf-8", Format::Json),
+            ("application/problem+json", Format::Json),
+            ("application/xhtml+xml", Format::Html),
+            ("application/atom+xml", Format::Xml),
+            ("text/xml", Format::Xml),
+            ("application/x-yaml", Format::Yaml),
+            ("application/openapi+yaml", Format::Yaml),
+        ] {
+            assert_eq!(Format::detect(content_type, "plain"), format, "{}", content_type);
+        }
+    }
+
+    #[test]
+    fn guesses_formats_from_the_body() {
+        assert_eq!(Format::detect("text/plain", " {\"a\": 1}"), Format::Json);
+        assert_eq!(Format::detect("", "<!DOCTYPE HTML><html>"), Format::Html);
+        assert_eq!(Format::detect("application/octet-stream", "<?xml version=\"1.0\"?><a/>"), Format::Xml);
+        assert_eq!(Format::detect("", "key: value"), Format::Text);
+    }
+
+    #[test]
+    fn prints_each_format() {
+        assert_eq!(pretty_print(Format::Json, "{\"a\":1}"), "{\n  \"a\": 1\n}");
+        assert_eq!(pretty_print(Format::Yaml, "a:   1"), "a: 1");
+        assert_eq!(pretty_print(Format::Xml, "<a><b/></a>"), "<a>\n  <b/>\n</a>");
+        assert_eq!(pretty_print(Format::Html, "<div><p>x</p></div>"), "<div>\n  <p>x</p>\n</div>");
+        assert_eq!(pretty_print(Format::Text, "  as is "), "  as is ");
+    }
+}
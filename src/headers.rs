@@ -2,7 +2,11 @@ use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use std::error::Error;
 use std::fmt;
 
+use crate::charset;
+use crate::output::infoln;
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum HeaderError {
     InvalidFormat(String),
     InvalidName(String),
@@ -65,8 +69,8 @@ pub fn print_headers(headers: &HeaderMap, title: &str){
         return;
     }
 
-    println!("{}", title);
+    infoln!("{}", title);
     for(name, value) in headers {
-        println!(" {}: {:?}", name, value);
+        infoln!(" {}: {:?}", name, charset::header_value(value));
     }
 }
\ No newline at end of file
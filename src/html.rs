@@ -0,0 +1,380 @@
+/// Elements without content or end tag.
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements that flow with the surrounding text instead of starting a line.
+const INLINE_ELEMENTS: [&str; 22] = [
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "dfn", "em", "i", "kbd", "label", "mark", "q", "s",
+    "samp", "small", "span", "strong", "sub", "sup",
+];
+
+/// Elements whose content is not markup and is kept as it is.
+const RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "pre", "textarea"];
+
+/// Elements that an opening tag of the same name closes implicitly.
+const SELF_NESTING_ELEMENTS: [&str; 9] = ["p", "li", "dt", "dd", "tr", "td", "th", "option", "thead"];
+
+/// Elements that start a new line in the text rendering.
+const TEXT_BLOCK_ELEMENTS: [&str; 24] = [
+    "address", "article", "aside", "blockquote", "dd", "div", "dl", "dt", "figcaption", "figure", "footer", "form",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "li", "main", "nav", "p", "tr",
+];
+
+#[derive(Debug)]
+enum Token<'a> {
+    Text(&'a str),
+    Start { name: String, raw: &'a str, self_closing: bool },
+    End { name: String, raw: &'a str },
+    /// Comments, doctypes and processing instructions.
+    Other(&'a str),
+    /// Content of a raw text element.
+    Raw(&'a str),
+}
+
+/// Splits HTML into tags and text. Never fails: anything that does not look
+/// like a tag is text.
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if open > 0 {
+            tokens.push(Token::Text(&rest[..open]));
+            rest = &rest[open..];
+        }
+
+        let after = &rest[1..];
+        let length = if after.starts_with("!--") {
+            rest.find("-->").map(|end| end + 3)
+        } else if after.starts_with('!') || after.starts_with('?') {
+            rest.find('>').map(|end| end + 1)
+        } else if after.starts_with('/') || after.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            tag_length(rest)
+        } else {
+            None
+        };
+        let Some(length) = length else {
+            // A stray `<` is text.
+            let next = rest[1..].find('<').map_or(rest.len(), |index| index + 1);
+            tokens.push(Token::Text(&rest[..next]));
+            rest = &rest[next..];
+            continue;
+        };
+
+        let raw = &rest[..length];
+        rest = &rest[length..];
+        if after.starts_with('!') || after.starts_with('?') {
+            tokens.push(Token::Other(raw));
+        } else if let Some(name) = raw.strip_prefix("</") {
+            tokens.push(Token::End { name: tag_name(name), raw });
+        } else {
+            let name = tag_name(&raw[1..]);
+            let self_closing = raw.ends_with("/>");
+            if RAW_TEXT_ELEMENTS.contains(&name.as_str()) && !self_closing {
+                let end = find_ignore_case(rest, &format!("</{}", name)).unwrap_or(rest.len());
+                tokens.push(Token::Start { name, raw, self_closing });
+                if end > 0 {
+                    tokens.push(Token::Raw(&rest[..end]));
+                }
+                rest = &rest[end..];
+            } else {
+                tokens.push(Token::Start { name, raw, self_closing });
+            }
+        }
+    }
+    tokens
+}
+
+/// Length of the tag at the start of `text`, skipping `>` inside quoted
+/// attribute values.
+fn tag_length(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in text.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index + 1),
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn tag_name(text: &str) -> String {
+    text.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | ':'))
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn find_ignore_case(text: &str, needle: &str) -> Option<usize> {
+    text.to_ascii_lowercase().find(needle)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                collapsed.push(' ');
+                space = false;
+            }
+            collapsed.push(c);
+        }
+    }
+    if space {
+        collapsed.push(' ');
+    }
+    collapsed
+}
+
+/// Collects output lines, with inline content appended to the current one.
+#[derive(Default)]
+struct Lines {
+    lines: Vec<String>,
+    current: String,
+    depth: usize,
+}
+
+impl Lines {
+    fn inline(&mut self, text: &str) {
+        if self.current.is_empty() {
+            let text = text.trim_start();
+            if text.is_empty() {
+                return;
+            }
+            self.current = "  ".repeat(self.depth);
+        }
+        self.current.push_str(text);
+    }
+
+    fn block(&mut self, text: &str) {
+        self.flush();
+        self.lines.push(format!("{}{}", "  ".repeat(self.depth), text.trim()));
+    }
+
+    fn flush(&mut self) {
+        let line = std::mem::take(&mut self.current);
+        if !line.trim().is_empty() {
+            self.lines.push(line.trim_end().to_string());
+        }
+    }
+}
+
+/// An open element and the line of its start tag.
+struct Open {
+    name: String,
+    line: usize,
+}
+
+/// Closes open elements up to and including `name` and returns it, if it is
+/// open.
+fn close(stack: &mut Vec<Open>, name: &str) -> Option<Open> {
+    let index = stack.iter().rposition(|open| open.name == name)?;
+    stack.drain(index..).next()
+}
+
+/// Indents block elements by two spaces per level and keeps inline elements
+/// and text on one line, also with the tags around them when an element
+/// holds nothing else. Tolerates unclosed and stray tags, so it never fails;
+/// `pre`, `textarea`, `script` and `style` content is kept.
+pub fn pretty_print_html(html: &str) -> String {
+    let mut lines = Lines::default();
+    let mut stack: Vec<Open> = Vec::new();
+
+    for token in tokenize(html) {
+        match token {
+            Token::Text(text) => lines.inline(&collapse_whitespace(text)),
+            Token::Other(raw) => lines.block(raw),
+            Token::Start { name, raw, .. } if INLINE_ELEMENTS.contains(&name.as_str()) => lines.inline(raw),
+            Token::End { name, raw } if INLINE_ELEMENTS.contains(&name.as_str()) => lines.inline(raw),
+            Token::Start { name, raw, self_closing } => {
+                if SELF_NESTING_ELEMENTS.contains(&name.as_str())
+                    && stack.last().is_some_and(|open| open.name == name)
+                {
+                    stack.pop();
+                }
+                lines.depth = stack.len();
+                lines.block(raw);
+                if !self_closing && !VOID_ELEMENTS.contains(&name.as_str()) {
+                    stack.push(Open { name, line: lines.lines.len() - 1 });
+                }
+            }
+            // Raw content directly follows the start tag of its element.
+            Token::Raw(content) => {
+                lines.flush();
+                if stack.last().is_some_and(|open| open.name == "pre" || open.name == "textarea") {
+                    lines.lines.push(content.trim_matches('\n').to_string());
+                } else {
+                    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+                        lines.lines.push(format!("{}{}", "  ".repeat(stack.len()), line.trim()));
+                    }
+                }
+            }
+            Token::End { name, raw } => match close(&mut stack, &name) {
+                // Only inline content since the start tag: one line.
+                Some(open) if open.line + 1 == lines.lines.len() && open.name != "pre" => {
+                    let content = std::mem::take(&mut lines.current);
+                    lines.lines[open.line].push_str(content.trim());
+                    lines.lines[open.line].push_str(raw);
+                }
+                _ => {
+                    lines.depth = stack.len();
+                    lines.block(raw);
+                }
+            },
+        }
+        lines.depth = stack.len();
+    }
+    lines.flush();
+    lines.lines.join("\n")
+}
+
+/// Renders the readable text of `html`: no tags, scripts or styles, entities
+/// decoded, one line per paragraph, list item or table row, and `-` before
+/// list items.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut hidden = 0usize;
+    let mut raw_element = String::new();
+
+    for token in tokenize(html) {
+        match token {
+            Token::Start { name, .. } if matches!(name.as_str(), "script" | "style" | "head") => {
+                hidden += 1;
+                raw_element = name;
+            }
+            Token::End { name, .. } if matches!(name.as_str(), "script" | "style" | "head") => {
+                hidden = hidden.saturating_sub(1);
+            }
+            _ if hidden > 0 => {}
+            Token::Text(content) => text.push_str(&decode_entities(&collapse_whitespace(content))),
+            Token::Raw(content) if raw_element != "script" && raw_element != "style" => {
+                text.push_str(&decode_entities(content));
+            }
+            Token::Start { name, .. } => {
+                raw_element = name.clone();
+                match name.as_str() {
+                    "br" => text.push('\n'),
+                    "li" => text.push_str("\n- "),
+                    "td" | "th" => text.push('\t'),
+                    name if TEXT_BLOCK_ELEMENTS.contains(&name) || RAW_TEXT_ELEMENTS.contains(&name) => {
+                        text.push('\n')
+                    }
+                    _ => {}
+                }
+            }
+            Token::End { name, .. } if TEXT_BLOCK_ELEMENTS.contains(&name.as_str()) || name == "pre" => {
+                text.push('\n')
+            }
+            _ => {}
+        }
+    }
+
+    // One blank line at most, and no stray spaces around line breaks.
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+            lines.push(line);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Decodes the common named entities and numeric character references.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..].find(';').filter(|end| *end <= 10).map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            "copy" => Some('©'),
+            "mdash" => Some('—'),
+            "ndash" => Some('–'),
+            "hellip" => Some('…'),
+            _ => match entity.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16).ok().and_then(char::from_u32),
+                Some(number) => number.parse().ok().and_then(char::from_u32),
+                None => None,
+            },
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indents_blocks_and_keeps_inline_content() {
+        let html = "<!DOCTYPE html><html><body><div class=\"a\"><p>Hello <b>bold</b> world</p><ul><li>one</li><li>two</ul></div></body></html>";
+        assert_eq!(
+            pretty_print_html(html),
+            "<!DOCTYPE html>\n<html>\n  <body>\n    <div class=\"a\">\n      <p>Hello <b>bold</b> world</p>\n      \
+             <ul>\n        <li>one</li>\n        <li>\n          two\n      </ul>\n    </div>\n  </body>\n</html>"
+        );
+    }
+
+    #[test]
+    fn keeps_void_and_stray_tags() {
+        let html = "<div><img src=\"a>b.png\"><br/>text</span></div><p>unclosed";
+        assert_eq!(
+            pretty_print_html(html),
+            "<div>\n  <img src=\"a>b.png\">\n  <br/>\n  text</span>\n</div>\n<p>\n  unclosed"
+        );
+        assert_eq!(pretty_print_html("1 < 2 <3"), "1 < 2 <3");
+    }
+
+    #[test]
+    fn keeps_raw_text_elements() {
+        let html = "<div><pre>  a\n    b</pre><p>after</p></div><script>\n  if (a < b) {\n    run();\n  }\n</script>";
+        assert_eq!(
+            pretty_print_html(html),
+            "<div>\n  <pre>\n  a\n    b\n  </pre>\n  <p>after</p>\n</div>\n<script>\n  if (a < b) {\n  run();\n  }\n</script>"
+        );
+    }
+
+    #[test]
+    fn never_joins_pre_onto_one_line() {
+        assert_eq!(pretty_print_html("<div><pre></pre></div>"), "<div>\n  <pre>\n  </pre>\n</div>");
+        assert_eq!(pretty_print_html("<pre></pre><p>x</p>"), "<pre>\n</pre>\n<p>x</p>");
+        assert_eq!(pretty_print_html("<pre>x</pre><div>y</div>"), "<pre>\nx\n</pre>\n<div>y</div>");
+    }
+
+    #[test]
+    fn renders_readable_text() {
+        let html = "<html><head><title>T</title><style>p{}</style></head><body><h1>Title</h1>\
+                    <p>Fish &amp; chips &#8212; &#x41;&hellip; &bogus;</p><ul><li>one</li><li>two</li></ul>\
+                    <table><tr><td>a</td><td>b</td></tr></table><script>alert(1)</script><pre>x  y</pre></body></html>";
+        assert_eq!(html_to_text(html), "Title\n\nFish & chips \u{2014} A\u{2026} &bogus;\n\n- one\n\n- two\n\na\tb\n\nx  y");
+    }
+}
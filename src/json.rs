@@ -57,6 +57,7 @@ pub fn validate_json(text: &str) -> Result<(), JsonError>{
     Ok(())
 }
 
+#[allow(dead_code)]
 pub fn minify_json(text: &str) -> Result<String, JsonError>{
     let parsed: Value = serde_json::from_str(text)?;
     let minified = serde_json::to_string(&parsed)?;
@@ -1,10 +1,75 @@
+mod auth;
+mod charset;
+mod client;
+mod compression;
+mod config;
+mod content;
+mod cookies;
+mod diff;
+mod dns;
+mod filter;
+mod format;
 mod headers;
+mod html;
 mod json;
+mod oauth;
+mod output;
+mod pager;
+mod protobuf;
+mod proxy;
+mod redirect;
+mod retry;
+mod schema;
+mod sender;
+mod serialization;
+mod session;
+mod snapshot;
+mod sigv4;
+mod signing;
+#[cfg(test)]
+mod testing;
+mod timing;
+mod tls;
+mod tree;
 mod tui;
+mod unix;
+mod wire;
+mod xml;
+mod yaml;
 
+use auth::{ApiKeyLocation, AuthError, AuthType};
+use client::{BuildError, ClientSettings, ConnectionTracker, HttpVersion, SendError};
+use charset::CharsetError;
 use clap::{Parser, Subcommand};
+use compression::{Body, CompressionError, Encoding};
+use config::ConfigError;
+use cookies::{CookieError, CookieJar};
+use diff::{DiffError, Difference, Snapshot, SnapshotBody};
+use dns::{DnsConfig, DnsError, DnsSettings, IpFamily};
+use filter::{Filter, FilterError};
+use format::Format;
+use oauth::{OAuthError, OAuthSession};
+use protobuf::{ProtobufError, ProtobufSettings};
+use proxy::{ProxyError, ProxySettings, Route};
+use redirect::{Hop, RedirectError, RedirectPolicy};
+use retry::{RetryError, RetryPolicy};
+use schema::{Schema, SchemaError, Schemas, Violation};
+use serialization::{Serialization, SerializationError};
+use sender::{Sender, Sent};
+use session::Session;
+use snapshot::{Outcome, Rules, SnapshotError};
+use sigv4::SigV4Error;
+use signing::{HmacAlgorithm, JwtAlgorithm, RequestSigner, SignatureEncoding, SigningError};
+use std::path::PathBuf;
+use timing::Timings;
+use tls::{TlsConfig, TlsError, TlsVersion};
+use std::sync::Arc;
+use std::time::Instant;
+use unix::UnixError;
 use headers::{add_headers_to_request, parse_headers, print_headers, HeaderError};
-use json::{pretty_print_json_safe, JsonError};
+use json::JsonError;
+use output::infoln;
+use std::io::{self, Write};
 
 #[derive(Parser)]
 #[command(name = "http")]
@@ -20,6 +85,8 @@ enum HttpMethod {
         url: String,
         #[arg(short = 'H', long = "header", action = clap::ArgAction::Append)]
         headers: Vec<String>,
+        #[command(flatten)]
+        options: RequestOptions,
     },
     Post {
         url: String,
@@ -29,6 +96,8 @@ enum HttpMethod {
         json: Option<String>,
         #[arg(short = 'H', long = "header", action = clap::ArgAction::Append)]
         headers: Vec<String>,
+        #[command(flatten)]
+        options: RequestOptions,
     },
     Put {
         url: String,
@@ -38,20 +107,488 @@ enum HttpMethod {
         json: Option<String>,
         #[arg(short = 'H', long = "header", action = clap::ArgAction::Append)]
         headers: Vec<String>,
+        #[command(flatten)]
+        options: RequestOptions,
     },
     Delete {
         url: String,
         #[arg(short = 'H', long = "header", action = clap::ArgAction::Append)]
         headers: Vec<String>,
+        #[command(flatten)]
+        options: RequestOptions,
     },
-    Tui,
+    /// Compare two responses: each side is a URL, requested with the same options, or a file saved with --save
+    Diff {
+        /// URL or file for the old response
+        old: String,
+        /// URL or file for the new response
+        new: String,
+        /// Method used for URLs
+        #[arg(short = 'X', long, default_value = "GET", value_parser = parse_method)]
+        method: reqwest::Method,
+        #[arg(short, long)]
+        data: Option<String>,
+        #[arg(short, long)]
+        json: Option<String>,
+        #[arg(short = 'H', long = "header", action = clap::ArgAction::Append)]
+        headers: Vec<String>,
+        /// Header left out of the comparison (repeatable); Date always is
+        #[arg(long = "ignore-header", value_name = "NAME", action = clap::ArgAction::Append)]
+        ignore_headers: Vec<String>,
+        #[command(flatten)]
+        options: SendOptions,
+    },
+    Tui {
+        /// Environment from environments.json to use for OAuth 2.0
+        #[arg(long)]
+        env: Option<String>,
+        /// Session whose cookies, auth and headers are loaded and kept up to date
+        #[arg(long)]
+        session: Option<String>,
+        #[command(flatten)]
+        connection: ConnectionOptions,
+        #[command(flatten)]
+        protobuf: ProtobufOptions,
+        #[command(flatten)]
+        schema: SchemaOptions,
+    },
+}
+
+/// How connections are made, shared by the request subcommands and the TUI.
+#[derive(clap::Args)]
+struct ConnectionOptions {
+    /// Proxy URL (http://, https://, socks5:// or socks5h://) for all requests
+    #[arg(long)]
+    proxy: Option<String>,
+    /// Comma-separated hosts, domains, IPs or CIDR ranges that bypass the proxy
+    #[arg(long = "no-proxy")]
+    no_proxy: Option<String>,
+    /// Proxy credentials as `user:pass` (prompts when the password is omitted)
+    #[arg(long = "proxy-auth")]
+    proxy_auth: Option<String>,
+    /// PEM CA bundle to trust instead of the system roots
+    #[arg(long)]
+    cacert: Option<PathBuf>,
+    /// Client certificate: PEM, or PKCS#12 when the file is not PEM
+    #[arg(long)]
+    cert: Option<PathBuf>,
+    /// Private key for a PEM --cert (when not in the same file)
+    #[arg(long)]
+    key: Option<PathBuf>,
+    /// Password of a PKCS#12 --cert
+    #[arg(long = "cert-password")]
+    cert_password: Option<String>,
+    /// Skip server certificate verification (dangerous)
+    #[arg(short = 'k', long)]
+    insecure: bool,
+    /// Minimum TLS version
+    #[arg(long = "tls-min", value_enum)]
+    tls_min: Option<TlsVersion>,
+    /// Accepted server public key as `sha256//<base64>` (repeatable)
+    #[arg(long = "pin", action = clap::ArgAction::Append)]
+    pins: Vec<String>,
+    /// Use `address` for `host`, as `host:port:address[,address...]` (repeatable)
+    #[arg(long = "resolve", action = clap::ArgAction::Append)]
+    resolve: Vec<String>,
+    /// Only connect to IPv4 addresses
+    #[arg(short = '4', long = "ipv4", conflicts_with = "ipv6")]
+    ipv4: bool,
+    /// Only connect to IPv6 addresses
+    #[arg(short = '6', long = "ipv6")]
+    ipv6: bool,
+    /// DNS server to query instead of the system resolver, as `ip[:port]`
+    #[arg(long = "dns-server")]
+    dns_server: Option<String>,
+    /// Send requests over this Unix domain socket instead of TCP
+    #[arg(long = "unix-socket")]
+    unix_socket: Option<PathBuf>,
+    /// Only use HTTP/1.1
+    #[arg(long = "http1.1", conflicts_with_all = ["http2", "http2_prior_knowledge"])]
+    http1_1: bool,
+    /// Require HTTP/2, negotiated during the TLS handshake
+    #[arg(long = "http2", conflicts_with = "http2_prior_knowledge")]
+    http2: bool,
+    /// Use HTTP/2 without negotiation, also for cleartext http:// (h2c)
+    #[arg(long = "http2-prior-knowledge")]
+    http2_prior_knowledge: bool,
+    /// Give up on an attempt after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+    /// Retry failed attempts up to N times
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retry: usize,
+    /// Comma-separated statuses that are retried
+    #[arg(long = "retry-on", value_name = "STATUSES", default_value = "429,502,503,504")]
+    retry_on: String,
+    /// Seconds before the first retry, doubled for every further one
+    #[arg(long = "retry-delay", value_name = "SECONDS", default_value_t = 1.0)]
+    retry_delay: f64,
+    /// Longest wait between attempts, also for Retry-After
+    #[arg(long = "retry-max-delay", value_name = "SECONDS", default_value_t = 60.0)]
+    retry_max_delay: f64,
+    /// Also retry methods that are not idempotent, such as POST and PATCH
+    #[arg(long = "retry-all-methods")]
+    retry_all_methods: bool,
+}
+
+impl ConnectionOptions {
+    /// Settings from the command line, falling back to the environment.
+    fn client_settings(&self, environment: &config::Environment) -> Result<ClientSettings, ClientError> {
+        let credentials = match &self.proxy_auth {
+            Some(spec) => match auth::parse_auth(spec, AuthType::Basic)? {
+                auth::Auth::Basic { username, password } => Some((username, password)),
+                _ => None,
+            },
+            None => None,
+        };
+
+        let tls = self.tls_config(environment.tls.clone().unwrap_or_default());
+        if tls.insecure {
+            infoln!("WARNING: TLS certificate verification is disabled (--insecure).");
+            infoln!("WARNING: Anyone on the network path can read and modify this traffic.");
+        }
+
+        Ok(ClientSettings {
+            proxy: ProxySettings::resolve(
+                self.proxy.as_deref(),
+                self.no_proxy.as_deref(),
+                credentials,
+                environment.proxy.as_ref(),
+            )?,
+            dns: DnsSettings::new(&self.dns_config(environment.dns.clone().unwrap_or_default()))?,
+            tls,
+            unix_socket: self.unix_socket.clone(),
+            http_version: self.http_version(),
+            timeout: self.timeout.map(|timeout| retry::seconds("--timeout", timeout)).transpose()?,
+            retry: self.retry_policy()?,
+            ..ClientSettings::default()
+        })
+    }
+
+    fn http_version(&self) -> HttpVersion {
+        if self.http1_1 {
+            HttpVersion::Http11
+        } else if self.http2 {
+            HttpVersion::Http2
+        } else if self.http2_prior_knowledge {
+            HttpVersion::Http2PriorKnowledge
+        } else {
+            HttpVersion::Negotiate
+        }
+    }
+
+    fn retry_policy(&self) -> Result<RetryPolicy, RetryError> {
+        Ok(RetryPolicy {
+            retries: self.retry,
+            statuses: RetryPolicy::parse_statuses(&self.retry_on)?,
+            all_methods: self.retry_all_methods,
+            base_delay: retry::seconds("--retry-delay", self.retry_delay)?,
+            max_delay: retry::seconds("--retry-max-delay", self.retry_max_delay)?,
+        })
+    }
+
+    /// The environment's DNS settings with command-line values on top.
+    fn dns_config(&self, mut config: DnsConfig) -> DnsConfig {
+        config.resolve.extend(self.resolve.iter().cloned());
+        if self.ipv4 {
+            config.family = Some(IpFamily::Ipv4);
+        } else if self.ipv6 {
+            config.family = Some(IpFamily::Ipv6);
+        }
+        if self.dns_server.is_some() {
+            config.server = self.dns_server.clone();
+        }
+        config
+    }
+
+    /// The environment's TLS settings with command-line values on top.
+    fn tls_config(&self, mut config: TlsConfig) -> TlsConfig {
+        if self.cacert.is_some() {
+            config.cacert = self.cacert.clone();
+        }
+        if self.cert.is_some() {
+            config.cert = self.cert.clone();
+            config.key = self.key.clone();
+        }
+        if self.cert_password.is_some() {
+            config.cert_password = self.cert_password.clone();
+        }
+        if self.tls_min.is_some() {
+            config.min_version = self.tls_min;
+        }
+        config.insecure |= self.insecure;
+        config.pins.extend(self.pins.iter().cloned());
+        config
+    }
+}
+
+/// Options shared by every request subcommand.
+#[derive(clap::Args)]
+struct RequestOptions {
+    #[command(flatten)]
+    send: SendOptions,
+    /// Print how long DNS, connect, TLS, waiting and download took
+    #[arg(long)]
+    timings: bool,
+    /// Keep the response body as received: no Content-Encoding, MessagePack, CBOR or Protobuf decoding
+    #[arg(long)]
+    raw: bool,
+    /// Show the response body as a hex and ASCII dump
+    #[arg(long)]
+    hex: bool,
+    /// Show HTML bodies as plain text instead of indented markup
+    #[arg(long = "html-text")]
+    html_text: bool,
+    /// Print only what a JSONPath (`$.items[*].id`) or jq (`.items[] | .id`) filter selects from a JSON body
+    #[arg(long, value_name = "EXPRESSION", conflicts_with = "hex")]
+    filter: Option<String>,
+    /// Decode the response body with this charset, e.g. shift_jis or windows-1252
+    #[arg(long = "response-charset", value_name = "CHARSET")]
+    response_charset: Option<String>,
+    /// Save the status, headers and decoded body to a file for `http diff`
+    #[arg(long, value_name = "FILE")]
+    save: Option<PathBuf>,
+    /// Compare the response with a recorded snapshot (name or .json path), recording it on the first run
+    #[arg(long, value_name = "NAME")]
+    snapshot: Option<String>,
+    /// Record the response as the new snapshot instead of comparing
+    #[arg(long = "update-snapshot", requires = "snapshot")]
+    update_snapshot: bool,
+    /// Header kept in the snapshot besides Content-Type (repeatable)
+    #[arg(long = "snapshot-header", value_name = "NAME", requires = "snapshot", action = clap::ArgAction::Append)]
+    snapshot_headers: Vec<String>,
+    /// JSON path of a value that changes every time, e.g. `$..updatedAt` or `$.items[*].id` (repeatable)
+    #[arg(long = "snapshot-ignore", value_name = "PATH", requires = "snapshot", action = clap::ArgAction::Append)]
+    snapshot_ignore: Vec<String>,
+    #[command(flatten)]
+    schema: SchemaOptions,
+}
+
+/// How a request is built and sent, shared by the request subcommands and
+/// `diff`.
+#[derive(clap::Args)]
+struct SendOptions {
+    /// Print the exact request and response heads, and connection details
+    #[arg(short = 'v', long)]
+    verbose: bool,
+    /// Print the request that would be sent, without sending it
+    #[arg(long)]
+    offline: bool,
+    /// Print the server certificate chain (subject, issuer, SANs, validity)
+    #[arg(long = "show-cert")]
+    show_cert: bool,
+    /// Compress the request body and send the matching Content-Encoding
+    #[arg(long, value_enum)]
+    compress: Option<Encoding>,
+    /// Encode the --json body as MessagePack, CBOR or Protobuf and send it with that Content-Type
+    #[arg(long = "send-as", value_enum, value_name = "FORMAT")]
+    send_as: Option<Serialization>,
+    /// Credentials as `user:pass` (prompts when the password is omitted) or a bearer token
+    #[arg(short = 'a', long)]
+    auth: Option<String>,
+    /// Authentication scheme used with --auth
+    #[arg(long = "auth-type", value_enum, default_value_t = AuthType::Basic)]
+    auth_type: AuthType,
+    /// API key sent as a header, in `Key: Value` format
+    #[arg(long = "api-key")]
+    api_key: Option<String>,
+    /// API key sent as a query parameter, in `name=value` format
+    #[arg(long = "api-key-query")]
+    api_key_query: Option<String>,
+    /// Environment from environments.json whose settings (e.g. OAuth 2.0) apply
+    #[arg(short = 'e', long)]
+    env: Option<String>,
+    /// Named session (or session file path) persisting cookies, auth and headers
+    #[arg(long)]
+    session: Option<String>,
+    /// Load cookies from a Netscape cookies.txt file
+    #[arg(long = "import-cookies")]
+    import_cookies: Option<PathBuf>,
+    /// Write the cookie jar to a Netscape cookies.txt file after the request
+    #[arg(long = "export-cookies")]
+    export_cookies: Option<PathBuf>,
+    /// Follow redirects (the default)
+    #[arg(long, overrides_with = "no_follow")]
+    follow: bool,
+    /// Return redirect responses instead of following them
+    #[arg(long = "no-follow", overrides_with = "follow")]
+    no_follow: bool,
+    #[arg(long = "max-redirects", default_value_t = 10)]
+    max_redirects: usize,
+    /// Resend the method and body on 301/302/303 instead of switching to GET
+    #[arg(long = "keep-method")]
+    keep_method: bool,
+    /// Keep Authorization and Cookie headers on redirects to another origin
+    #[arg(long = "keep-auth")]
+    keep_auth: bool,
+    #[command(flatten)]
+    connection: ConnectionOptions,
+    #[command(flatten)]
+    protobuf: ProtobufOptions,
+    /// Sign the request with AWS Signature Version 4, as `service:region`
+    #[arg(long = "aws-sigv4", conflicts_with = "auth")]
+    aws_sigv4: Option<String>,
+    /// Profile from the shared AWS credentials file (instead of AWS_* env vars)
+    #[arg(long = "aws-profile", requires = "aws_sigv4")]
+    aws_profile: Option<String>,
+    /// Sign with UNSIGNED-PAYLOAD instead of hashing the body
+    #[arg(long = "aws-unsigned-payload", requires = "aws_sigv4")]
+    aws_unsigned_payload: bool,
+    /// Secret for an HMAC signature header
+    #[arg(long = "hmac-key", conflicts_with = "hmac_key_file")]
+    hmac_key: Option<String>,
+    /// File holding the HMAC secret
+    #[arg(long = "hmac-key-file")]
+    hmac_key_file: Option<PathBuf>,
+    #[arg(long = "hmac-algorithm", value_enum, default_value_t = HmacAlgorithm::Sha256)]
+    hmac_algorithm: HmacAlgorithm,
+    /// Canonical string to sign; see README for placeholders
+    #[arg(long = "hmac-template", default_value = signing::DEFAULT_HMAC_TEMPLATE)]
+    hmac_template: String,
+    #[arg(long = "hmac-header", default_value = "X-Signature")]
+    hmac_header: String,
+    /// Header carrying the signed timestamp (empty to omit)
+    #[arg(long = "hmac-timestamp-header", default_value = "X-Timestamp")]
+    hmac_timestamp_header: String,
+    #[arg(long = "hmac-encoding", value_enum, default_value_t = SignatureEncoding::Hex)]
+    hmac_encoding: SignatureEncoding,
+    /// Key file for a per-request JWT (raw secret for HS256, PEM for RS256/ES256)
+    #[arg(long = "jwt-key")]
+    jwt_key: Option<PathBuf>,
+    #[arg(long = "jwt-algorithm", value_enum, default_value_t = JwtAlgorithm::HS256)]
+    jwt_algorithm: JwtAlgorithm,
+    /// Extra JWT claim in `name=value` format (repeatable)
+    #[arg(long = "jwt-claim", action = clap::ArgAction::Append)]
+    jwt_claims: Vec<String>,
+    /// JWT lifetime in seconds
+    #[arg(long = "jwt-expiry", default_value_t = 300)]
+    jwt_expiry: u64,
+    /// Header the JWT is placed in (Authorization gets a Bearer prefix)
+    #[arg(long = "jwt-header", default_value = "Authorization")]
+    jwt_header: String,
+}
+
+/// Protobuf schema and message types, shared by the request subcommands and
+/// the TUI.
+#[derive(clap::Args)]
+struct ProtobufOptions {
+    /// .proto file or descriptor set (protoc --descriptor_set_out) with the message types (repeatable)
+    #[arg(long, value_name = "FILE", action = clap::ArgAction::Append)]
+    proto: Vec<PathBuf>,
+    /// Message type of the body sent with --send-as protobuf, e.g. shop.v1.Order
+    #[arg(long = "proto-request", value_name = "MESSAGE")]
+    proto_request: Option<String>,
+    /// Message type of Protobuf responses, instead of the one their Content-Type names
+    #[arg(long = "proto-response", value_name = "MESSAGE")]
+    proto_response: Option<String>,
+}
+
+/// JSON Schemas for request and response bodies, shared by the request
+/// subcommands and the TUI.
+#[derive(clap::Args)]
+struct SchemaOptions {
+    /// JSON Schema (draft 2020-12 or draft-07) the request body must match; it is not sent otherwise
+    #[arg(long = "request-schema", value_name = "FILE")]
+    request_schema: Option<PathBuf>,
+    /// JSON Schema (draft 2020-12 or draft-07) the response body must match, or the command exits with 1
+    #[arg(long = "response-schema", value_name = "FILE")]
+    response_schema: Option<PathBuf>,
+}
+
+/// A method name in any case, such as `post` or `PATCH`.
+fn parse_method(name: &str) -> Result<reqwest::Method, String> {
+    reqwest::Method::from_bytes(name.to_ascii_uppercase().as_bytes()).map_err(|_| format!("invalid method '{}'", name))
+}
+
+impl SchemaOptions {
+    fn schemas(&self) -> Result<Schemas, SchemaError> {
+        Schemas::load(self.request_schema.as_deref(), self.response_schema.as_deref())
+    }
+}
+
+impl ProtobufOptions {
+    fn settings(&self) -> Result<ProtobufSettings, ProtobufError> {
+        ProtobufSettings::new(&self.proto, self.proto_request.clone(), self.proto_response.clone())
+    }
+}
+
+impl SendOptions {
+    fn redirect_policy(&self) -> RedirectPolicy {
+        RedirectPolicy {
+            follow: !self.no_follow,
+            max_redirects: self.max_redirects,
+            keep_method: self.keep_method,
+            keep_auth: self.keep_auth,
+        }
+    }
+
+    /// HMAC/JWT signers from the command line, falling back to the environment.
+    fn signers(&self, environment: &config::Environment) -> Result<Vec<RequestSigner>, SigningError> {
+        let mut signers = Vec::new();
+
+        if self.hmac_key.is_some() || self.hmac_key_file.is_some() {
+            signers.push(RequestSigner::Hmac(signing::HmacConfig {
+                key: self.hmac_key.clone(),
+                key_file: self.hmac_key_file.clone(),
+                algorithm: self.hmac_algorithm,
+                template: self.hmac_template.clone(),
+                header: self.hmac_header.clone(),
+                timestamp_header: Some(self.hmac_timestamp_header.clone()).filter(|h| !h.is_empty()),
+                encoding: self.hmac_encoding,
+            }));
+        } else if let Some(config) = &environment.hmac {
+            signers.push(RequestSigner::Hmac(config.clone()));
+        }
+
+        if let Some(key_file) = &self.jwt_key {
+            let claims = self
+                .jwt_claims
+                .iter()
+                .map(|claim| signing::parse_claim(claim))
+                .collect::<Result<_, _>>()?;
+            signers.push(RequestSigner::Jwt(signing::JwtConfig {
+                key_file: key_file.clone(),
+                algorithm: self.jwt_algorithm,
+                claims,
+                expiry_secs: self.jwt_expiry,
+                header: self.jwt_header.clone(),
+            }));
+        } else if let Some(config) = &environment.jwt {
+            signers.push(RequestSigner::Jwt(config.clone()));
+        }
+
+        Ok(signers)
+    }
 }
 
 #[derive(Debug)]
 enum ClientError {
     Request(reqwest::Error),
+    Send(SendError),
     Header(HeaderError),
     Json(JsonError),
+    Auth(AuthError),
+    Config(ConfigError),
+    OAuth(OAuthError),
+    SigV4(SigV4Error),
+    Signing(SigningError),
+    Cookie(CookieError),
+    Redirect(RedirectError),
+    Proxy(ProxyError),
+    Dns(DnsError),
+    Tls(TlsError),
+    Unix(UnixError),
+    Version(SendError),
+    Retry(RetryError),
+    Compression(CompressionError),
+    Output(io::Error),
+    Charset(CharsetError),
+    Protobuf(ProtobufError),
+    Serialization(SerializationError),
+    Filter(FilterError),
+    Diff(DiffError),
+    Snapshot(SnapshotError),
+    Schema(SchemaError),
     Tui(Box<dyn std::error::Error>), // Add a new variant for TUI errors
 }
 
@@ -59,8 +596,31 @@ impl std::fmt::Display for ClientError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ClientError::Request(e) => write!(f, "Request error: {}", e),
+            ClientError::Send(e) => write!(f, "Request error: {}", e),
             ClientError::Header(e) => write!(f, "Header error: {}", e),
             ClientError::Json(e) => write!(f, "JSON error: {}", e),
+            ClientError::Auth(e) => write!(f, "Auth error: {}", e),
+            ClientError::Config(e) => write!(f, "Config error: {}", e),
+            ClientError::OAuth(e) => write!(f, "OAuth error: {}", e),
+            ClientError::SigV4(e) => write!(f, "SigV4 error: {}", e),
+            ClientError::Signing(e) => write!(f, "Signing error: {}", e),
+            ClientError::Cookie(e) => write!(f, "Cookie error: {}", e),
+            ClientError::Redirect(e) => write!(f, "Redirect error: {}", e),
+            ClientError::Proxy(e) => write!(f, "Proxy error: {}", e),
+            ClientError::Dns(e) => write!(f, "DNS error: {}", e),
+            ClientError::Tls(e) => write!(f, "TLS error: {}", e),
+            ClientError::Unix(e) => write!(f, "Unix socket error: {}", e),
+            ClientError::Version(e) => write!(f, "HTTP version error: {}", e),
+            ClientError::Retry(e) => write!(f, "Retry error: {}", e),
+            ClientError::Compression(e) => write!(f, "Compression error: {}", e),
+            ClientError::Output(e) => write!(f, "Could not write the response body: {}", e),
+            ClientError::Charset(e) => write!(f, "Charset error: {}", e),
+            ClientError::Protobuf(e) => write!(f, "Protobuf error: {}", e),
+            ClientError::Serialization(e) => write!(f, "Serialization error: {}", e),
+            ClientError::Filter(e) => write!(f, "Filter error: {}", e),
+            ClientError::Diff(e) => write!(f, "Diff error: {}", e),
+            ClientError::Snapshot(e) => write!(f, "Snapshot error: {}", e),
+            ClientError::Schema(e) => write!(f, "Schema error: {}", e),
             ClientError::Tui(e) => write!(f, "TUI error: {}", e),
         }
     }
@@ -86,122 +646,561 @@ impl From<HeaderError> for ClientError {
     }
 }
 
+impl From<AuthError> for ClientError {
+    fn from(error: AuthError) -> Self {
+        ClientError::Auth(error)
+    }
+}
+
+impl From<ConfigError> for ClientError {
+    fn from(error: ConfigError) -> Self {
+        ClientError::Config(error)
+    }
+}
+
+impl From<OAuthError> for ClientError {
+    fn from(error: OAuthError) -> Self {
+        ClientError::OAuth(error)
+    }
+}
+
+impl From<SigV4Error> for ClientError {
+    fn from(error: SigV4Error) -> Self {
+        ClientError::SigV4(error)
+    }
+}
+
+impl From<SigningError> for ClientError {
+    fn from(error: SigningError) -> Self {
+        ClientError::Signing(error)
+    }
+}
+
+impl From<CookieError> for ClientError {
+    fn from(error: CookieError) -> Self {
+        ClientError::Cookie(error)
+    }
+}
+
+impl From<RedirectError> for ClientError {
+    fn from(error: RedirectError) -> Self {
+        ClientError::Redirect(error)
+    }
+}
+
+impl From<ProxyError> for ClientError {
+    fn from(error: ProxyError) -> Self {
+        ClientError::Proxy(error)
+    }
+}
+
+impl From<DnsError> for ClientError {
+    fn from(error: DnsError) -> Self {
+        ClientError::Dns(error)
+    }
+}
+
+impl From<TlsError> for ClientError {
+    fn from(error: TlsError) -> Self {
+        ClientError::Tls(error)
+    }
+}
+
+impl From<SendError> for ClientError {
+    fn from(error: SendError) -> Self {
+        match error {
+            SendError::Request(e) => ClientError::Request(e),
+            SendError::Unix(e) => ClientError::Unix(e),
+            e @ SendError::NotHttp2(..) => ClientError::Version(e),
+            e => ClientError::Send(e),
+        }
+    }
+}
+
+impl From<BuildError> for ClientError {
+    fn from(error: BuildError) -> Self {
+        match error {
+            BuildError::Tls(e) => ClientError::Tls(e),
+            BuildError::Client(e) => ClientError::Request(e),
+        }
+    }
+}
+
+impl From<RetryError> for ClientError {
+    fn from(error: RetryError) -> Self {
+        ClientError::Retry(error)
+    }
+}
+
+impl From<CharsetError> for ClientError {
+    fn from(error: CharsetError) -> Self {
+        ClientError::Charset(error)
+    }
+}
+
+impl From<CompressionError> for ClientError {
+    fn from(error: CompressionError) -> Self {
+        ClientError::Compression(error)
+    }
+}
+
+impl From<ProtobufError> for ClientError {
+    fn from(error: ProtobufError) -> Self {
+        ClientError::Protobuf(error)
+    }
+}
+
+impl From<SerializationError> for ClientError {
+    fn from(error: SerializationError) -> Self {
+        ClientError::Serialization(error)
+    }
+}
+
+impl From<FilterError> for ClientError {
+    fn from(error: FilterError) -> Self {
+        ClientError::Filter(error)
+    }
+}
+
+impl From<DiffError> for ClientError {
+    fn from(error: DiffError) -> Self {
+        ClientError::Diff(error)
+    }
+}
+
+impl From<SnapshotError> for ClientError {
+    fn from(error: SnapshotError) -> Self {
+        ClientError::Snapshot(error)
+    }
+}
+
+impl From<SchemaError> for ClientError {
+    fn from(error: SchemaError) -> Self {
+        ClientError::Schema(error)
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), ClientError> {
     let args = Args::parse();
-    let client = reqwest::Client::new();
-
-    match args.command {
-        HttpMethod::Get { url, headers } => {
-            println!("GET {}", url);
 
-            if !headers.is_empty() {
-                if let Ok(header_map) = parse_headers(&headers) {
-                    print_headers(&header_map, "Request Headers");
-                }
-            }
-            
-            let mut request = client.get(&url);
-            request = add_headers_to_request(request, &headers)?;
-            let response = request.send().await?;
-            print_response(response).await?;
-        }
-        HttpMethod::Post { url, data, json, headers } => {
-            println!("POST {}", url);
-            
-            match (data.as_ref(), json.as_ref()) {
-                (Some(_), Some(_)) => {
-                    return Err(ClientError::Json(JsonError::InvalidJSon(
-                        "Cannot use both --data and --json options".to_string()
-                    )));
-                }
-                _ => {}
+    let matched = match args.command {
+        HttpMethod::Get { url, headers, options } => {
+            send_request(reqwest::Method::GET, &url, None, None, &headers, &options).await?
+        }
+        HttpMethod::Post { url, data, json, headers, options } => {
+            send_request(reqwest::Method::POST, &url, data, json, &headers, &options).await?
+        }
+        HttpMethod::Put { url, data, json, headers, options } => {
+            send_request(reqwest::Method::PUT, &url, data, json, &headers, &options).await?
+        }
+        HttpMethod::Delete { url, headers, options } => {
+            send_request(reqwest::Method::DELETE, &url, None, None, &headers, &options).await?
+        }
+        HttpMethod::Diff { old, new, method, data, json, headers, ignore_headers, options } => {
+            let input = RequestInput { method, data, json, headers: &headers };
+            diff_responses(&old, &new, input, &ignore_headers, &options).await?
+        }
+        HttpMethod::Tui { env, session, connection, protobuf, schema } => {
+            let environment = match &env {
+                Some(name) => config::load_environment(name)?,
+                None => config::Environment::default(),
+            };
+            let settings = connection.client_settings(&environment)?;
+            let signers = environment.signers();
+            let oauth = match (&env, environment.oauth) {
+                (Some(name), Some(config)) => Some(OAuthSession::new(name, config)),
+                _ => None,
+            };
+            let session = session.as_deref().map(Session::load).transpose()?;
+            let protobuf = protobuf.settings()?;
+            let schemas = schema.schemas()?;
+            println!("Launching TUI mode...");
+            if let Err(e) = tui::run_tui(oauth, signers, session, settings, protobuf, schemas).await {
+                eprintln!("TUI error: {}", e);
+                return Err(ClientError::Tui(e));
             }
+            true
+        }
+    };
 
-            if !headers.is_empty() {
-                if let Ok(header_map) = parse_headers(&headers) {
-                    print_headers(&header_map, "Request Headers");
-                }
-            }
+    // Like diff(1), exit with 1 when responses differ from each other or
+    // from their snapshot.
+    if !matched {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// What to send, as given on the command line.
+#[derive(Clone)]
+struct RequestInput<'a> {
+    method: reqwest::Method,
+    data: Option<String>,
+    json: Option<String>,
+    headers: &'a [String],
+}
+
+/// What `fetch` ended with.
+enum Fetched {
+    Response(Box<reqwest::Response>, (Timings, Instant)),
+    /// The request was printed with `--offline`, or its body broke the
+    /// request schema; whether that counts as passing.
+    NotSent(bool),
+}
+
+/// Sends the request and prints the response. Returns false when the
+/// response does not match its `--snapshot` or either body breaks its schema;
+/// a request body that breaks its schema is not sent.
+async fn send_request(
+    method: reqwest::Method,
+    url: &str,
+    data: Option<String>,
+    json: Option<String>,
+    headers: &[String],
+    options: &RequestOptions,
+) -> Result<bool, ClientError> {
+    let charset = options.response_charset.as_deref().map(charset::lookup).transpose()?;
+    let protobuf = options.send.protobuf.settings()?;
+    let schemas = options.schema.schemas()?;
+    let filter = options.filter.as_deref().map(Filter::parse).transpose()?;
+    let input = RequestInput { method, data, json, headers };
+    let fetched = fetch(url, input, &options.send, &protobuf, schemas.request.as_ref()).await?;
+    let (response, timings) = match fetched {
+        Fetched::Response(response, timings) => (*response, timings),
+        Fetched::NotSent(passed) => return Ok(passed),
+    };
+    let timing = options.timings.then_some(timings);
+    print_response(response, timing, charset, &protobuf, schemas.response.as_ref(), filter.as_ref(), options).await
+}
+
+/// Builds the request with its body, auth, session, signatures and
+/// connection settings, sends it following redirects, and saves the
+/// session's cookies.
+async fn fetch(
+    url: &str,
+    input: RequestInput<'_>,
+    options: &SendOptions,
+    protobuf: &ProtobufSettings,
+    request_schema: Option<&Schema>,
+) -> Result<Fetched, ClientError> {
+    let RequestInput { method, data, json, headers } = input;
+    infoln!("{} {}", method, url);
+
+    if data.is_some() && json.is_some() {
+        return Err(ClientError::Json(JsonError::InvalidJSon(
+            "Cannot use both --data and --json options".to_string()
+        )));
+    }
 
-            let mut request = client.post(&url);
-            request = add_headers_to_request(request, &headers)?;
+    if options.send_as.is_some() && json.is_none() {
+        return Err(ClientError::Json(JsonError::InvalidJSon(
+            "--send-as encodes a body given with --json".to_string()
+        )));
+    }
+
+    let mut session = options.session.as_deref().map(Session::load).transpose()?;
+    let headers = match &mut session {
+        Some(session) => session.merge_headers(headers),
+        None => headers.to_vec(),
+    };
+
+    let environment = match &options.env {
+        Some(name) => config::load_environment(name)?,
+        None => config::Environment::default(),
+    };
+    let mut settings = options.connection.client_settings(&environment)?;
+    if options.verbose {
+        settings.wire = Some(Arc::default());
+    }
+
+    let jar = Arc::new(match &session {
+        Some(session) => session.cookie_jar()?,
+        None => CookieJar::new(),
+    });
+    if let Some(path) = &options.import_cookies {
+        for entry in cookies::read_netscape(path)? {
+            jar.insert(&entry)?;
+        }
+    }
+    let client = settings.build(jar.clone())?;
+
+    if let Ok(header_map) = parse_headers(&headers) {
+        print_headers(&header_map, "Request Headers");
+    }
+
+    let mut request = client.request(method, url);
+    request = add_headers_to_request(request, &headers)?;
+
+    // Credentials given on the command line replace the session's.
+    let auth = match (&options.auth, &mut session) {
+        (Some(credentials), Some(session)) => {
+            session.auth = auth::parse_auth(credentials, options.auth_type)?;
+            session.auth.clone()
+        }
+        (Some(credentials), None) => auth::parse_auth(credentials, options.auth_type)?,
+        (None, Some(session)) => session.auth.clone(),
+        (None, None) => auth::Auth::None,
+    };
+    request = auth::apply_auth(request, &auth);
+
+    // Explicit --auth wins over the environment's OAuth 2.0 settings.
+    let oauth = match (&options.env, &auth, &environment.oauth) {
+        (Some(name), auth::Auth::None, Some(config)) => Some(OAuthSession::new(name, config.clone())),
+        _ => None,
+    };
+
+    if let Some(spec) = &options.api_key {
+        request = auth::apply_api_key(request, &auth::parse_api_key(spec, ApiKeyLocation::Header)?);
+    }
+    if let Some(spec) = &options.api_key_query {
+        request = auth::apply_api_key(request, &auth::parse_api_key(spec, ApiKeyLocation::Query)?);
+    }
+
+    if let Some(schema) = request_schema {
+        let violations = match json.as_deref().or(data.as_deref()) {
+            Some(body) => schema.validate_text(body),
+            None => vec![Violation { pointer: String::new(), message: "the request has no body".to_string() }],
+        };
+        if !violations.is_empty() {
+            print_violations("Request body", schema, &violations);
+            infoln!("(the request was not sent)");
+            return Ok(Fetched::NotSent(false));
+        }
+    }
 
-            if let Some(json_data) = json {
-                json::validate_json(&json_data)?;
+    if let Some(json_data) = json {
+        json::validate_json(&json_data)?;
+        match options.send_as {
+            Some(format) => {
+                let encoded = format.encode(&json_data, protobuf)?;
+                infoln!("Sending JSON data as {} ({} bytes)", format, encoded.len());
+                request = request.header("Content-Type", format.content_type()).body(encoded);
+            }
+            None => {
                 request = request
                     .header("Content-Type", "application/json")
                     .body(json_data);
-                println!("Sending JSON data");
-            } else if let Some(raw_data) = data {
-                request = request.body(raw_data);
-                println!("Sending raw data");
+                infoln!("Sending JSON data");
             }
-            let response = request.send().await?;
-            print_response(response).await?;
-        }
-
-        HttpMethod::Put { url, data, json, headers } => {
-            println!("PUT {}", url);
-            
-            match (data.as_ref(), json.as_ref()) {
-                (Some(_), Some(_)) => {
-                    return Err(ClientError::Json(JsonError::InvalidJSon(
-                        "Cannot use both --data and --json options".to_string()
-                    )));
+        }
+    } else if let Some(raw_data) = data {
+        request = request.body(raw_data);
+        infoln!("Sending raw data");
+    }
+
+    let mut request = request.build()?;
+    // Signatures cover the body as sent, so compress first.
+    if let Some(encoding) = options.compress
+        && let Some((plain, compressed)) = compression::compress_body(&mut request, encoding)?
+    {
+        infoln!("Compressed the body with {} ({} -> {} bytes)", encoding, plain, compressed);
+    }
+    let signers = options.signers(&environment)?;
+    let sigv4 = options
+        .aws_sigv4
+        .as_deref()
+        .map(|spec| sigv4::Signer::from_spec(spec, options.aws_profile.as_deref(), options.aws_unsigned_payload))
+        .transpose()?;
+    let sender = Sender {
+        client: &client,
+        settings: &settings,
+        auth: &auth,
+        oauth: oauth.as_ref(),
+        signers: &signers,
+        sigv4: sigv4.as_ref(),
+    };
+    if options.offline {
+        sender.sign::<ClientError>(&mut request)?;
+        client.prepare(&mut request);
+        println!("{}", client.outgoing(&request));
+        if oauth.is_some() {
+            println!("(an OAuth 2.0 bearer token is added when sending)");
+        } else if matches!(auth, auth::Auth::Digest { .. }) {
+            println!("(Digest credentials are added after the server's challenge)");
+        }
+        return Ok(Fetched::NotSent(true));
+    }
+
+    let mut connections = ConnectionTracker::default();
+    let mut timings = (Timings::default(), Instant::now());
+    let send = async |request: reqwest::Request, with_auth: bool| -> Result<reqwest::Response, ClientError> {
+        let route = settings.proxy.route(request.url());
+        if options.verbose {
+            match client.unix_socket(request.url()) {
+                Some(socket) => infoln!("* {} {} via unix:{}", request.method(), request.url(), socket.display()),
+                None => infoln!("* {} {} via {}", request.method(), request.url(), route),
+            }
+        }
+        let Sent { response, attempts, timings: sent } = sender.send(request, with_auth).await;
+        timings = sent;
+        if options.verbose {
+            if settings.retry.retries > 0 {
+                for attempt in &attempts {
+                    infoln!("* {}", attempt);
                 }
-                _ => {}
             }
+            let resolutions = settings.dns.take_resolutions();
+            for resolution in &resolutions {
+                infoln!("* {}", resolution);
+            }
+            if let Ok(response) = &response {
+                let requests = match route {
+                    Route::Proxy { .. } => None,
+                    _ => connections.record(response.url(), response.remote_addr(), &resolutions),
+                };
+                print_connection(response, requests);
+            }
+            for message in settings.wire.iter().flat_map(|wire| wire.take()) {
+                infoln!("{}", message);
+            }
+        }
+        response
+    };
+    let result = redirect::follow(request, &options.redirect_policy(), send).await;
+    // The chain is also worth seeing when verification or pinning failed.
+    if options.show_cert {
+        print_certificates(&settings.certificates.chains());
+    }
+    let (response, hops) = result?;
+    print_redirects(&hops);
+
+    if let Some(session) = &mut session {
+        session.store_cookies(&jar);
+        session.save()?;
+    }
+    if let Some(path) = &options.export_cookies {
+        cookies::write_netscape(path, &jar.entries())?;
+    }
+    Ok(Fetched::Response(Box::new(response), timings))
+}
 
-            if !headers.is_empty() {
-                if let Ok(header_map) = parse_headers(&headers) {
-                    print_headers(&header_map, "Request Headers");
+/// Prints how two responses differ and returns whether they are the same.
+/// URLs are fetched like any other request, with the same method, body and
+/// options on both sides.
+async fn diff_responses(
+    old: &str,
+    new: &str,
+    input: RequestInput<'_>,
+    ignore_headers: &[String],
+    options: &SendOptions,
+) -> Result<bool, ClientError> {
+    let protobuf = options.protobuf.settings()?;
+    let mut ignored: Vec<String> = diff::VOLATILE_HEADERS.iter().map(|name| name.to_string()).collect();
+    ignored.extend(ignore_headers.iter().cloned());
+
+    let mut snapshots = Vec::new();
+    let mut not_sent = None;
+    for side in [old, new] {
+        let snapshot = if side.starts_with("http://") || side.starts_with("https://") {
+            let response = match fetch(side, input.clone(), options, &protobuf, None).await? {
+                Fetched::Response(response, _) => *response,
+                Fetched::NotSent(passed) => {
+                    not_sent = Some(not_sent.unwrap_or(true) && passed);
+                    continue;
                 }
-            }
+            };
+            let status = response.status().as_u16();
+            let header_map = response.headers().clone();
+            let body = Body::decode(&header_map, response.bytes().await?.to_vec(), false)?;
+            Snapshot::from_response(status, &header_list(&header_map), &body.bytes, &protobuf)
+        } else {
+            Snapshot::load(std::path::Path::new(side), &protobuf)?
+        };
+        snapshots.push(snapshot.without_headers(&ignored));
+    }
+    // With --offline there is nothing to compare.
+    if let Some(passed) = not_sent {
+        return Ok(passed);
+    }
 
-            let mut request = client.put(&url);
-            request = add_headers_to_request(request, &headers)?;
+    let difference = Difference::new(&snapshots[0], &snapshots[1]);
+    infoln!("{} -> {}: {}", old, new, difference.summary());
+    print!("{}", difference);
+    Ok(difference.is_empty())
+}
 
-            if let Some(json_data) = json {
-                json::validate_json(&json_data)?;
-                request = request
-                    .header("Content-Type", "application/json")
-                    .body(json_data);
-                println!("Sending JSON data");
-            } else if let Some(raw_data) = data {
-                request = request.body(raw_data);
-                println!("Sending raw data");
-            }
-            let response = request.send().await?;
-            print_response(response).await?;
-        }
-        HttpMethod::Delete { url, headers } => {
-            println!("DELETE {}", url);
-            let mut request = client.delete(&url);
-            request = add_headers_to_request(request, &headers)?;
-            let response = request.send().await?;
-            print_response(response).await?;
-        }
-        HttpMethod::Tui => {
-            println!("Launching TUI mode...");
-            if let Err(e) = tui::run_tui().await {
-                eprintln!("TUI error: {}", e);
-                return Err(ClientError::Tui(e));
+/// Header names and values as text, in the order received.
+fn header_list(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers.iter().map(|(name, value)| (name.to_string(), charset::header_value(value))).collect()
+}
+
+fn print_connection(response: &reqwest::Response, requests: Option<usize>) {
+    let version = response.version();
+    let Some(address) = response.remote_addr() else {
+        infoln!("* {:?}", version);
+        return;
+    };
+    match requests {
+        Some(1) | None => infoln!("* {:?} connected to {}", version, address),
+        Some(requests) => infoln!(
+            "* {:?} reusing the connection to {} (request {})",
+            version, address, requests
+        ),
+    }
+    // hyper does not expose stream ids, so the id is worked out from the
+    // request count and labelled as such.
+    if version == reqwest::Version::HTTP_2
+        && let Some(requests) = requests
+    {
+        infoln!("* HTTP/2 stream {} (estimated from the request count)", client::estimated_stream_id(requests));
+    }
+}
+
+fn print_certificates(chains: &[(String, Vec<Vec<u8>>)]) {
+    for (server, chain) in chains {
+        infoln!("Certificate chain for {}", server);
+        for (depth, certificate) in chain.iter().enumerate() {
+            for (index, (label, value)) in tls::describe_certificate(certificate).into_iter().enumerate() {
+                let prefix = if index == 0 { format!(" {}", depth) } else { "  ".to_string() };
+                infoln!("{} {:<8} {}", prefix, format!("{}:", label), value);
             }
         }
     }
+}
 
-    Ok(())
+fn print_redirects(hops: &[Hop]) {
+    if hops.is_empty() {
+        return;
+    }
+
+    infoln!("Redirects");
+    for hop in hops {
+        infoln!(
+            " {} {} -> {} {} ({}ms)",
+            hop.method,
+            hop.url,
+            hop.status,
+            hop.location,
+            hop.duration.as_millis()
+        );
+    }
 }
 
-async fn print_response(response: reqwest::Response) -> Result<(), ClientError> {
-    println!("Status: {}", response.status());
+/// Prints the response; with `timing`, the phases of the request whose
+/// headers arrived at the given instant are printed after the body. Binary
+/// bodies are only written when stdout is redirected, byte for byte;
+/// MessagePack, CBOR and Protobuf bodies are printed as JSON unless `--raw`.
+/// With `filter`, only its results are printed, and a body that is not JSON
+/// is an error. Returns false when the response does not match its snapshot
+/// or `schema`.
+async fn print_response(
+    response: reqwest::Response,
+    timing: Option<(Timings, Instant)>,
+    charset: Option<&'static encoding_rs::Encoding>,
+    protobuf: &ProtobufSettings,
+    schema: Option<&Schema>,
+    filter: Option<&Filter>,
+    options: &RequestOptions,
+) -> Result<bool, ClientError> {
+    infoln!("Status: {} ({:?})", response.status(), response.version());
+    let status = response.status().as_u16();
 
-    let important_headers = ["content-type", "content-length", "server"];
+    let important_headers = ["content-type", "content-encoding", "content-length", "server", "location"];
     let headers = response.headers();
     for header_name in &important_headers {
         if let Some(value) = headers.get(*header_name) {
-            println!("{}: {:?}", header_name, value);
+            infoln!("{}: {:?}", header_name, charset::header_value(value));
         }
     }
 
@@ -212,15 +1211,151 @@ async fn print_response(response: reqwest::Response) -> Result<(), ClientError>
         .unwrap_or("")
         .to_string(); // Convert to owned String
 
-    let body = response.text().await?;
+    let headers = headers.clone();
+    let bytes = response.bytes().await?;
+    let timings = timing.map(|(mut timings, first_byte)| {
+        timings.transfer = first_byte.elapsed();
+        timings
+    });
+    let decoded = Body::decode(&headers, bytes.to_vec(), options.raw)?;
+    if let Some(summary) = decoded.summary() {
+        infoln!("Body: {}", summary);
+    }
+    // Saving, snapshot checks and schema checks share one decoded copy.
+    let needs_snapshot = options.save.is_some() || options.snapshot.is_some() || schema.is_some();
+    let response_snapshot =
+        needs_snapshot.then(|| Snapshot::from_response(status, &header_list(&headers), &decoded.bytes, protobuf));
+    if let Some(path) = &options.save
+        && let Some(response) = &response_snapshot
+    {
+        response.save(path)?;
+        infoln!("Saved the response to {}", path.display());
+    }
+    let checked = schema.zip(response_snapshot.as_ref()).map(|(schema, response)| {
+        let violations = match &response.body {
+            SnapshotBody::Json(value) => schema.validate(value),
+            _ => vec![Violation { pointer: String::new(), message: "the body is not JSON".to_string() }],
+        };
+        (schema, violations)
+    });
+    let outcome = match (&options.snapshot, response_snapshot) {
+        (Some(name), Some(response)) => {
+            let rules = Rules::new(&options.snapshot_headers, &options.snapshot_ignore)?;
+            Some(snapshot::check(name, options.update_snapshot, rules, response)?)
+        }
+        _ => None,
+    };
 
-    println!("\nResponse Body:");
-    if content_type.contains("application/json") || json::is_json_like(&body) {
-        let pretty_json = pretty_print_json_safe(&body);
-        println!("{}", pretty_json);
-    } else {
-        println!("{}", body);
+    // Bodies that do not decode are treated like any other binary body.
+    let tree = match Serialization::from_content_type(&content_type) {
+        Some(format) if !options.raw && !options.hex => match format.decode(&decoded.bytes, &content_type, protobuf) {
+            Ok(tree) => {
+                infoln!("Format: {}", tree.description);
+                Some(tree)
+            }
+            Err(e) => {
+                infoln!("Format: not decoded: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    // A charset given on the command line means the body is text, unless it
+    // is still compressed.
+    let binary = decoded.encoded || (charset.is_none() && content::is_binary(&content_type, &decoded.bytes));
+    let text = (!options.hex && !binary && tree.is_none())
+        .then(|| charset::decode(&decoded.bytes, &content_type, charset));
+    if let Some(summary) = text.as_ref().and_then(charset::Text::summary) {
+        infoln!("Charset: {}", summary);
     }
 
-    Ok(())
+    if let Some(filter) = filter {
+        let value = match (tree, text) {
+            (Some(tree), _) => tree.value,
+            (None, Some(text)) => match Format::detect(&content_type, &text.text) {
+                Format::Json => serde_json::from_str(&text.text)
+                    .map_err(|e| FilterError::NotJson(format!("the body does not parse: {}", e)))?,
+                format => return Err(FilterError::NotJson(format!("this body is {}", format)).into()),
+            },
+            (None, None) => return Err(FilterError::NotJson("this body is binary".to_string()).into()),
+        };
+        let results = filter.apply(&value)?;
+        match results.len() {
+            1 => infoln!("\nResponse Body (1 filter result):"),
+            count => infoln!("\nResponse Body ({} filter results):", count),
+        }
+        println!("{}", filter::render(&results));
+        if let Some(timings) = &timings {
+            print_timings(timings);
+        }
+        return Ok(print_checks(outcome, checked));
+    }
+
+    infoln!("\nResponse Body:");
+    match (tree, text) {
+        _ if options.hex => print!("{}", content::hex_dump(&decoded.bytes, 16)),
+        (Some(tree), _) => println!("{}", tree.pretty()),
+        (None, Some(text)) => match Format::detect(&content_type, &text.text) {
+            Format::Html if options.html_text => println!("{}", html::html_to_text(&text.text)),
+            format => println!("{}", format::pretty_print(format, &text.text)),
+        },
+        (None, None) if output::is_terminal() => println!(
+            "(binary body not shown: {}; use --hex to view it or redirect the output to save it)",
+            content::describe(&content_type, &decoded.bytes)
+        ),
+        (None, None) => io::stdout().write_all(&decoded.bytes).map_err(ClientError::Output)?,
+    }
+
+    if let Some(timings) = &timings {
+        print_timings(timings);
+    }
+    Ok(print_checks(outcome, checked))
+}
+
+/// Reports the snapshot and schema checks after the body and returns
+/// whether they passed.
+fn print_checks(outcome: Option<Outcome>, checked: Option<(&Schema, Vec<Violation>)>) -> bool {
+    let mut passed = true;
+    if let Some((schema, violations)) = checked {
+        if violations.is_empty() {
+            infoln!("\nResponse body matches {}", schema.path.display());
+        } else {
+            print_violations("\nResponse body", schema, &violations);
+            passed = false;
+        }
+    }
+    match outcome {
+        Some(Outcome::Recorded(path)) => infoln!("\nSnapshot recorded in {}", path.display()),
+        Some(Outcome::Updated(path)) => infoln!("\nSnapshot updated in {}", path.display()),
+        Some(Outcome::Matched(path)) => infoln!("\nSnapshot matches {}", path.display()),
+        Some(Outcome::Mismatched(path, difference)) => {
+            infoln!("\nSnapshot differs from {}: {}", path.display(), difference.summary());
+            infoln!("{}", difference.to_string().trim_end());
+            infoln!("(run with --update-snapshot to accept the new response)");
+            passed = false;
+        }
+        None => {}
+    }
+    passed
+}
+
+fn print_violations(label: &str, schema: &Schema, violations: &[Violation]) {
+    let count = match violations.len() {
+        1 => "1 violation".to_string(),
+        count => format!("{} violations", count),
+    };
+    infoln!("{} does not match {} ({}): {}", label, schema.path.display(), schema.draft, count);
+    for violation in violations {
+        infoln!(" {}", violation);
+    }
+}
+
+fn print_timings(timings: &Timings) {
+    infoln!("\nTimings");
+    for (label, _, length) in timings.phases() {
+        infoln!(" {:<16} {:>10}", label, timing::millis(length));
+    }
+    infoln!(" {:<16} {:>10}", "Time to 1st byte", timing::millis(timings.first_byte()));
+    infoln!(" {:<16} {:>10}", "Total", timing::millis(timings.total()));
 }
\ No newline at end of file
@@ -0,0 +1,597 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::client::{Client, SendError};
+use crate::config::{config_dir, write_private};
+
+/// Tokens this close to expiry are refreshed before use.
+const EXPIRY_MARGIN_SECS: u64 = 30;
+
+#[derive(Debug)]
+pub enum OAuthError {
+    MissingSetting(&'static str),
+    Request(SendError),
+    TokenEndpoint(StatusCode, String),
+    Callback(String),
+    AuthorizationSet,
+    Io(std::io::Error),
+}
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OAuthError::MissingSetting(name) => {
+                write!(f, "OAuth 2.0 setting '{}' is required for this grant", name)
+            }
+            OAuthError::Request(e) => {
+                write!(f, "Token request failed: {}", e)
+            }
+            OAuthError::TokenEndpoint(status, body) => {
+                write!(f, "Token endpoint returned {}: {}", status, body)
+            }
+            OAuthError::Callback(reason) => {
+                write!(f, "Authorization failed: {}", reason)
+            }
+            OAuthError::AuthorizationSet => {
+                write!(f, "The OAuth 2.0 token would replace the Authorization header another auth option set")
+            }
+            OAuthError::Io(e) => {
+                write!(f, "OAuth 2.0 I/O error: {}", e)
+            }
+        }
+    }
+}
+
+impl Error for OAuthError {}
+
+impl From<reqwest::Error> for OAuthError {
+    fn from(error: reqwest::Error) -> Self {
+        OAuthError::Request(SendError::Request(error))
+    }
+}
+
+impl From<SendError> for OAuthError {
+    fn from(error: SendError) -> Self {
+        OAuthError::Request(error)
+    }
+}
+
+impl From<std::io::Error> for OAuthError {
+    fn from(error: std::io::Error) -> Self {
+        OAuthError::Io(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantType {
+    ClientCredentials,
+    Password,
+    RefreshToken,
+    AuthorizationCode,
+}
+
+/// The `oauth` section of an environment in `environments.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthConfig {
+    pub grant: GrantType,
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub refresh_token: Option<String>,
+    pub authorization_url: Option<String>,
+    #[serde(default = "default_redirect_port")]
+    pub redirect_port: u16,
+}
+
+fn default_redirect_port() -> u16 {
+    8765
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Token {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// Unix timestamp after which the access token is no longer valid.
+    pub expires_at: Option<u64>,
+}
+
+impl Token {
+    fn is_fresh(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now() + EXPIRY_MARGIN_SECS < expires_at,
+            None => true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+/// OAuth 2.0 settings of one environment together with its on-disk token cache.
+#[derive(Debug, Clone)]
+pub struct OAuthSession {
+    pub environment: String,
+    pub config: OAuthConfig,
+    /// Set by the TUI, which cannot print the authorization URL to the terminal.
+    pub quiet: bool,
+    cache_dir: PathBuf,
+}
+
+impl OAuthSession {
+    pub fn new(environment: &str, config: OAuthConfig) -> Self {
+        OAuthSession {
+            environment: environment.to_string(),
+            config,
+            quiet: false,
+            cache_dir: config_dir().join("tokens"),
+        }
+    }
+
+    /// One file per environment, grant, token endpoint, client, scope and
+    /// user, so changing any of them never picks up a token issued for the
+    /// old value.
+    fn cache_path(&self) -> PathBuf {
+        let grant = format!("{:?}", self.config.grant);
+        let mut hasher = Sha256::new();
+        for part in [
+            self.environment.as_str(),
+            grant.as_str(),
+            self.config.token_url.as_str(),
+            self.config.client_id.as_str(),
+            self.config.scope.as_deref().unwrap_or(""),
+            self.config.username.as_deref().unwrap_or(""),
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        let hash: String = hasher.finalize()[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.cache_dir.join(format!("{}-{}.json", self.environment, hash))
+    }
+
+    fn load_cached(&self) -> Option<Token> {
+        let text = std::fs::read_to_string(self.cache_path()).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn store(&self, token: &Token) -> Result<(), OAuthError> {
+        let text = serde_json::to_string_pretty(token).map_err(std::io::Error::from)?;
+        write_private(&self.cache_path(), text.as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns a usable access token, preferring the cache, then a refresh,
+    /// then the configured grant.
+    pub async fn access_token(&self, client: &reqwest::Client) -> Result<Token, OAuthError> {
+        match self.load_cached() {
+            Some(token) if token.is_fresh() => Ok(token),
+            cached => self.renew(client, cached).await,
+        }
+    }
+
+    async fn renew(&self, client: &reqwest::Client, cached: Option<Token>) -> Result<Token, OAuthError> {
+        let refresh_token = cached
+            .and_then(|token| token.refresh_token)
+            .or_else(|| self.config.refresh_token.clone());
+
+        let token = match refresh_token {
+            Some(refresh_token) => match self.refresh(client, &refresh_token).await {
+                Ok(token) => token,
+                Err(_) if self.config.grant != GrantType::RefreshToken => self.acquire(client).await?,
+                Err(e) => return Err(e),
+            },
+            None => self.acquire(client).await?,
+        };
+
+        self.store(&token)?;
+        Ok(token)
+    }
+
+    async fn acquire(&self, client: &reqwest::Client) -> Result<Token, OAuthError> {
+        let config = &self.config;
+        let mut form = Vec::new();
+
+        match config.grant {
+            GrantType::ClientCredentials => {
+                form.push(("grant_type", "client_credentials".to_string()));
+            }
+            GrantType::Password => {
+                let username = config.username.clone().ok_or(OAuthError::MissingSetting("username"))?;
+                let password = config.password.clone().ok_or(OAuthError::MissingSetting("password"))?;
+                form.push(("grant_type", "password".to_string()));
+                form.push(("username", username));
+                form.push(("password", password));
+            }
+            GrantType::RefreshToken => {
+                return Err(OAuthError::MissingSetting("refresh_token"));
+            }
+            GrantType::AuthorizationCode => {
+                return self.authorize(client).await;
+            }
+        }
+
+        if let Some(scope) = &config.scope {
+            form.push(("scope", scope.clone()));
+        }
+        self.request_token(client, form).await
+    }
+
+    async fn refresh(&self, client: &reqwest::Client, refresh_token: &str) -> Result<Token, OAuthError> {
+        let form = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.to_string()),
+        ];
+        let mut token = self.request_token(client, form).await?;
+        // Servers may omit the refresh token when it is not rotated.
+        if token.refresh_token.is_none() {
+            token.refresh_token = Some(refresh_token.to_string());
+        }
+        Ok(token)
+    }
+
+    /// Authorization code grant with PKCE, receiving the code on a temporary
+    /// localhost listener.
+    async fn authorize(&self, client: &reqwest::Client) -> Result<Token, OAuthError> {
+        let config = &self.config;
+        let authorization_url = config
+            .authorization_url
+            .as_deref()
+            .ok_or(OAuthError::MissingSetting("authorization_url"))?;
+
+        let verifier = random_string(64);
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        let state = random_string(24);
+        let redirect_uri = format!("http://127.0.0.1:{}/callback", config.redirect_port);
+
+        let mut url = reqwest::Url::parse(authorization_url)
+            .map_err(|e| OAuthError::Callback(format!("invalid authorization_url: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", &redirect_uri)
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+        if let Some(scope) = &config.scope {
+            url.query_pairs_mut().append_pair("scope", scope);
+        }
+
+        let listener = TcpListener::bind(("127.0.0.1", config.redirect_port)).await?;
+        if !self.quiet {
+            eprintln!("Open this URL in your browser to authorize:\n{}", url);
+        }
+        open_browser(url.as_str());
+
+        let code = tokio::time::timeout(Duration::from_secs(300), receive_code(&listener, &state))
+            .await
+            .map_err(|_| OAuthError::Callback("timed out waiting for the redirect".to_string()))??;
+
+        let form = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", verifier),
+        ];
+        self.request_token(client, form).await
+    }
+
+    async fn request_token(
+        &self,
+        client: &reqwest::Client,
+        mut form: Vec<(&str, String)>,
+    ) -> Result<Token, OAuthError> {
+        let config = &self.config;
+        let mut request = client.post(&config.token_url);
+        match &config.client_secret {
+            Some(secret) => request = request.basic_auth(&config.client_id, Some(secret)),
+            None => form.push(("client_id", config.client_id.clone())),
+        }
+
+        let response = request.form(&form).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(OAuthError::TokenEndpoint(status, body));
+        }
+
+        let parsed: TokenResponse = serde_json::from_str(&body)
+            .map_err(|e| OAuthError::TokenEndpoint(status, format!("{}: {}", e, body)))?;
+        Ok(Token {
+            access_token: parsed.access_token,
+            refresh_token: parsed.refresh_token,
+            expires_at: parsed.expires_in.map(|secs| now() + secs),
+        })
+    }
+
+    /// Sends `request` with a Bearer token, refreshing and retrying once if
+    /// the server rejects the token with 401.
+    pub async fn send(
+        &self,
+        client: &Client,
+        mut request: reqwest::Request,
+    ) -> Result<reqwest::Response, OAuthError> {
+        if request.headers().contains_key(AUTHORIZATION) {
+            return Err(OAuthError::AuthorizationSet);
+        }
+        let token = self.access_token(client.http()).await?;
+        let retry = request.try_clone();
+        set_bearer(&mut request, &token);
+
+        let response = client.execute(request).await?;
+        let mut retry = match retry {
+            Some(retry) if response.status() == StatusCode::UNAUTHORIZED => retry,
+            _ => return Ok(response),
+        };
+
+        let token = self.renew(client.http(), Some(token)).await?;
+        set_bearer(&mut retry, &token);
+        Ok(client.execute(retry).await?)
+    }
+}
+
+fn set_bearer(request: &mut reqwest::Request, token: &Token) {
+    if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token.access_token)) {
+        request.headers_mut().insert(AUTHORIZATION, value);
+    }
+}
+
+/// Waits for the browser redirect and returns the authorization code.
+async fn receive_code(listener: &TcpListener, expected_state: &str) -> Result<String, OAuthError> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut buffer = vec![0u8; 8192];
+        let read = stream.read(&mut buffer).await?;
+        let request = String::from_utf8_lossy(&buffer[..read]);
+
+        let target = request.lines().next().and_then(|line| line.split_whitespace().nth(1));
+        let Some(target) = target.filter(|t| t.starts_with("/callback")) else {
+            // Browsers also ask for /favicon.ico and the like.
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await?;
+            continue;
+        };
+
+        let url = reqwest::Url::parse(&format!("http://localhost{}", target))
+            .map_err(|e| OAuthError::Callback(e.to_string()))?;
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+
+        let result = if let Some(error) = param("error") {
+            Err(OAuthError::Callback(error))
+        } else if param("state").as_deref() != Some(expected_state) {
+            Err(OAuthError::Callback("state mismatch".to_string()))
+        } else {
+            param("code").ok_or_else(|| OAuthError::Callback("no code in redirect".to_string()))
+        };
+
+        let page = match &result {
+            Ok(_) => "Authorization complete. You can close this window.",
+            Err(_) => "Authorization failed. Check the terminal for details.",
+        };
+        let reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            page.len(),
+            page
+        );
+        stream.write_all(reply.as_bytes()).await?;
+        return result;
+    }
+}
+
+fn open_browser(url: &str) {
+    let command = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    let _ = std::process::Command::new(command)
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{reply, Server};
+
+    /// A token endpoint answering each request with the next canned reply.
+    async fn token_endpoint(replies: Vec<(u16, &str)>) -> (String, Server) {
+        let replies = replies
+            .into_iter()
+            .map(|(status, body)| reply(status, &[("Content-Type", "application/json")], body.as_bytes()))
+            .collect();
+        let server = Server::start(replies).await;
+        (server.url("/token"), server)
+    }
+
+    fn forms(server: &Server) -> Vec<String> {
+        server.received().iter().map(|request| String::from_utf8_lossy(&request.body).into_owned()).collect()
+    }
+
+    fn session(name: &str, token_url: &str, grant: GrantType) -> OAuthSession {
+        let config = OAuthConfig {
+            grant,
+            token_url: token_url.to_string(),
+            client_id: "client".to_string(),
+            client_secret: Some("secret".to_string()),
+            scope: Some("read".to_string()),
+            username: None,
+            password: None,
+            refresh_token: None,
+            authorization_url: None,
+            redirect_port: default_redirect_port(),
+        };
+        let mut session = OAuthSession::new("test", config);
+        session.cache_dir = std::env::temp_dir().join(format!("http-client-oauth-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&session.cache_dir);
+        session
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().no_proxy().build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn acquires_and_caches_token() {
+        let (url, server) = token_endpoint(vec![(200, r#"{"access_token":"abc","expires_in":3600}"#)]).await;
+        let session = session("acquire", &url, GrantType::ClientCredentials);
+
+        let token = session.access_token(&client()).await.unwrap();
+        assert_eq!(token.access_token, "abc");
+        assert!(token.expires_at.unwrap() > now() + 3000);
+        assert_eq!(forms(&server), ["grant_type=client_credentials&scope=read"]);
+        assert_eq!(server.received()[0].header("authorization"), Some("Basic Y2xpZW50OnNlY3JldA=="));
+
+        // Served from the cache: the endpoint only answers once.
+        let token = session.access_token(&client()).await.unwrap();
+        assert_eq!(token.access_token, "abc");
+        assert_eq!(server.received().len(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(session.cache_path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn refreshes_expired_token() {
+        let (url, server) = token_endpoint(vec![(200, r#"{"access_token":"new","expires_in":60}"#)]).await;
+        let session = session("refresh", &url, GrantType::ClientCredentials);
+        let expired = Token {
+            access_token: "old".to_string(),
+            refresh_token: Some("r1".to_string()),
+            expires_at: Some(now() + EXPIRY_MARGIN_SECS / 2),
+        };
+        session.store(&expired).unwrap();
+
+        let token = session.access_token(&client()).await.unwrap();
+        assert_eq!(token.access_token, "new");
+        // Kept when the server does not rotate it.
+        assert_eq!(token.refresh_token.as_deref(), Some("r1"));
+        assert_eq!(forms(&server), ["grant_type=refresh_token&refresh_token=r1"]);
+        assert_eq!(session.load_cached().unwrap().access_token, "new");
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_grant_when_refresh_fails() {
+        let (url, server) = token_endpoint(vec![
+            (400, r#"{"error":"invalid_grant"}"#),
+            (200, r#"{"access_token":"fresh"}"#),
+        ])
+        .await;
+        let session = session("fallback", &url, GrantType::ClientCredentials);
+        let expired = Token {
+            access_token: "old".to_string(),
+            refresh_token: Some("revoked".to_string()),
+            expires_at: Some(now().saturating_sub(10)),
+        };
+        session.store(&expired).unwrap();
+
+        let token = session.access_token(&client()).await.unwrap();
+        assert_eq!(token.access_token, "fresh");
+        assert_eq!(token.expires_at, None);
+        assert_eq!(server.received().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn reports_token_endpoint_errors() {
+        let (url, _) = token_endpoint(vec![(401, r#"{"error":"invalid_client"}"#)]).await;
+        let session = session("error", &url, GrantType::ClientCredentials);
+        match session.access_token(&client()).await {
+            Err(OAuthError::TokenEndpoint(status, body)) => {
+                assert_eq!(status, StatusCode::UNAUTHORIZED);
+                assert!(body.contains("invalid_client"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(session.load_cached().is_none());
+    }
+
+    #[test]
+    fn cache_key_covers_grant_endpoint_client_scope_and_user() {
+        let base = session("key", "https://a.example/token", GrantType::ClientCredentials);
+        let mut other_scope = base.clone();
+        other_scope.config.scope = Some("write".to_string());
+        let mut other_client = base.clone();
+        other_client.config.client_id = "other".to_string();
+        let other_url = session("key", "https://b.example/token", GrantType::ClientCredentials);
+        let other_grant = session("key", "https://a.example/token", GrantType::Password);
+        let mut other_user = other_grant.clone();
+        other_user.config.username = Some("bob".to_string());
+
+        let paths =
+            [&base, &other_scope, &other_client, &other_url, &other_grant, &other_user].map(OAuthSession::cache_path);
+        for (i, path) in paths.iter().enumerate() {
+            assert!(path.file_name().unwrap().to_string_lossy().starts_with("test-"));
+            assert!(paths[i + 1..].iter().all(|other| other != path));
+        }
+        assert_eq!(base.cache_path(), base.clone().cache_path());
+    }
+
+    #[test]
+    fn changed_grant_or_user_misses_cache() {
+        let mut session = session("miss", "https://a.example/token", GrantType::Password);
+        session.config.username = Some("alice".to_string());
+        let token = Token { access_token: "alice".to_string(), refresh_token: None, expires_at: None };
+        session.store(&token).unwrap();
+        assert_eq!(session.load_cached().unwrap().access_token, "alice");
+
+        let mut other_user = session.clone();
+        other_user.config.username = Some("bob".to_string());
+        assert!(other_user.load_cached().is_none());
+
+        let mut other_grant = session.clone();
+        other_grant.config.grant = GrantType::ClientCredentials;
+        assert!(other_grant.load_cached().is_none());
+    }
+
+    #[test]
+    fn password_grant_requires_credentials() {
+        let session = session("password", "http://127.0.0.1:9/token", GrantType::Password);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(session.acquire(&client()));
+        assert!(matches!(result, Err(OAuthError::MissingSetting("username"))));
+    }
+}
@@ -0,0 +1,21 @@
+use std::io::{self, IsTerminal};
+
+/// Whether stdout is a terminal. When it is redirected, only the response
+/// body is written there, so files and pipes get nothing else.
+pub fn is_terminal() -> bool {
+    io::stdout().is_terminal()
+}
+
+/// `println!` for everything but the response body: stdout on a terminal,
+/// stderr when stdout is redirected.
+macro_rules! infoln {
+    ($($arg:tt)*) => {
+        if $crate::output::is_terminal() {
+            println!($($arg)*);
+        } else {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use infoln;
@@ -0,0 +1,350 @@
+/// Text shown a screen at a time: scrolled by rows, wrapped or scrolled
+/// sideways, and searched. Positions are in chars, not bytes.
+#[derive(Debug, Clone)]
+pub struct Pager {
+    /// First row shown.
+    pub top: usize,
+    /// First column shown when lines are not wrapped.
+    pub left: usize,
+    pub wrap: bool,
+    pub search: String,
+    /// Index of the selected match.
+    pub current: Option<usize>,
+    /// Size of the text area at the last draw, without line numbers.
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for Pager {
+    fn default() -> Self {
+        Pager { top: 0, left: 0, wrap: true, search: String::new(), current: None, width: 80, height: 20 }
+    }
+}
+
+/// A row on screen: a line, or part of one when wrapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Row {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Where the search text was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Columns for line numbers and the space after them.
+pub fn gutter_width(text: &str) -> usize {
+    text.lines().count().max(1).to_string().len() + 1
+}
+
+impl Pager {
+    /// Starts again at the top of new text, keeping the wrap setting.
+    pub fn reset(&mut self) {
+        *self = Pager { wrap: self.wrap, width: self.width, height: self.height, ..Pager::default() };
+    }
+
+    pub fn rows(&self, text: &str) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (line, content) in text.lines().enumerate() {
+            let length = content.chars().count();
+            if self.wrap && self.width > 0 && length > self.width {
+                for start in (0..length).step_by(self.width) {
+                    rows.push(Row { line, start, end: (start + self.width).min(length) });
+                }
+            } else {
+                rows.push(Row { line, start: 0, end: length });
+            }
+        }
+        rows
+    }
+
+    fn last_top(&self, text: &str) -> usize {
+        self.rows(text).len().saturating_sub(self.height)
+    }
+
+    /// Keeps the view inside the text after it changed or was resized.
+    pub fn clamp(&mut self, text: &str) {
+        self.top = self.top.min(self.last_top(text));
+    }
+
+    pub fn scroll(&mut self, text: &str, offset: isize) {
+        self.top = self.top.saturating_add_signed(offset).min(self.last_top(text));
+    }
+
+    pub fn scroll_page(&mut self, text: &str, pages: isize) {
+        self.scroll(text, pages * self.height.max(1) as isize);
+    }
+
+    pub fn scroll_to_end(&mut self, text: &str) {
+        self.top = self.last_top(text);
+    }
+
+    /// Scrolls sideways, up to where the longest line ends.
+    pub fn scroll_sideways(&mut self, text: &str, offset: isize) {
+        if self.wrap {
+            return;
+        }
+        let longest = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+        self.left = self.left.saturating_add_signed(offset).min(longest.saturating_sub(self.width));
+    }
+
+    pub fn toggle_wrap(&mut self, text: &str) {
+        // Keep the line at the top in view.
+        let line = self.rows(text).get(self.top).map_or(0, |row| row.line);
+        self.wrap = !self.wrap;
+        self.left = 0;
+        self.top = self.rows(text).iter().position(|row| row.line == line).unwrap_or(0);
+        self.clamp(text);
+    }
+
+    /// Every match of the search text, ignoring case.
+    pub fn matches(&self, text: &str) -> Vec<Match> {
+        let query: Vec<char> = self.search.chars().flat_map(char::to_lowercase).collect();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut matches = Vec::new();
+        for (line, content) in text.lines().enumerate() {
+            // Chars that lowercase to several are compared by their first.
+            let chars: Vec<char> = content.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect();
+            let mut start = 0;
+            while start + query.len() <= chars.len() {
+                if chars[start..start + query.len()] == query[..] {
+                    matches.push(Match { line, start, end: start + query.len() });
+                    start += query.len();
+                } else {
+                    start += 1;
+                }
+            }
+        }
+        matches
+    }
+
+    /// Selects the first match from the top of the view as the search text
+    /// is typed. Returns how many matches there are.
+    pub fn search_from_top(&mut self, text: &str) -> usize {
+        let matches = self.matches(text);
+        let rows = self.rows(text);
+        let top = rows.get(self.top).map_or((0, 0), |row| (row.line, row.start));
+        self.current = matches
+            .iter()
+            .position(|found| (found.line, found.start) >= top)
+            .or((!matches.is_empty()).then_some(0));
+        if let Some(current) = self.current {
+            self.reveal(&rows, matches[current]);
+        }
+        matches.len()
+    }
+
+    /// Selects the next or previous match, wrapping around, and scrolls to
+    /// it. Returns the selected match number and how many there are.
+    pub fn find(&mut self, text: &str, forward: bool) -> Option<(usize, usize)> {
+        let matches = self.matches(text);
+        if matches.is_empty() {
+            self.current = None;
+            return None;
+        }
+        let count = matches.len();
+        let current = match (self.current.filter(|current| *current < count), forward) {
+            (Some(current), true) => (current + 1) % count,
+            (Some(current), false) => (current + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.current = Some(current);
+        self.reveal(&self.rows(text), matches[current]);
+        Some((current + 1, count))
+    }
+
+    /// Scrolls a match into view, a third of the way down.
+    fn reveal(&mut self, rows: &[Row], found: Match) {
+        let row = rows
+            .iter()
+            .position(|row| row.line == found.line && found.start < row.end.max(row.start + 1))
+            .unwrap_or(0);
+        if row < self.top || row >= self.top + self.height {
+            self.top = row.saturating_sub(self.height / 3).min(rows.len().saturating_sub(self.height));
+        }
+        if !self.wrap && (found.start < self.left || found.end > self.left + self.width) {
+            self.left = found.start.saturating_sub(self.width / 3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pager(width: usize, height: usize) -> Pager {
+        Pager { width, height, ..Pager::default() }
+    }
+
+    /// Lines `line 0` to `line {count - 1}`.
+    fn numbered(count: usize) -> String {
+        (0..count).map(|line| format!("line {}", line)).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn wraps_long_lines_by_chars() {
+        let mut pager = pager(4, 10);
+        let text = "abcdéfghij\n\nxy";
+        assert_eq!(
+            pager.rows(text),
+            [
+                Row { line: 0, start: 0, end: 4 },
+                Row { line: 0, start: 4, end: 8 },
+                Row { line: 0, start: 8, end: 10 },
+                Row { line: 1, start: 0, end: 0 },
+                Row { line: 2, start: 0, end: 2 },
+            ]
+        );
+        pager.wrap = false;
+        assert_eq!(pager.rows(text)[0], Row { line: 0, start: 0, end: 10 });
+        assert_eq!(pager.rows(text).len(), 3);
+    }
+
+    #[test]
+    fn sizes_the_gutter_for_the_last_line_number() {
+        assert_eq!(gutter_width(""), 2);
+        assert_eq!(gutter_width(&numbered(9)), 2);
+        assert_eq!(gutter_width(&numbered(10)), 3);
+        assert_eq!(gutter_width(&numbered(100)), 4);
+    }
+
+    #[test]
+    fn scrolls_within_the_text() {
+        let text = numbered(50);
+        let mut pager = pager(80, 10);
+        pager.scroll(&text, -5);
+        assert_eq!(pager.top, 0);
+        pager.scroll(&text, 15);
+        assert_eq!(pager.top, 15);
+        pager.scroll_page(&text, 2);
+        assert_eq!(pager.top, 35);
+        pager.scroll_page(&text, 1);
+        assert_eq!(pager.top, 40, "the last page stays full");
+        pager.scroll_page(&text, -10);
+        assert_eq!(pager.top, 0);
+        pager.scroll_to_end(&text);
+        assert_eq!(pager.top, 40);
+
+        // Shorter text or a taller view pull the view back.
+        pager.clamp(&numbered(45));
+        assert_eq!(pager.top, 35);
+        pager.height = 100;
+        pager.clamp(&text);
+        assert_eq!(pager.top, 0);
+    }
+
+    #[test]
+    fn scrolls_sideways_only_without_wrapping() {
+        let text = format!("{}\nshort", "x".repeat(30));
+        let mut pager = pager(10, 5);
+        pager.scroll_sideways(&text, 5);
+        assert_eq!(pager.left, 0);
+        pager.toggle_wrap(&text);
+        pager.scroll_sideways(&text, 5);
+        assert_eq!(pager.left, 5);
+        pager.scroll_sideways(&text, 100);
+        assert_eq!(pager.left, 20);
+        pager.scroll_sideways(&text, -100);
+        assert_eq!(pager.left, 0);
+    }
+
+    #[test]
+    fn keeps_the_top_line_when_wrapping_changes() {
+        // Each line wraps onto three rows.
+        let text = (0..20).map(|line| format!("{:02}{}", line, "-".repeat(8))).collect::<Vec<_>>().join("\n");
+        let mut pager = pager(4, 5);
+        pager.scroll(&text, 9);
+        assert_eq!(pager.rows(&text)[pager.top].line, 3);
+        pager.toggle_wrap(&text);
+        assert_eq!((pager.wrap, pager.top), (false, 3));
+        pager.left = 4;
+        pager.toggle_wrap(&text);
+        assert_eq!((pager.wrap, pager.top, pager.left), (true, 9, 0));
+    }
+
+    #[test]
+    fn resets_for_new_text() {
+        let mut pager = Pager { top: 7, left: 3, wrap: false, search: "x".to_string(), current: Some(1), width: 30, height: 9 };
+        pager.reset();
+        assert_eq!((pager.top, pager.left, pager.wrap, pager.current), (0, 0, false, None));
+        assert_eq!((pager.width, pager.height), (30, 9));
+        assert!(pager.search.is_empty());
+    }
+
+    #[test]
+    fn finds_matches_ignoring_case() {
+        let mut pager = pager(80, 10);
+        assert!(pager.matches("anything").is_empty());
+        pager.search = "AB".to_string();
+        assert_eq!(
+            pager.matches("abab xaB\nÄb\nnone"),
+            [
+                Match { line: 0, start: 0, end: 2 },
+                Match { line: 0, start: 2, end: 4 },
+                Match { line: 0, start: 6, end: 8 },
+            ]
+        );
+        pager.search = "äB".to_string();
+        assert_eq!(pager.matches("xÄb"), [Match { line: 0, start: 1, end: 3 }]);
+        pager.search = "aa".to_string();
+        assert_eq!(pager.matches("aaa").len(), 1, "matches do not overlap");
+    }
+
+    #[test]
+    fn steps_through_matches_and_wraps_around() {
+        let text = numbered(100);
+        let mut pager = pager(80, 10);
+        pager.search = "line 5".to_string();
+        // line 5 and line 50 to 59.
+        assert_eq!(pager.find(&text, true), Some((1, 11)));
+        assert_eq!(pager.top, 0, "already in view");
+        assert_eq!(pager.find(&text, true), Some((2, 11)));
+        assert_eq!(pager.top, 47, "a third of the way down");
+        assert_eq!(pager.find(&text, false), Some((1, 11)));
+        assert_eq!(pager.find(&text, false), Some((11, 11)));
+        assert_eq!(pager.find(&text, true), Some((1, 11)));
+
+        pager.search = "missing".to_string();
+        assert_eq!(pager.find(&text, true), None);
+        assert_eq!(pager.current, None);
+    }
+
+    #[test]
+    fn searches_from_the_top_of_the_view() {
+        let text = numbered(100);
+        let mut pager = pager(80, 10);
+        pager.scroll(&text, 30);
+        pager.search = "line 2".to_string();
+        // line 2 and line 20 to 29 all sit above the view, so the first wins.
+        assert_eq!(pager.search_from_top(&text), 11);
+        assert_eq!(pager.current, Some(0));
+        assert_eq!(pager.top, 0);
+
+        pager.scroll(&text, 45);
+        pager.search = "line 7".to_string();
+        assert_eq!(pager.search_from_top(&text), 11);
+        assert_eq!(pager.current, Some(1), "line 70 is the first below the top");
+        assert_eq!(pager.top, 67);
+    }
+
+    #[test]
+    fn reveals_matches_sideways() {
+        let text = format!("{}needle", " ".repeat(40));
+        let mut unwrapped = Pager { wrap: false, search: "needle".to_string(), ..pager(10, 5) };
+        unwrapped.find(&text, true);
+        assert_eq!(unwrapped.left, 37);
+
+        // Wrapped, the row holding the match is scrolled to instead.
+        let mut wrapped = Pager { search: "needle".to_string(), ..pager(10, 2) };
+        wrapped.find(&text, true);
+        assert_eq!((wrapped.top, wrapped.left), (3, 0));
+    }
+}
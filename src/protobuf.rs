@@ -0,0 +1,387 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use prost_reflect::prost::{DecodeError, Message};
+use prost_reflect::{DescriptorError, DescriptorPool, DynamicMessage, MessageDescriptor, SerializeOptions};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::content;
+use crate::serialization::Ordered;
+
+/// Nesting depth up to which schema-less decoding tries to read a
+/// length-delimited field as an embedded message.
+const MAX_WIRE_DEPTH: usize = 32;
+
+/// Largest field number the wire format allows.
+const MAX_FIELD_NUMBER: u64 = (1 << 29) - 1;
+
+#[derive(Debug)]
+pub enum ProtobufError {
+    Read(PathBuf, io::Error),
+    Compile(protox::Error),
+    Descriptor(String, DescriptorError),
+    UnknownMessage(String, Vec<String>),
+    MissingSchema,
+    MissingRequestMessage,
+    Decode(String, DecodeError),
+    Json(String, serde_json::Error),
+    Wire,
+}
+
+impl fmt::Display for ProtobufError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtobufError::Read(path, e) => write!(f, "Could not read '{}': {}", path.display(), e),
+            ProtobufError::Compile(e) => write!(f, "Could not compile the .proto files: {}", e),
+            ProtobufError::Descriptor(source, e) => write!(f, "Invalid descriptors in {}: {}", source, e),
+            ProtobufError::UnknownMessage(name, known) => {
+                write!(f, "Unknown message type '{}'", name)?;
+                match known.as_slice() {
+                    [] => write!(f, "; the schema has no messages"),
+                    known => write!(f, ". Known types: {}", known.join(", ")),
+                }
+            }
+            ProtobufError::MissingSchema => {
+                write!(f, "Protobuf message types need a schema: use --proto with a .proto file or descriptor set")
+            }
+            ProtobufError::MissingRequestMessage => {
+                write!(f, "Sending Protobuf needs the message type of the body: use --proto-request")
+            }
+            ProtobufError::Decode(name, e) => write!(f, "Body is not a valid '{}' message: {}", name, e),
+            ProtobufError::Json(name, e) => write!(f, "Body does not match message '{}': {}", name, e),
+            ProtobufError::Wire => write!(f, "Body is not in the Protobuf wire format"),
+        }
+    }
+}
+
+impl Error for ProtobufError {}
+
+/// Message types loaded from `.proto` files and descriptor sets.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pool: DescriptorPool,
+}
+
+impl Schema {
+    /// Loads `.proto` files, compiled with their own directories as import
+    /// paths, and descriptor sets (`protoc --descriptor_set_out`), told apart
+    /// by extension.
+    pub fn load(paths: &[PathBuf]) -> Result<Schema, ProtobufError> {
+        let mut pool = DescriptorPool::new();
+        let (sources, sets): (Vec<&PathBuf>, Vec<&PathBuf>) = paths
+            .iter()
+            .partition(|path| path.extension().is_some_and(|extension| extension == "proto"));
+
+        for path in sets {
+            let bytes = fs::read(path).map_err(|e| ProtobufError::Read(path.clone(), e))?;
+            pool.decode_file_descriptor_set(bytes.as_slice())
+                .map_err(|e| ProtobufError::Descriptor(path.display().to_string(), e))?;
+        }
+        if !sources.is_empty() {
+            let mut includes: Vec<&Path> = Vec::new();
+            for path in &sources {
+                let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty());
+                let directory = directory.unwrap_or(Path::new("."));
+                if !includes.contains(&directory) {
+                    includes.push(directory);
+                }
+            }
+            let set = protox::compile(&sources, &includes).map_err(ProtobufError::Compile)?;
+            pool.add_file_descriptor_set(set)
+                .map_err(|e| ProtobufError::Descriptor("the .proto files".to_string(), e))?;
+        }
+        Ok(Schema { pool })
+    }
+
+    /// Finds a message by full name (`shop.v1.Order`), or by its own name
+    /// when only one message has it.
+    pub fn message(&self, name: &str) -> Result<MessageDescriptor, ProtobufError> {
+        let name = name.trim_start_matches('.');
+        if let Some(message) = self.pool.get_message_by_name(name) {
+            return Ok(message);
+        }
+        let mut matches = self.pool.all_messages().filter(|message| message.name() == name);
+        match (matches.next(), matches.next()) {
+            (Some(message), None) => Ok(message),
+            _ => Err(ProtobufError::UnknownMessage(
+                name.to_string(),
+                self.pool
+                    .all_messages()
+                    .filter(|message| !message.package_name().starts_with("google.protobuf"))
+                    .map(|message| message.full_name().to_string())
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// The schema and message types given on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct ProtobufSettings {
+    pub schema: Option<Schema>,
+    /// Message type of request bodies sent as Protobuf.
+    pub request: Option<String>,
+    /// Message type of responses, instead of the one the Content-Type names.
+    pub response: Option<String>,
+}
+
+impl ProtobufSettings {
+    pub fn new(paths: &[PathBuf], request: Option<String>, response: Option<String>) -> Result<Self, ProtobufError> {
+        if paths.is_empty() {
+            if request.is_some() || response.is_some() {
+                return Err(ProtobufError::MissingSchema);
+            }
+            return Ok(ProtobufSettings::default());
+        }
+        let schema = Schema::load(paths)?;
+        // Misspelled types are reported before anything is sent.
+        for name in request.iter().chain(&response) {
+            schema.message(name)?;
+        }
+        Ok(ProtobufSettings { schema: Some(schema), request, response })
+    }
+
+    /// Decodes a response with the schema when its message type is known,
+    /// from `--proto-response` or a `proto` or `messageType` Content-Type
+    /// parameter. Otherwise the wire format is decoded without a schema. The
+    /// full name of the message type is returned with the tree.
+    pub fn decode(&self, bytes: &[u8], content_type: &str) -> Result<(Ordered, Option<String>), ProtobufError> {
+        if let Some(schema) = &self.schema {
+            let advertised = content::parameter(content_type, "proto")
+                .or_else(|| content::parameter(content_type, "messageType"));
+            let message = match (&self.response, advertised) {
+                (Some(name), _) => Some(schema.message(name)?),
+                // A type the schema does not know falls back to the wire format.
+                (None, Some(name)) => schema.message(&name).ok(),
+                (None, None) => None,
+            };
+            if let Some(message) = message {
+                return decode_message(&message, bytes).map(|value| (value, Some(message.full_name().to_string())));
+            }
+        }
+        decode_wire(bytes).map(|value| (value, None))
+    }
+
+    /// Encodes a JSON body, in the Protobuf JSON mapping, as the
+    /// `--proto-request` message type.
+    pub fn encode(&self, json: &str) -> Result<Vec<u8>, ProtobufError> {
+        let schema = self.schema.as_ref().ok_or(ProtobufError::MissingSchema)?;
+        let name = self.request.as_deref().ok_or(ProtobufError::MissingRequestMessage)?;
+        let message = schema.message(name)?;
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let decoded = DynamicMessage::deserialize(message.clone(), &mut deserializer)
+            .and_then(|decoded| deserializer.end().map(|_| decoded))
+            .map_err(|e| ProtobufError::Json(message.full_name().to_string(), e))?;
+        Ok(decoded.encode_to_vec())
+    }
+}
+
+/// The message as JSON with the field names of the `.proto` file, including
+/// fields left at their default value, in field number order.
+fn decode_message(message: &MessageDescriptor, bytes: &[u8]) -> Result<Ordered, ProtobufError> {
+    let name = message.full_name().to_string();
+    let decoded = DynamicMessage::decode(message.clone(), bytes).map_err(|e| ProtobufError::Decode(name.clone(), e))?;
+    let options = SerializeOptions::new().use_proto_field_name(true).skip_default_fields(false);
+    let mut json = Vec::new();
+    decoded
+        .serialize_with_options(&mut serde_json::Serializer::new(&mut json), &options)
+        .map_err(|e| ProtobufError::Json(name.clone(), e))?;
+    serde_json::from_slice(&json).map_err(|e| ProtobufError::Json(name, e))
+}
+
+/// Decodes a message without its schema, like `protoc --decode_raw`: keys
+/// are field numbers and repeated fields become arrays. Length-delimited
+/// fields are shown as text when they are printable UTF-8, as an embedded
+/// message when they parse as one, and as base64 otherwise.
+pub fn decode_wire(bytes: &[u8]) -> Result<Ordered, ProtobufError> {
+    wire_message(bytes, 0).map(Ordered::Map).ok_or(ProtobufError::Wire)
+}
+
+/// Fields in the order they first appear.
+fn wire_message(bytes: &[u8], depth: usize) -> Option<Vec<(String, Ordered)>> {
+    let mut fields: Vec<(String, Ordered)> = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let key = varint(&mut rest)?;
+        let number = key >> 3;
+        if number == 0 || number > MAX_FIELD_NUMBER {
+            return None;
+        }
+        let value = match key & 7 {
+            0 => Value::from(varint(&mut rest)?).into(),
+            1 => {
+                let (fixed, remaining) = rest.split_first_chunk::<8>()?;
+                rest = remaining;
+                Value::from(u64::from_le_bytes(*fixed)).into()
+            }
+            2 => {
+                let length = usize::try_from(varint(&mut rest)?).ok().filter(|length| *length <= rest.len())?;
+                let (field, remaining) = rest.split_at(length);
+                rest = remaining;
+                length_delimited(field, depth)
+            }
+            5 => {
+                let (fixed, remaining) = rest.split_first_chunk::<4>()?;
+                rest = remaining;
+                Value::from(u32::from_le_bytes(*fixed)).into()
+            }
+            // Groups are deprecated; anything else is not Protobuf.
+            _ => return None,
+        };
+
+        let number = number.to_string();
+        match fields.iter_mut().find(|(field, _)| *field == number) {
+            Some((_, Ordered::Array(values))) => values.push(value),
+            Some((_, first)) => {
+                let earlier = std::mem::replace(first, Ordered::Array(Vec::new()));
+                *first = Ordered::Array(vec![earlier, value]);
+            }
+            None => fields.push((number, value)),
+        }
+    }
+    Some(fields)
+}
+
+fn length_delimited(field: &[u8], depth: usize) -> Ordered {
+    if let Ok(text) = std::str::from_utf8(field)
+        && text.chars().all(|c| !c.is_control() || c.is_whitespace())
+    {
+        return Value::String(text.to_string()).into();
+    }
+    if depth < MAX_WIRE_DEPTH
+        && let Some(message) = wire_message(field, depth + 1)
+    {
+        return Ordered::Map(message);
+    }
+    Value::String(STANDARD.encode(field)).into()
+}
+
+fn varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER: &str = r#"
+        syntax = "proto3";
+        package shop.v1;
+
+        message Item {
+            string sku = 1;
+            uint32 quantity = 2;
+        }
+
+        message Order {
+            string id = 3;
+            repeated Item items = 1;
+            bool paid = 2;
+        }
+    "#;
+
+    fn schema(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("http-client-protobuf-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("order.proto");
+        fs::write(&path, ORDER).unwrap();
+        path
+    }
+
+    fn settings(name: &str, request: Option<&str>, response: Option<&str>) -> ProtobufSettings {
+        ProtobufSettings::new(&[schema(name)], request.map(String::from), response.map(String::from)).unwrap()
+    }
+
+    fn json(value: &Ordered) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    #[test]
+    fn decodes_scalars_without_a_schema() {
+        // 1: varint 150, 2: fixed64 1, 3: fixed32 7.
+        let bytes = [0x08, 0x96, 0x01, 0x11, 1, 0, 0, 0, 0, 0, 0, 0, 0x1d, 7, 0, 0, 0];
+        assert_eq!(json(&decode_wire(&bytes).unwrap()), r#"{"1":150,"2":1,"3":7}"#);
+    }
+
+    #[test]
+    fn decodes_strings_messages_and_bytes() {
+        let mut bytes = vec![0x0a, 5];
+        bytes.extend_from_slice(b"hello");
+        // 2: an embedded message with 1: varint 1.
+        bytes.extend_from_slice(&[0x12, 2, 0x08, 0x01]);
+        // 3: bytes that are neither text nor a message.
+        bytes.extend_from_slice(&[0x1a, 2, 0xff, 0xff]);
+        assert_eq!(json(&decode_wire(&bytes).unwrap()), r#"{"1":"hello","2":{"1":1},"3":"//8="}"#);
+    }
+
+    #[test]
+    fn collects_repeated_fields_into_arrays() {
+        let bytes = [0x08, 1, 0x10, 5, 0x08, 2, 0x08, 3];
+        assert_eq!(json(&decode_wire(&bytes).unwrap()), r#"{"1":[1,2,3],"2":5}"#);
+    }
+
+    #[test]
+    fn keeps_fields_in_wire_order() {
+        let bytes = [0x18, 3, 0x08, 1, 0x10, 2];
+        assert_eq!(json(&decode_wire(&bytes).unwrap()), r#"{"3":3,"1":1,"2":2}"#);
+    }
+
+    #[test]
+    fn rejects_bytes_outside_the_wire_format() {
+        for bytes in [&[0x08][..], &[0x0a, 5, b'a'], &[0x0b], &[0x00, 1], &[0xff; 11]] {
+            assert!(matches!(decode_wire(bytes), Err(ProtobufError::Wire)), "{:?}", bytes);
+        }
+    }
+
+    #[test]
+    fn decodes_with_the_schema_in_field_number_order() {
+        let settings = settings("decode", Some("Order"), None);
+        let encoded = settings.encode(r#"{"id": "A1", "items": [{"sku": "x", "quantity": 2}]}"#).unwrap();
+        let (value, message) = settings.decode(&encoded, "application/x-protobuf; proto=shop.v1.Order").unwrap();
+        assert_eq!(message.as_deref(), Some("shop.v1.Order"));
+        assert_eq!(json(&value), r#"{"items":[{"sku":"x","quantity":2}],"paid":false,"id":"A1"}"#);
+    }
+
+    #[test]
+    fn picks_the_message_type() {
+        let settings = settings("pick", Some("shop.v1.Item"), None);
+        let encoded = settings.encode(r#"{"sku": "x"}"#).unwrap();
+        // Without a type the wire format is shown.
+        let (value, message) = settings.decode(&encoded, "application/x-protobuf").unwrap();
+        assert_eq!((json(&value), message), (r#"{"1":"x"}"#.to_string(), None));
+        // An unknown advertised type falls back to the wire format too.
+        let (_, message) = settings.decode(&encoded, "application/x-protobuf; messageType=Other").unwrap();
+        assert_eq!(message, None);
+
+        let settings = ProtobufSettings { response: Some("Item".to_string()), ..settings };
+        let (value, message) = settings.decode(&encoded, "application/x-protobuf; proto=shop.v1.Order").unwrap();
+        assert_eq!(message.as_deref(), Some("shop.v1.Item"));
+        assert_eq!(json(&value), r#"{"sku":"x","quantity":0}"#);
+    }
+
+    #[test]
+    fn reports_schema_errors() {
+        let error = ProtobufSettings::new(&[schema("unknown")], None, Some("Missing".to_string())).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown message type 'Missing'. Known types: shop.v1.Item, shop.v1.Order");
+        let error = ProtobufSettings::new(&[], Some("Order".to_string()), None).unwrap_err();
+        assert!(matches!(error, ProtobufError::MissingSchema));
+        assert!(matches!(settings("no-request", None, None).encode("{}"), Err(ProtobufError::MissingRequestMessage)));
+
+        let settings = settings("invalid", Some("Order"), Some("Order"));
+        assert!(matches!(settings.encode(r#"{"paid": "yes"}"#), Err(ProtobufError::Json(..))));
+        assert!(matches!(settings.decode(&[0x0a, 5], ""), Err(ProtobufError::Decode(..))));
+    }
+}
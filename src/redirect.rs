@@ -0,0 +1,301 @@
+use reqwest::header::{
+    AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HOST, LOCATION, PROXY_AUTHORIZATION,
+};
+use reqwest::{Method, Request, Response, StatusCode, Url};
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Headers only sent to the origin they were configured for.
+const CREDENTIAL_HEADERS: [reqwest::header::HeaderName; 3] = [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION];
+
+#[derive(Debug)]
+pub enum RedirectError {
+    TooManyRedirects(usize),
+    InvalidLocation(String),
+    BodyNotReplayable,
+}
+
+impl fmt::Display for RedirectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedirectError::TooManyRedirects(max) => {
+                write!(f, "Too many redirects (limit is {})", max)
+            }
+            RedirectError::InvalidLocation(location) => {
+                write!(f, "Invalid redirect location: '{}'", location)
+            }
+            RedirectError::BodyNotReplayable => {
+                write!(f, "Request body cannot be resent to the redirect target")
+            }
+        }
+    }
+}
+
+impl Error for RedirectError {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectPolicy {
+    pub follow: bool,
+    pub max_redirects: usize,
+    /// Resend the original method and body on 301/302/303 instead of
+    /// switching to GET.
+    pub keep_method: bool,
+    /// Keep credentials when a redirect leaves the original origin.
+    pub keep_auth: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        RedirectPolicy {
+            follow: true,
+            max_redirects: 10,
+            keep_method: false,
+            keep_auth: false,
+        }
+    }
+}
+
+/// One redirect response that was followed.
+#[derive(Debug, Clone)]
+pub struct Hop {
+    pub method: Method,
+    pub url: Url,
+    pub status: StatusCode,
+    pub location: String,
+    pub duration: Duration,
+}
+
+/// Sends `request` through `send`, following redirects according to
+/// `policy`. `send` is told whether the request still targets the original
+/// origin, so that it only adds credentials and signatures where they
+/// belong. Each hop starts from a copy taken before `send` saw the request,
+/// so whatever `send` adds is computed afresh for the hop's method and URL.
+pub async fn follow<E, F>(
+    request: Request,
+    policy: &RedirectPolicy,
+    mut send: F,
+) -> Result<(Response, Vec<Hop>), E>
+where
+    E: From<RedirectError>,
+    F: AsyncFnMut(Request, bool) -> Result<Response, E>,
+{
+    let origin = request.url().origin().ascii_serialization();
+    let mut hops = Vec::new();
+    let mut request = request;
+
+    loop {
+        let next = if policy.follow { request.try_clone() } else { None };
+        let method = request.method().clone();
+        let url = request.url().clone();
+        let same_origin = url.origin().ascii_serialization() == origin;
+
+        let start = Instant::now();
+        let response = send(request, same_origin || policy.keep_auth).await?;
+        let duration = start.elapsed();
+
+        let location = match response.headers().get(LOCATION) {
+            Some(location) if policy.follow && response.status().is_redirection() => {
+                String::from_utf8_lossy(location.as_bytes()).into_owned()
+            }
+            _ => return Ok((response, hops)),
+        };
+        if hops.len() == policy.max_redirects {
+            return Err(RedirectError::TooManyRedirects(policy.max_redirects).into());
+        }
+
+        let target = url
+            .join(&location)
+            .map_err(|_| RedirectError::InvalidLocation(location.clone()))?;
+        let next = next.ok_or(RedirectError::BodyNotReplayable)?;
+        request = redirected_request(next, response.status(), target, &origin, policy);
+
+        hops.push(Hop {
+            method,
+            url,
+            status: response.status(),
+            location,
+            duration,
+        });
+    }
+}
+
+fn redirected_request(
+    mut request: Request,
+    status: StatusCode,
+    target: Url,
+    origin: &str,
+    policy: &RedirectPolicy,
+) -> Request {
+    // 307/308 always keep the method; 303 means "GET the result"; 301/302
+    // turn POST into GET as browsers do.
+    let to_get = match status {
+        StatusCode::SEE_OTHER => request.method() != Method::HEAD,
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => request.method() == Method::POST,
+        _ => false,
+    };
+    if to_get && !policy.keep_method {
+        *request.method_mut() = Method::GET;
+        *request.body_mut() = None;
+        for name in [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING] {
+            request.headers_mut().remove(name);
+        }
+    }
+
+    if target.host_str() != request.url().host_str() || target.port() != request.url().port() {
+        request.headers_mut().remove(HOST);
+    }
+    if target.origin().ascii_serialization() != origin && !policy.keep_auth {
+        for name in &CREDENTIAL_HEADERS {
+            request.headers_mut().remove(name);
+        }
+    }
+
+    *request.url_mut() = target;
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::sync::Mutex;
+
+    const ORIGIN: &str = "https://api.example.com";
+
+    fn request(method: Method, url: &str) -> Request {
+        let mut request = Request::new(method, url.parse().unwrap());
+        let headers = request.headers_mut();
+        for (name, value) in [
+            (AUTHORIZATION, "Bearer secret"),
+            (COOKIE, "sid=1"),
+            (PROXY_AUTHORIZATION, "Basic cHJveHk="),
+            (CONTENT_TYPE, "application/json"),
+            (CONTENT_LENGTH, "2"),
+            (CONTENT_ENCODING, "gzip"),
+            (HOST, "api.example.com"),
+        ] {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+        *request.body_mut() = Some("{}".into());
+        request
+    }
+
+    fn redirect(method: Method, status: u16, target: &str, policy: RedirectPolicy) -> Request {
+        let request = request(method, "https://api.example.com/start");
+        let target = request.url().join(target).unwrap();
+        redirected_request(request, StatusCode::from_u16(status).unwrap(), target, ORIGIN, &policy)
+    }
+
+    #[test]
+    fn switches_post_to_get_without_body_headers() {
+        for status in [301, 302, 303] {
+            let request = redirect(Method::POST, status, "/next", RedirectPolicy::default());
+            assert_eq!(request.method(), Method::GET, "{}", status);
+            assert!(request.body().is_none());
+            for name in [CONTENT_TYPE, CONTENT_LENGTH, CONTENT_ENCODING] {
+                assert!(!request.headers().contains_key(&name), "{} kept after {}", name, status);
+            }
+            // Same origin: credentials stay.
+            assert!(request.headers().contains_key(AUTHORIZATION));
+        }
+    }
+
+    #[test]
+    fn keeps_method_when_asked_or_required() {
+        for status in [307, 308] {
+            let request = redirect(Method::POST, status, "/next", RedirectPolicy::default());
+            assert_eq!(request.method(), Method::POST);
+            assert!(request.body().is_some());
+            assert_eq!(request.headers()[CONTENT_ENCODING], "gzip");
+        }
+        let policy = RedirectPolicy { keep_method: true, ..RedirectPolicy::default() };
+        assert_eq!(redirect(Method::POST, 302, "/next", policy).method(), Method::POST);
+        assert_eq!(redirect(Method::PUT, 302, "/next", RedirectPolicy::default()).method(), Method::PUT);
+        assert_eq!(redirect(Method::HEAD, 303, "/next", RedirectPolicy::default()).method(), Method::HEAD);
+        assert_eq!(redirect(Method::PUT, 303, "/next", RedirectPolicy::default()).method(), Method::GET);
+    }
+
+    #[test]
+    fn strips_credentials_across_origins() {
+        let request = redirect(Method::GET, 302, "https://cdn.example.net/file", RedirectPolicy::default());
+        assert_eq!(request.url().as_str(), "https://cdn.example.net/file");
+        for name in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, HOST] {
+            assert!(!request.headers().contains_key(&name), "{} kept", name);
+        }
+
+        // Another scheme or port is another origin too.
+        let request = redirect(Method::GET, 302, "http://api.example.com/", RedirectPolicy::default());
+        assert!(!request.headers().contains_key(AUTHORIZATION));
+        let request = redirect(Method::GET, 302, "https://api.example.com:8443/", RedirectPolicy::default());
+        assert!(!request.headers().contains_key(AUTHORIZATION));
+        assert!(!request.headers().contains_key(HOST));
+
+        let policy = RedirectPolicy { keep_auth: true, ..RedirectPolicy::default() };
+        let request = redirect(Method::GET, 302, "https://cdn.example.net/file", policy);
+        assert_eq!(request.headers()[AUTHORIZATION], "Bearer secret");
+        assert!(!request.headers().contains_key(HOST));
+    }
+
+    fn response(status: u16, location: Option<&str>) -> Response {
+        let mut response = hyper::Response::builder().status(status);
+        if let Some(location) = location {
+            response = response.header(LOCATION, location);
+        }
+        Response::from(response.body("").unwrap())
+    }
+
+    /// Follows a fixed route, recording what each hop was sent with.
+    async fn follow_route(
+        request: Request,
+        policy: RedirectPolicy,
+    ) -> (Result<(Response, Vec<Hop>), RedirectError>, Vec<(Method, String, bool)>) {
+        let sent = Mutex::new(Vec::new());
+        let result = follow(request, &policy, async |request: Request, with_auth: bool| {
+            let url = request.url().to_string();
+            sent.lock().unwrap().push((request.method().clone(), url.clone(), with_auth));
+            Ok::<_, RedirectError>(match url.as_str() {
+                "https://api.example.com/start" => response(303, Some("/moved")),
+                "https://api.example.com/moved" => response(302, Some("https://cdn.example.net/final")),
+                "https://api.example.com/loop" => response(302, Some("/loop")),
+                _ => response(200, None),
+            })
+        })
+        .await;
+        (result, sent.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn follows_hops_and_tells_send_where_credentials_belong() {
+        let (result, sent) = follow_route(request(Method::POST, "https://api.example.com/start"), RedirectPolicy::default()).await;
+        let (response, hops) = result.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            sent,
+            [
+                (Method::POST, "https://api.example.com/start".to_string(), true),
+                (Method::GET, "https://api.example.com/moved".to_string(), true),
+                (Method::GET, "https://cdn.example.net/final".to_string(), false),
+            ]
+        );
+        let statuses: Vec<u16> = hops.iter().map(|hop| hop.status.as_u16()).collect();
+        assert_eq!(statuses, [303, 302]);
+        assert_eq!(hops[0].method, Method::POST);
+        assert_eq!(hops[1].location, "https://cdn.example.net/final");
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_limit_or_when_not_following() {
+        let policy = RedirectPolicy { max_redirects: 3, ..RedirectPolicy::default() };
+        let (result, sent) = follow_route(request(Method::GET, "https://api.example.com/loop"), policy).await;
+        assert!(matches!(result, Err(RedirectError::TooManyRedirects(3))));
+        assert_eq!(sent.len(), 4);
+
+        let policy = RedirectPolicy { follow: false, ..RedirectPolicy::default() };
+        let (result, sent) = follow_route(request(Method::GET, "https://api.example.com/loop"), policy).await;
+        let (response, hops) = result.unwrap();
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert!(hops.is_empty());
+        assert_eq!(sent.len(), 1);
+    }
+}
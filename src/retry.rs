@@ -0,0 +1,284 @@
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Method, Response, Url};
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const DEFAULT_RETRY_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+#[derive(Debug)]
+pub enum RetryError {
+    InvalidStatus(String),
+    InvalidSeconds(&'static str, f64),
+}
+
+impl fmt::Display for RetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryError::InvalidStatus(status) => {
+                write!(f, "Invalid --retry-on status: '{}'. Use comma-separated codes such as 502,503", status)
+            }
+            RetryError::InvalidSeconds(flag, value) => {
+                write!(f, "Invalid {} value: {}. Use a non-negative number of seconds", flag, value)
+            }
+        }
+    }
+}
+
+impl Error for RetryError {}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Extra attempts after the first one.
+    pub retries: usize,
+    pub statuses: Vec<u16>,
+    /// Also retry methods that are not idempotent, such as POST.
+    pub all_methods: bool,
+    /// Delay before the first retry; it doubles for every further one.
+    pub base_delay: Duration,
+    /// Upper bound for backoff and `Retry-After` delays.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 0,
+            statuses: DEFAULT_RETRY_STATUSES.to_vec(),
+            all_methods: false,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn parse_statuses(list: &str) -> Result<Vec<u16>, RetryError> {
+        list.split(',')
+            .map(str::trim)
+            .filter(|status| !status.is_empty())
+            .map(|status| {
+                status
+                    .parse()
+                    .ok()
+                    .filter(|code| (100..600).contains(code))
+                    .ok_or_else(|| RetryError::InvalidStatus(status.to_string()))
+            })
+            .collect()
+    }
+
+    /// Whether a request with `method` may be sent again.
+    pub fn allows(&self, method: &Method) -> bool {
+        self.all_methods || is_idempotent(method)
+    }
+
+    pub fn retries_status(&self, response: &Response) -> bool {
+        self.statuses.contains(&response.status().as_u16())
+    }
+
+    /// How long to wait before retry number `retry` (starting at 1):
+    /// `Retry-After` when the server sent one, otherwise exponential backoff
+    /// with jitter between half and all of the delay.
+    pub fn delay(&self, retry: usize, response: Option<&Response>) -> Duration {
+        if let Some(delay) = response.and_then(retry_after) {
+            return delay.min(self.max_delay);
+        }
+        let exponent = retry.saturating_sub(1).min(16) as u32;
+        let backoff = self.base_delay.saturating_mul(2u32.pow(exponent)).min(self.max_delay);
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Converts a `flag` value given in seconds.
+pub fn seconds(flag: &'static str, value: f64) -> Result<Duration, RetryError> {
+    Duration::try_from_secs_f64(value).map_err(|_| RetryError::InvalidSeconds(flag, value))
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE
+    )
+}
+
+/// `Retry-After` as seconds or an HTTP date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// One try at sending a request.
+#[derive(Debug, Clone)]
+pub struct Attempt {
+    pub url: Url,
+    pub number: usize,
+    /// The status line or the error.
+    pub outcome: String,
+    pub duration: Duration,
+    /// Wait before the next attempt, if there is one.
+    pub retry_in: Option<Duration>,
+}
+
+impl fmt::Display for Attempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Attempt {}: {} ({}ms)", self.number, self.outcome, self.duration.as_millis())?;
+        if let Some(delay) = self.retry_in {
+            write!(f, ", retrying in {:.1}s", delay.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+/// Attempts made by a client, for verbose output and the TUI history.
+#[derive(Debug, Default)]
+pub struct AttemptLog(Mutex<Vec<Attempt>>);
+
+impl AttemptLog {
+    pub fn record(&self, attempt: Attempt) {
+        self.0.lock().unwrap().push(attempt);
+    }
+
+    /// Attempts recorded since the last call.
+    pub fn take(&self) -> Vec<Attempt> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientSettings;
+    use crate::cookies::CookieJar;
+    use crate::testing::{reply, Server};
+    use std::sync::Arc;
+
+    fn response(retry_after: Option<&str>) -> Response {
+        let mut builder = hyper::Response::builder().status(503);
+        if let Some(value) = retry_after {
+            builder = builder.header(RETRY_AFTER, value);
+        }
+        Response::from(builder.body(hyper::Body::empty()).unwrap())
+    }
+
+    fn policy(base: u64, max: u64) -> RetryPolicy {
+        RetryPolicy {
+            retries: 3,
+            base_delay: Duration::from_millis(base),
+            max_delay: Duration::from_millis(max),
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn parses_status_lists() {
+        assert_eq!(RetryPolicy::parse_statuses("500, 502,,503").unwrap(), [500, 502, 503]);
+        for list in ["5xx", "99", "600", "502,abc"] {
+            assert!(matches!(RetryPolicy::parse_statuses(list), Err(RetryError::InvalidStatus(_))), "{}", list);
+        }
+        assert_eq!(seconds("--retry-delay", 0.25).unwrap(), Duration::from_millis(250));
+        assert!(matches!(seconds("--timeout", -1.0), Err(RetryError::InvalidSeconds("--timeout", _))));
+    }
+
+    #[test]
+    fn retries_idempotent_methods_only() {
+        let policy = RetryPolicy::default();
+        for method in [Method::GET, Method::HEAD, Method::PUT, Method::DELETE, Method::OPTIONS] {
+            assert!(policy.allows(&method), "{}", method);
+        }
+        assert!(!policy.allows(&Method::POST));
+        assert!(!policy.allows(&Method::PATCH));
+        assert!(RetryPolicy { all_methods: true, ..policy }.allows(&Method::POST));
+    }
+
+    #[test]
+    fn backs_off_exponentially_with_jitter() {
+        let policy = policy(100, 350);
+        for (retry, full) in [(1, 100), (2, 200), (3, 350), (40, 350)] {
+            let delay = policy.delay(retry, None);
+            let full = Duration::from_millis(full);
+            assert!(delay >= full / 2 && delay <= full, "retry {}: {:?}", retry, delay);
+        }
+        // Statuses without Retry-After back off the same way.
+        let delay = policy.delay(1, Some(&response(None)));
+        assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn honours_retry_after() {
+        let policy = policy(100, 60_000);
+        assert_eq!(policy.delay(1, Some(&response(Some("7")))), Duration::from_secs(7));
+        assert_eq!(policy.delay(3, Some(&response(Some(" 0 ")))), Duration::ZERO);
+        // Capped at the maximum delay.
+        assert_eq!(policy.delay(1, Some(&response(Some("3600")))), Duration::from_secs(60));
+
+        let in_ten = (chrono::Utc::now() + chrono::Duration::seconds(10)).to_rfc2822();
+        let delay = policy.delay(1, Some(&response(Some(&in_ten))));
+        assert!(delay > Duration::from_secs(8) && delay <= Duration::from_secs(10), "{:?}", delay);
+        let past = policy.delay(1, Some(&response(Some("Wed, 21 Oct 2015 07:28:00 GMT"))));
+        assert_eq!(past, Duration::ZERO);
+        // Unparseable values fall back to backoff.
+        assert!(policy.delay(1, Some(&response(Some("soon")))) <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn describes_attempts() {
+        let attempt = Attempt {
+            url: Url::parse("https://example.com/").unwrap(),
+            number: 2,
+            outcome: "HTTP/1.1 503 Service Unavailable".to_string(),
+            duration: Duration::from_millis(42),
+            retry_in: Some(Duration::from_millis(1500)),
+        };
+        assert_eq!(attempt.to_string(), "Attempt 2: HTTP/1.1 503 Service Unavailable (42ms), retrying in 1.5s");
+        let last = Attempt { retry_in: None, ..attempt };
+        assert_eq!(last.to_string(), "Attempt 2: HTTP/1.1 503 Service Unavailable (42ms)");
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let server = Server::start(vec![
+            reply(503, &[("Retry-After", "0")], b""),
+            reply(502, &[], b""),
+            reply(200, &[], b"done"),
+        ])
+        .await;
+        let settings = ClientSettings { retry: policy(1, 10), ..ClientSettings::default() };
+        let client = settings.build(Arc::new(CookieJar::new())).unwrap();
+
+        let request = client.request(Method::GET, &server.url("/")).build().unwrap();
+        let response = client.execute(request).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "done");
+
+        let attempts = settings.attempts.take();
+        let outcomes: Vec<(usize, &str, bool)> =
+            attempts.iter().map(|a| (a.number, a.outcome.as_str(), a.retry_in.is_some())).collect();
+        assert_eq!(
+            outcomes,
+            [
+                (1, "HTTP/1.1 503 Service Unavailable", true),
+                (2, "HTTP/1.1 502 Bad Gateway", true),
+                (3, "HTTP/1.1 200 OK", false)
+            ]
+        );
+        assert_eq!(attempts[0].retry_in, Some(Duration::ZERO));
+    }
+
+    #[tokio::test]
+    async fn sends_post_once() {
+        let server = Server::start(vec![reply(503, &[], b""), reply(200, &[], b"")]).await;
+        let settings = ClientSettings { retry: policy(1, 10), ..ClientSettings::default() };
+        let client = settings.build(Arc::new(CookieJar::new())).unwrap();
+
+        let request = client.request(Method::POST, &server.url("/")).body("x").build().unwrap();
+        assert_eq!(client.execute(request).await.unwrap().status(), 503);
+        assert_eq!(settings.attempts.take().len(), 1);
+        assert_eq!(server.received().len(), 1);
+    }
+}
@@ -0,0 +1,998 @@
+use regex_automata::meta::Regex;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `$ref`s followed without moving into the value, which a schema referring
+/// to itself would do forever. The count starts again at each item and
+/// property.
+const MAX_REF_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub enum SchemaError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, serde_json::Error),
+    UnsupportedDraft(PathBuf, String),
+    Reference(PathBuf, String),
+    Pattern(PathBuf, String, String),
+    UnsupportedKeyword(PathBuf, &'static str),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Read(path, e) => write!(f, "Could not read '{}': {}", path.display(), e),
+            SchemaError::Parse(path, e) => write!(f, "Invalid schema file '{}': {}", path.display(), e),
+            SchemaError::UnsupportedDraft(path, uri) => write!(
+                f,
+                "'{}' uses {}; draft 2020-12 and draft-07 are supported",
+                path.display(),
+                uri
+            ),
+            SchemaError::Reference(path, reference) => write!(
+                f,
+                "Cannot resolve $ref '{}' in '{}'; only references to local files are supported",
+                reference,
+                path.display()
+            ),
+            SchemaError::Pattern(path, pattern, e) => {
+                write!(f, "Invalid pattern '{}' in '{}': {}", pattern, path.display(), e)
+            }
+            SchemaError::UnsupportedKeyword(path, keyword) => {
+                write!(f, "'{}' uses {}, which is not supported", path.display(), keyword)
+            }
+        }
+    }
+}
+
+impl Error for SchemaError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Draft {
+    Draft7,
+    Draft2020,
+}
+
+impl fmt::Display for Draft {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Draft::Draft7 => write!(f, "draft-07"),
+            Draft::Draft2020 => write!(f, "draft 2020-12"),
+        }
+    }
+}
+
+/// Where a value breaks the schema, as a JSON pointer, and how.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pointer.as_str() {
+            "" => write!(f, "(root): {}", self.message),
+            pointer => write!(f, "{}: {}", pointer, self.message),
+        }
+    }
+}
+
+/// A JSON Schema read from a local file, with the files its `$ref`s name.
+/// `format` and `$dynamicRef` are not checked, 2020-12 schemas using the
+/// `unevaluated*` keywords are refused, and `$id` does not change where
+/// references are resolved from.
+#[derive(Debug)]
+pub struct Schema {
+    pub path: PathBuf,
+    pub draft: Draft,
+    documents: HashMap<PathBuf, Value>,
+    patterns: HashMap<String, Regex>,
+}
+
+/// The schemas given with `--request-schema` and `--response-schema`.
+#[derive(Debug, Default)]
+pub struct Schemas {
+    pub request: Option<Schema>,
+    pub response: Option<Schema>,
+}
+
+fn read(path: &Path) -> Result<Value, SchemaError> {
+    let text = std::fs::read_to_string(path).map_err(|e| SchemaError::Read(path.to_path_buf(), e))?;
+    serde_json::from_str(&text).map_err(|e| SchemaError::Parse(path.to_path_buf(), e))
+}
+
+/// The schemas directly inside a schema object: the values of keywords that
+/// hold a schema, a list of them or a map of them. Keywords holding data,
+/// such as `const`, `enum` and `examples`, are left out.
+fn subschemas(map: &Map<String, Value>) -> Vec<&Value> {
+    let mut found = Vec::new();
+    for (keyword, value) in map {
+        match (keyword.as_str(), value) {
+            ("items" | "prefixItems" | "allOf" | "anyOf" | "oneOf", Value::Array(schemas)) => found.extend(schemas),
+            (
+                "items" | "additionalItems" | "contains" | "additionalProperties" | "propertyNames" | "not" | "if"
+                | "then" | "else" | "unevaluatedItems" | "unevaluatedProperties" | "contentSchema",
+                schema,
+            ) => found.push(schema),
+            (
+                "properties" | "patternProperties" | "$defs" | "definitions" | "dependentSchemas" | "dependencies",
+                Value::Object(schemas),
+            ) => found.extend(schemas.values()),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Every `$ref` in a document.
+fn references<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+        found.push(reference);
+    }
+    subschemas(map).into_iter().for_each(|schema| references(schema, found));
+}
+
+/// Every `pattern` and `patternProperties` regular expression in a document.
+fn patterns<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    if let Some(pattern) = map.get("pattern").and_then(Value::as_str) {
+        found.push(pattern);
+    }
+    if let Some(properties) = map.get("patternProperties").and_then(Value::as_object) {
+        found.extend(properties.keys().map(String::as_str));
+    }
+    subschemas(map).into_iter().for_each(|schema| patterns(schema, found));
+}
+
+/// The first `unevaluatedItems` or `unevaluatedProperties` in a document.
+/// They depend on what every other keyword evaluated, which is not tracked.
+fn unevaluated(value: &Value) -> Option<&'static str> {
+    let Value::Object(map) = value else {
+        return None;
+    };
+    ["unevaluatedItems", "unevaluatedProperties"]
+        .into_iter()
+        .find(|keyword| map.contains_key(*keyword))
+        .or_else(|| subschemas(map).into_iter().find_map(unevaluated))
+}
+
+/// The file a reference names, relative to the file it is in.
+fn referenced_file(base: &Path, reference: &str) -> PathBuf {
+    let file = reference.split_once('#').map_or(reference, |(file, _)| file);
+    match file {
+        "" => base.to_path_buf(),
+        file => base.parent().unwrap_or(Path::new("")).join(file),
+    }
+}
+
+/// Decodes `%XX` escapes, which URI fragments use for some characters.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// A subschema named by `$anchor`, or by a draft-07 `$id` of `#name`.
+fn find_anchor<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => {
+            let anchor = map.get("$anchor").and_then(Value::as_str);
+            let id = map.get("$id").and_then(Value::as_str).and_then(|id| id.strip_prefix('#'));
+            if anchor == Some(name) || id == Some(name) {
+                return Some(value);
+            }
+            map.values().find_map(|value| find_anchor(value, name))
+        }
+        Value::Array(values) => values.iter().find_map(|value| find_anchor(value, name)),
+        _ => None,
+    }
+}
+
+/// Escapes a key for a JSON pointer.
+fn pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        "number" => value.is_number(),
+        name => type_name(value) == name,
+    }
+}
+
+/// JSON equality, under which `1` and `1.0` are the same number.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a == b || a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b)),
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, value)| b.get(key).is_some_and(|other| equal(value, other)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn is_multiple(value: &Value, divisor: &Value) -> bool {
+    if let (Some(value), Some(divisor)) = (value.as_i64(), divisor.as_i64())
+        && divisor != 0
+    {
+        return value % divisor == 0;
+    }
+    match (value.as_f64(), divisor.as_f64()) {
+        (Some(value), Some(divisor)) if divisor > 0.0 => {
+            let quotient = value / divisor;
+            (quotient - quotient.round()).abs() < 1e-9
+        }
+        _ => true,
+    }
+}
+
+fn push(violations: &mut Vec<Violation>, pointer: &str, message: String) {
+    violations.push(Violation { pointer: pointer.to_string(), message });
+}
+
+impl Schema {
+    /// Reads a schema and the local files its `$ref`s name. The draft comes
+    /// from `$schema` and is 2020-12 when it is missing.
+    pub fn load(path: &Path) -> Result<Schema, SchemaError> {
+        let root = read(path)?;
+        let draft = match root.get("$schema").and_then(Value::as_str) {
+            None => Draft::Draft2020,
+            Some(uri) if uri.contains("draft-07") => Draft::Draft7,
+            Some(uri) if uri.contains("2020-12") => Draft::Draft2020,
+            Some(uri) => return Err(SchemaError::UnsupportedDraft(path.to_path_buf(), uri.to_string())),
+        };
+
+        let mut documents = HashMap::new();
+        let mut pending = vec![(path.to_path_buf(), root)];
+        while let Some((file, document)) = pending.pop() {
+            let mut found = Vec::new();
+            references(&document, &mut found);
+            for target in found.into_iter().map(|reference| referenced_file(&file, reference)) {
+                if target != file
+                    && !documents.contains_key(&target)
+                    && !pending.iter().any(|(pending, _)| *pending == target)
+                {
+                    let document = read(&target)?;
+                    pending.push((target, document));
+                }
+            }
+            documents.insert(file, document);
+        }
+
+        // Draft-07 has no such keywords, so there they are ignored like any unknown one.
+        if draft == Draft::Draft2020
+            && let Some((file, keyword)) =
+                documents.iter().find_map(|(file, document)| unevaluated(document).map(|keyword| (file, keyword)))
+        {
+            return Err(SchemaError::UnsupportedKeyword(file.clone(), keyword));
+        }
+
+        let mut schema = Schema { path: path.to_path_buf(), draft, documents, patterns: HashMap::new() };
+        for (file, document) in &schema.documents {
+            let mut found = Vec::new();
+            references(document, &mut found);
+            if let Some(reference) = found.into_iter().find(|reference| schema.resolve(file, reference).is_none()) {
+                return Err(SchemaError::Reference(file.clone(), reference.to_string()));
+            }
+        }
+        let mut compiled = HashMap::new();
+        for (file, document) in &schema.documents {
+            let mut found = Vec::new();
+            patterns(document, &mut found);
+            for pattern in found {
+                let regex = Regex::new(pattern).map_err(|e| {
+                    // The syntax error ends with a line saying what is wrong.
+                    let reason = e.syntax_error().map_or(e.to_string(), |e| e.to_string());
+                    let reason = reason.lines().last().unwrap_or_default().trim_start_matches("error: ").to_string();
+                    SchemaError::Pattern(file.clone(), pattern.to_string(), reason)
+                })?;
+                compiled.insert(pattern.to_string(), regex);
+            }
+        }
+        schema.patterns = compiled;
+        Ok(schema)
+    }
+
+    fn resolve(&self, base: &Path, reference: &str) -> Option<(&Path, &Value)> {
+        let (file, document) = self.documents.get_key_value(&referenced_file(base, reference))?;
+        let fragment = percent_decode(reference.split_once('#').map_or("", |(_, fragment)| fragment));
+        let target = match fragment.as_str() {
+            "" => Some(document),
+            pointer if pointer.starts_with('/') => document.pointer(pointer),
+            anchor => find_anchor(document, anchor),
+        };
+        target.map(|target| (file.as_path(), target))
+    }
+
+    /// Every place the value breaks the schema, in document order.
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(&self.documents[&self.path], &self.path, value, "", 0, &mut violations);
+        violations
+    }
+
+    /// Validates a JSON body given as text.
+    pub fn validate_text(&self, text: &str) -> Vec<Violation> {
+        match serde_json::from_str(text) {
+            Ok(value) => self.validate(&value),
+            Err(e) => vec![Violation { pointer: String::new(), message: format!("not JSON: {}", e) }],
+        }
+    }
+
+    fn matches(&self, schema: &Value, base: &Path, value: &Value, depth: usize) -> bool {
+        let mut violations = Vec::new();
+        self.check(schema, base, value, "", depth, &mut violations);
+        violations.is_empty()
+    }
+
+    fn check(&self, schema: &Value, base: &Path, value: &Value, pointer: &str, depth: usize, violations: &mut Vec<Violation>) {
+        let map = match schema {
+            Value::Bool(false) => return push(violations, pointer, "no value is allowed here".to_string()),
+            Value::Object(map) => map,
+            _ => return,
+        };
+
+        if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+            if depth >= MAX_REF_DEPTH {
+                return push(violations, pointer, format!("$ref '{}' nests too deeply", reference));
+            }
+            if let Some((file, target)) = self.resolve(base, reference) {
+                self.check(target, file, value, pointer, depth + 1, violations);
+            }
+            // Before 2019-09, keywords next to $ref are ignored.
+            if self.draft == Draft::Draft7 {
+                return;
+            }
+        }
+
+        self.check_generic(map, value, pointer, violations);
+        match value {
+            Value::Number(_) => self.check_number(map, value, pointer, violations),
+            Value::String(text) => self.check_string(map, text, pointer, violations),
+            Value::Array(items) => self.check_array(map, base, items, pointer, violations),
+            Value::Object(_) => self.check_object(map, base, value, pointer, depth, violations),
+            _ => {}
+        }
+        self.check_combinators(map, base, value, pointer, depth, violations);
+    }
+
+    fn check_generic(&self, map: &Map<String, Value>, value: &Value, pointer: &str, violations: &mut Vec<Violation>) {
+        if let Some(expected) = map.get("type") {
+            let names: Vec<&str> = match expected {
+                Value::String(name) => vec![name.as_str()],
+                Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !names.is_empty() && !names.iter().any(|name| has_type(value, name)) {
+                push(violations, pointer, format!("expected {}, got {}", names.join(" or "), type_name(value)));
+            }
+        }
+        if let Some(Value::Array(allowed)) = map.get("enum")
+            && !allowed.iter().any(|allowed| equal(allowed, value))
+        {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            push(violations, pointer, format!("{} is not one of {}", value, allowed.join(", ")));
+        }
+        if let Some(constant) = map.get("const")
+            && !equal(constant, value)
+        {
+            push(violations, pointer, format!("{} is not {}", value, constant));
+        }
+    }
+
+    fn check_number(&self, map: &Map<String, Value>, value: &Value, pointer: &str, violations: &mut Vec<Violation>) {
+        let number = value.as_f64().unwrap_or(0.0);
+        let bound = |keyword: &str| map.get(keyword).and_then(|bound| bound.as_f64().map(|limit| (bound, limit)));
+        if let Some((bound, limit)) = bound("minimum")
+            && number < limit
+        {
+            push(violations, pointer, format!("{} is less than the minimum of {}", value, bound));
+        }
+        if let Some((bound, limit)) = bound("maximum")
+            && number > limit
+        {
+            push(violations, pointer, format!("{} is more than the maximum of {}", value, bound));
+        }
+        if let Some((bound, limit)) = bound("exclusiveMinimum")
+            && number <= limit
+        {
+            push(violations, pointer, format!("{} is not more than {}", value, bound));
+        }
+        if let Some((bound, limit)) = bound("exclusiveMaximum")
+            && number >= limit
+        {
+            push(violations, pointer, format!("{} is not less than {}", value, bound));
+        }
+        if let Some(divisor) = map.get("multipleOf")
+            && !is_multiple(value, divisor)
+        {
+            push(violations, pointer, format!("{} is not a multiple of {}", value, divisor));
+        }
+    }
+
+    fn check_string(&self, map: &Map<String, Value>, text: &str, pointer: &str, violations: &mut Vec<Violation>) {
+        let length = text.chars().count() as u64;
+        if let Some(minimum) = map.get("minLength").and_then(Value::as_u64)
+            && length < minimum
+        {
+            push(violations, pointer, format!("is {} characters long, fewer than the minimum of {}", length, minimum));
+        }
+        if let Some(maximum) = map.get("maxLength").and_then(Value::as_u64)
+            && length > maximum
+        {
+            push(violations, pointer, format!("is {} characters long, more than the maximum of {}", length, maximum));
+        }
+        if let Some(pattern) = map.get("pattern").and_then(Value::as_str)
+            && self.patterns.get(pattern).is_some_and(|regex| !regex.is_match(text))
+        {
+            push(violations, pointer, format!("does not match the pattern '{}'", pattern));
+        }
+    }
+
+    fn check_array(
+        &self,
+        map: &Map<String, Value>,
+        base: &Path,
+        items: &[Value],
+        pointer: &str,
+        violations: &mut Vec<Violation>,
+    ) {
+        let item_pointer = |index: usize| format!("{}/{}", pointer, index);
+
+        // Schemas for the first items, then one for the rest.
+        let (prefix, rest) = match self.draft {
+            Draft::Draft2020 => (map.get("prefixItems"), map.get("items")),
+            Draft::Draft7 => match map.get("items") {
+                Some(Value::Array(_)) => (map.get("items"), map.get("additionalItems")),
+                items => (None, items),
+            },
+        };
+        let prefix: &[Value] = prefix.and_then(Value::as_array).map_or(&[], Vec::as_slice);
+        for (index, (item, schema)) in items.iter().zip(prefix).enumerate() {
+            self.check(schema, base, item, &item_pointer(index), 0, violations);
+        }
+        if let Some(schema) = rest {
+            for (index, item) in items.iter().enumerate().skip(prefix.len()) {
+                self.check(schema, base, item, &item_pointer(index), 0, violations);
+            }
+        }
+
+        let count = items.len() as u64;
+        if let Some(minimum) = map.get("minItems").and_then(Value::as_u64)
+            && count < minimum
+        {
+            push(violations, pointer, format!("has {} items, fewer than the minimum of {}", count, minimum));
+        }
+        if let Some(maximum) = map.get("maxItems").and_then(Value::as_u64)
+            && count > maximum
+        {
+            push(violations, pointer, format!("has {} items, more than the maximum of {}", count, maximum));
+        }
+        if map.get("uniqueItems") == Some(&Value::Bool(true))
+            && let Some((first, second)) = (0..items.len())
+                .flat_map(|first| (first + 1..items.len()).map(move |second| (first, second)))
+                .find(|(first, second)| equal(&items[*first], &items[*second]))
+        {
+            push(violations, pointer, format!("items {} and {} are equal", first, second));
+        }
+        if let Some(schema) = map.get("contains") {
+            let matching = items.iter().filter(|item| self.matches(schema, base, item, 0)).count() as u64;
+            let minimum = map.get("minContains").and_then(Value::as_u64).unwrap_or(1);
+            let maximum = map.get("maxContains").and_then(Value::as_u64);
+            if matching < minimum {
+                let message = match minimum {
+                    1 => "no item matches the schema in contains".to_string(),
+                    _ => format!("{} items match the schema in contains, fewer than {}", matching, minimum),
+                };
+                push(violations, pointer, message);
+            }
+            if let Some(maximum) = maximum
+                && matching > maximum
+            {
+                push(violations, pointer, format!("{} items match the schema in contains, more than {}", matching, maximum));
+            }
+        }
+    }
+
+    fn check_object(
+        &self,
+        map: &Map<String, Value>,
+        base: &Path,
+        value: &Value,
+        pointer: &str,
+        depth: usize,
+        violations: &mut Vec<Violation>,
+    ) {
+        let Value::Object(object) = value else {
+            return;
+        };
+        let property_pointer = |key: &str| format!("{}/{}", pointer, pointer_token(key));
+
+        for name in map.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                push(violations, pointer, format!("missing the required property '{}'", name));
+            }
+        }
+
+        let properties = map.get("properties").and_then(Value::as_object);
+        let pattern_properties = map.get("patternProperties").and_then(Value::as_object);
+        for (key, item) in object {
+            let mut evaluated = false;
+            if let Some(schema) = properties.and_then(|properties| properties.get(key)) {
+                self.check(schema, base, item, &property_pointer(key), 0, violations);
+                evaluated = true;
+            }
+            for (pattern, schema) in pattern_properties.into_iter().flatten() {
+                if self.patterns.get(pattern).is_some_and(|regex| regex.is_match(key)) {
+                    self.check(schema, base, item, &property_pointer(key), 0, violations);
+                    evaluated = true;
+                }
+            }
+            match map.get("additionalProperties") {
+                Some(Value::Bool(false)) if !evaluated => {
+                    push(violations, &property_pointer(key), "is not an allowed property".to_string())
+                }
+                Some(schema) if !evaluated => self.check(schema, base, item, &property_pointer(key), 0, violations),
+                _ => {}
+            }
+            if let Some(schema) = map.get("propertyNames")
+                && !self.matches(schema, base, &Value::String(key.clone()), 0)
+            {
+                push(violations, &property_pointer(key), "the property name does not match propertyNames".to_string());
+            }
+        }
+
+        let count = object.len() as u64;
+        if let Some(minimum) = map.get("minProperties").and_then(Value::as_u64)
+            && count < minimum
+        {
+            push(violations, pointer, format!("has {} properties, fewer than the minimum of {}", count, minimum));
+        }
+        if let Some(maximum) = map.get("maxProperties").and_then(Value::as_u64)
+            && count > maximum
+        {
+            push(violations, pointer, format!("has {} properties, more than the maximum of {}", count, maximum));
+        }
+
+        let keywords: &[&str] = match self.draft {
+            Draft::Draft2020 => &["dependentRequired", "dependentSchemas"],
+            // Draft-07 `dependencies` holds both kinds, told apart by type.
+            Draft::Draft7 => &["dependencies"],
+        };
+        let dependencies = keywords
+            .iter()
+            .filter_map(|keyword| map.get(*keyword).and_then(Value::as_object))
+            .flatten();
+        for (name, dependency) in dependencies.filter(|(name, _)| object.contains_key(*name)) {
+            match dependency {
+                Value::Array(required) => {
+                    for required in required.iter().filter_map(Value::as_str).filter(|required| !object.contains_key(*required)) {
+                        push(violations, pointer, format!("missing the property '{}', required with '{}'", required, name));
+                    }
+                }
+                schema => self.check(schema, base, value, pointer, depth, violations),
+            }
+        }
+    }
+
+    fn check_combinators(
+        &self,
+        map: &Map<String, Value>,
+        base: &Path,
+        value: &Value,
+        pointer: &str,
+        depth: usize,
+        violations: &mut Vec<Violation>,
+    ) {
+        for schema in map.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+            self.check(schema, base, value, pointer, depth, violations);
+        }
+        if let Some(Value::Array(schemas)) = map.get("anyOf")
+            && !schemas.iter().any(|schema| self.matches(schema, base, value, depth))
+        {
+            push(violations, pointer, format!("does not match any of the {} schemas in anyOf", schemas.len()));
+        }
+        if let Some(Value::Array(schemas)) = map.get("oneOf") {
+            match schemas.iter().filter(|schema| self.matches(schema, base, value, depth)).count() {
+                1 => {}
+                0 => push(violations, pointer, format!("does not match any of the {} schemas in oneOf", schemas.len())),
+                count => push(violations, pointer, format!("matches {} of the schemas in oneOf instead of one", count)),
+            }
+        }
+        if let Some(schema) = map.get("not")
+            && self.matches(schema, base, value, depth)
+        {
+            push(violations, pointer, "matches the schema in not".to_string());
+        }
+        if let Some(condition) = map.get("if") {
+            let branch = if self.matches(condition, base, value, depth) { map.get("then") } else { map.get("else") };
+            if let Some(schema) = branch {
+                self.check(schema, base, value, pointer, depth, violations);
+            }
+        }
+    }
+}
+
+impl Schemas {
+    pub fn load(request: Option<&Path>, response: Option<&Path>) -> Result<Schemas, SchemaError> {
+        Ok(Schemas {
+            request: request.map(Schema::load).transpose()?,
+            response: response.map(Schema::load).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn directory() -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("http-client-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn write(name: &str, document: &Value) -> PathBuf {
+        let path = directory().join(name);
+        std::fs::write(&path, document.to_string()).unwrap();
+        path
+    }
+
+    fn load(name: &str, document: Value) -> Schema {
+        Schema::load(&write(name, &document)).unwrap()
+    }
+
+    /// A draft-07 schema when `draft7`, otherwise 2020-12.
+    fn draft(name: &str, draft7: bool, mut document: Value) -> Schema {
+        if draft7 {
+            document["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+        }
+        load(name, document)
+    }
+
+    fn errors(schema: &Schema, value: Value) -> Vec<String> {
+        schema.validate(&value).iter().map(Violation::to_string).collect()
+    }
+
+    #[test]
+    fn picks_the_draft() {
+        assert_eq!(load("default.json", json!({})).draft, Draft::Draft2020);
+        assert_eq!(draft("seven.json", true, json!({})).draft, Draft::Draft7);
+        let path = write("draft4.json", &json!({"$schema": "http://json-schema.org/draft-04/schema#"}));
+        assert_eq!(
+            Schema::load(&path).unwrap_err().to_string(),
+            format!(
+                "'{}' uses http://json-schema.org/draft-04/schema#; draft 2020-12 and draft-07 are supported",
+                path.display()
+            )
+        );
+    }
+
+    #[test]
+    fn reports_bad_references_and_patterns() {
+        let path = write("dangling.json", &json!({"$ref": "#/$defs/missing"}));
+        assert!(matches!(Schema::load(&path), Err(SchemaError::Reference(_, reference)) if reference == "#/$defs/missing"));
+        let path = write("bad-pattern.json", &json!({"properties": {"a": {"pattern": "a("}}}));
+        assert!(matches!(Schema::load(&path), Err(SchemaError::Pattern(_, pattern, _)) if pattern == "a("));
+    }
+
+    #[test]
+    fn leaves_data_keywords_alone() {
+        // Neither of these is a schema, so nothing in them is compiled or followed.
+        let schema = load(
+            "data.json",
+            json!({
+                "const": {"pattern": "(", "$ref": "missing.json"},
+                "enum": [{"patternProperties": {"[": {}}}],
+                "examples": [{"$ref": "#/nowhere"}],
+                "properties": {"a": {"default": {"pattern": "("}}}
+            }),
+        );
+        assert!(schema.patterns.is_empty());
+        assert_eq!(schema.documents.len(), 1);
+    }
+
+    #[test]
+    fn checks_types_enums_and_constants() {
+        let schema = load("generic.json", json!({"type": ["integer", "null"], "enum": [1, 2, null], "not": {"const": 2}}));
+        assert!(errors(&schema, json!(1.0)).is_empty());
+        assert!(errors(&schema, json!(null)).is_empty());
+        assert_eq!(
+            errors(&schema, json!("1")),
+            ["(root): expected integer or null, got string", r#"(root): "1" is not one of 1, 2, null"#]
+        );
+        assert_eq!(errors(&schema, json!(2)), ["(root): matches the schema in not"]);
+        assert_eq!(errors(&load("false.json", json!(false)), json!(1)), ["(root): no value is allowed here"]);
+    }
+
+    #[test]
+    fn checks_numbers_and_strings() {
+        let schema = load(
+            "scalars.json",
+            json!({
+                "properties": {
+                    "n": {"minimum": 1, "exclusiveMaximum": 10, "multipleOf": 0.5},
+                    "s": {"minLength": 2, "maxLength": 3, "pattern": "^[a-zé]+$"}
+                }
+            }),
+        );
+        assert!(errors(&schema, json!({"n": 9.5, "s": "éa"})).is_empty());
+        assert_eq!(
+            errors(&schema, json!({"n": 10, "s": "ABCD"})),
+            [
+                "/n: 10 is not less than 10",
+                "/s: is 4 characters long, more than the maximum of 3",
+                "/s: does not match the pattern '^[a-zé]+$'"
+            ]
+        );
+        assert_eq!(
+            errors(&schema, json!({"n": 0.25})),
+            ["/n: 0.25 is less than the minimum of 1", "/n: 0.25 is not a multiple of 0.5"]
+        );
+    }
+
+    #[test]
+    fn checks_items_by_draft() {
+        let schema = load("items-2020.json", json!({"prefixItems": [{"type": "string"}], "items": {"type": "integer"}}));
+        assert!(errors(&schema, json!(["a", 1, 2])).is_empty());
+        assert_eq!(errors(&schema, json!([1, "b"])), ["/0: expected string, got integer", "/1: expected integer, got string"]);
+
+        let schema = draft("items-07.json", true, json!({"items": [{"type": "string"}], "additionalItems": false}));
+        assert!(errors(&schema, json!(["a"])).is_empty());
+        assert_eq!(errors(&schema, json!(["a", 1])), ["/1: no value is allowed here"]);
+        let schema = draft("items-07-all.json", true, json!({"items": {"type": "string"}}));
+        assert_eq!(errors(&schema, json!(["a", 1])), ["/1: expected string, got integer"]);
+        // 2020-12 has no additionalItems.
+        let schema = load("additional-2020.json", json!({"prefixItems": [{}], "additionalItems": false}));
+        assert!(errors(&schema, json!([1, 2])).is_empty());
+    }
+
+    #[test]
+    fn checks_array_sizes_and_contents() {
+        let schema = load(
+            "arrays.json",
+            json!({"minItems": 2, "maxItems": 4, "uniqueItems": true, "contains": {"type": "string"}, "maxContains": 1}),
+        );
+        assert!(errors(&schema, json!([1, "a"])).is_empty());
+        assert_eq!(
+            errors(&schema, json!([1])),
+            ["(root): has 1 items, fewer than the minimum of 2", "(root): no item matches the schema in contains"]
+        );
+        assert_eq!(
+            errors(&schema, json!([1.0, 1, "a", "b", 2])),
+            [
+                "(root): has 5 items, more than the maximum of 4",
+                "(root): items 0 and 1 are equal",
+                "(root): 2 items match the schema in contains, more than 1"
+            ]
+        );
+        let schema = load("min-contains.json", json!({"contains": {"const": 1}, "minContains": 2}));
+        assert_eq!(errors(&schema, json!([1, 2])), ["(root): 1 items match the schema in contains, fewer than 2"]);
+    }
+
+    #[test]
+    fn checks_properties() {
+        let schema = load(
+            "objects.json",
+            json!({
+                "required": ["id"],
+                "properties": {"id": {"type": "integer"}},
+                "patternProperties": {"^x-": {"type": "string"}},
+                "additionalProperties": false,
+                "propertyNames": {"maxLength": 5},
+                "maxProperties": 3
+            }),
+        );
+        assert!(errors(&schema, json!({"id": 1, "x-a": "b"})).is_empty());
+        assert_eq!(
+            errors(&schema, json!({"x-long": 1, "other": true, "a/b": 1, "c": 2})),
+            [
+                "(root): missing the required property 'id'",
+                "/a~1b: is not an allowed property",
+                "/c: is not an allowed property",
+                "/other: is not an allowed property",
+                "/x-long: expected string, got integer",
+                "/x-long: the property name does not match propertyNames",
+                "(root): has 4 properties, more than the maximum of 3"
+            ]
+        );
+        let schema = load("additional-schema.json", json!({"properties": {"a": {}}, "additionalProperties": {"type": "string"}}));
+        assert_eq!(errors(&schema, json!({"a": 1, "b": 2})), ["/b: expected string, got integer"]);
+    }
+
+    #[test]
+    fn checks_dependencies_by_draft() {
+        let schema = load(
+            "dependent-2020.json",
+            json!({"dependentRequired": {"card": ["cvc"]}, "dependentSchemas": {"card": {"required": ["name"]}}}),
+        );
+        assert!(errors(&schema, json!({"other": 1})).is_empty());
+        assert_eq!(
+            errors(&schema, json!({"card": 1})),
+            ["(root): missing the property 'cvc', required with 'card'", "(root): missing the required property 'name'"]
+        );
+
+        let schema = draft(
+            "dependencies-07.json",
+            true,
+            json!({"dependencies": {"card": ["cvc"], "name": {"properties": {"name": {"type": "string"}}}}}),
+        );
+        assert_eq!(
+            errors(&schema, json!({"card": 1, "name": 2})),
+            ["(root): missing the property 'cvc', required with 'card'", "/name: expected string, got integer"]
+        );
+
+        // Each draft ignores the other's keywords.
+        let both = json!({"dependentRequired": {"a": ["b"]}, "dependentSchemas": {"a": false}, "dependencies": {"a": ["c"]}});
+        let schema = load("dependencies-in-2020.json", both.clone());
+        assert_eq!(
+            errors(&schema, json!({"a": 1})),
+            ["(root): missing the property 'b', required with 'a'", "(root): no value is allowed here"]
+        );
+        let schema = draft("dependent-in-07.json", true, both);
+        assert_eq!(errors(&schema, json!({"a": 1})), ["(root): missing the property 'c', required with 'a'"]);
+    }
+
+    #[test]
+    fn refuses_unevaluated_keywords_in_2020_12() {
+        let path = write("unevaluated.json", &json!({"allOf": [{"unevaluatedProperties": false}]}));
+        assert_eq!(
+            Schema::load(&path).unwrap_err().to_string(),
+            format!("'{}' uses unevaluatedProperties, which is not supported", path.display())
+        );
+        write("unevaluated-items.json", &json!({"unevaluatedItems": false}));
+        let path = write("refers-to-unevaluated.json", &json!({"$ref": "unevaluated-items.json"}));
+        assert!(matches!(
+            Schema::load(&path),
+            Err(SchemaError::UnsupportedKeyword(file, "unevaluatedItems")) if file.ends_with("unevaluated-items.json")
+        ));
+
+        // Draft-07 does not know them, so they are ignored there.
+        let schema = draft("unevaluated-07.json", true, json!({"unevaluatedProperties": false}));
+        assert!(errors(&schema, json!({"a": 1})).is_empty());
+    }
+
+    #[test]
+    fn checks_combinators() {
+        let schema = load(
+            "combinators.json",
+            json!({
+                "allOf": [{"type": "object"}],
+                "anyOf": [{"required": ["a"]}, {"required": ["b"]}],
+                "oneOf": [{"required": ["a"]}, {"required": ["c"]}],
+                "if": {"required": ["a"]},
+                "then": {"properties": {"a": {"type": "string"}}},
+                "else": {"required": ["d"]}
+            }),
+        );
+        assert!(errors(&schema, json!({"a": "x"})).is_empty());
+        assert_eq!(
+            errors(&schema, json!({"a": 1, "c": 1})),
+            ["(root): matches 2 of the schemas in oneOf instead of one", "/a: expected string, got integer"]
+        );
+        assert_eq!(
+            errors(&schema, json!({"c": 1})),
+            ["(root): does not match any of the 2 schemas in anyOf", "(root): missing the required property 'd'"]
+        );
+        // `required` only applies to objects, so an array meets both branches.
+        assert_eq!(
+            errors(&schema, json!([])),
+            ["(root): expected object, got array", "(root): matches 2 of the schemas in oneOf instead of one"]
+        );
+    }
+
+    #[test]
+    fn follows_references_by_draft() {
+        write("shared.json", &json!({"$defs": {"id": {"type": "integer", "minimum": 1}}}));
+        let schema = load(
+            "refs-2020.json",
+            json!({
+                "properties": {
+                    "id": {"$ref": "shared.json#/$defs/id", "maximum": 5},
+                    "name": {"$ref": "#name"},
+                    "odd": {"$ref": "#/$defs/a~1b%25"}
+                },
+                "$defs": {"name": {"$anchor": "name", "type": "string"}, "a/b%": {"const": 1}}
+            }),
+        );
+        assert!(errors(&schema, json!({"id": 3, "name": "x", "odd": 1})).is_empty());
+        assert_eq!(
+            errors(&schema, json!({"id": 9, "name": 1, "odd": 2})),
+            [
+                "/id: 9 is more than the maximum of 5",
+                "/name: expected string, got integer",
+                "/odd: 2 is not 1"
+            ]
+        );
+
+        // Before 2019-09, keywords next to $ref are ignored.
+        let schema = draft(
+            "refs-07.json",
+            true,
+            json!({
+                "properties": {"id": {"$ref": "shared.json#/$defs/id", "maximum": 5}, "name": {"$ref": "#name"}},
+                "definitions": {"name": {"$id": "#name", "type": "string"}}
+            }),
+        );
+        assert!(errors(&schema, json!({"id": 9})).is_empty());
+        assert_eq!(errors(&schema, json!({"id": 0, "name": 1})), ["/id: 0 is less than the minimum of 1", "/name: expected string, got integer"]);
+    }
+
+    #[test]
+    fn stops_references_that_never_reach_a_value() {
+        let schema = load("loop.json", json!({"$ref": "#/$defs/a", "$defs": {"a": {"$ref": "#/$defs/a"}}}));
+        assert_eq!(errors(&schema, json!(1)), ["(root): $ref '#/$defs/a' nests too deeply"]);
+    }
+
+    #[test]
+    fn follows_recursive_schemas_into_deep_values() {
+        let schema = load(
+            "tree.json",
+            json!({
+                "$ref": "#/$defs/node",
+                "$defs": {"node": {"type": "object", "properties": {"children": {"items": {"$ref": "#/$defs/node"}}}}}
+            }),
+        );
+        let mut value = json!({});
+        for _ in 0..(MAX_REF_DEPTH * 2) {
+            value = json!({"children": [value]});
+        }
+        assert!(errors(&schema, value).is_empty());
+        let mut broken = json!(1);
+        for _ in 0..(MAX_REF_DEPTH * 2) {
+            broken = json!({"children": [broken]});
+        }
+        let violations = schema.validate(&broken);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].pointer.ends_with("/children/0"));
+        assert_eq!(violations[0].message, "expected object, got integer");
+    }
+
+    #[test]
+    fn validates_text() {
+        let schema = load("text.json", json!({"type": "object"}));
+        assert!(schema.validate_text("{}").is_empty());
+        assert!(schema.validate_text("{").remove(0).message.starts_with("not JSON: "));
+    }
+}
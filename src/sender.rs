@@ -0,0 +1,82 @@
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Request, Response};
+use std::time::Instant;
+
+use crate::auth::{self, Auth, AuthError};
+use crate::client::{Client, ClientSettings, SendError};
+use crate::oauth::{OAuthError, OAuthSession};
+use crate::retry::Attempt;
+use crate::signing::{RequestSigner, SigningError};
+use crate::sigv4::{self, SigV4Error};
+use crate::timing::Timings;
+
+/// Signs, authenticates and times each hop of a request, for the CLI and
+/// the TUI alike.
+pub struct Sender<'a> {
+    pub client: &'a Client,
+    pub settings: &'a ClientSettings,
+    pub auth: &'a Auth,
+    pub oauth: Option<&'a OAuthSession>,
+    pub signers: &'a [RequestSigner],
+    pub sigv4: Option<&'a sigv4::Signer>,
+}
+
+/// One hop as sent.
+pub struct Sent<E> {
+    pub response: Result<Response, E>,
+    /// Every attempt made, the last one being the response's.
+    pub attempts: Vec<Attempt>,
+    /// Phases of the last attempt and when its response headers arrived.
+    pub timings: (Timings, Instant),
+}
+
+impl Sender<'_> {
+    /// Applies the signing steps. Runs for every hop, so signatures cover the
+    /// method and URL actually sent.
+    pub fn sign<E>(&self, request: &mut Request) -> Result<(), E>
+    where
+        E: From<SigningError> + From<SigV4Error> + From<OAuthError> + From<AuthError>,
+    {
+        for signer in self.signers {
+            signer.sign(request)?;
+        }
+        if let Some(signer) = self.sigv4 {
+            signer.sign(request)?;
+        }
+        // OAuth 2.0 and Digest add their own Authorization header when sending.
+        if request.headers().contains_key(AUTHORIZATION) {
+            if self.oauth.is_some() {
+                return Err(OAuthError::AuthorizationSet.into());
+            }
+            if matches!(self.auth, Auth::Digest { .. }) {
+                return Err(AuthError::AuthorizationSet.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends one hop, signed and with credentials unless `with_auth` is off
+    /// because it left the original origin.
+    pub async fn send<E>(&self, mut request: Request, with_auth: bool) -> Sent<E>
+    where
+        E: From<SigningError> + From<SigV4Error> + From<OAuthError> + From<AuthError> + From<SendError>,
+    {
+        let signed = if with_auth { self.sign(&mut request) } else { Ok(()) };
+        let start = Instant::now();
+        let response = match (signed, self.oauth, with_auth) {
+            (Err(e), _, _) => Err(e),
+            (Ok(()), _, false) => self.client.execute(request).await.map_err(E::from),
+            (Ok(()), Some(oauth), true) => oauth.send(self.client, request).await.map_err(E::from),
+            (Ok(()), None, true) => auth::send_with_auth(self.client, request, self.auth).await.map_err(E::from),
+        };
+        let first_byte = Instant::now();
+        let attempts = self.settings.attempts.take();
+        // Time only the last attempt when earlier ones were retried.
+        let start = match attempts.last() {
+            Some(attempt) if attempt.number > 1 => first_byte.checked_sub(attempt.duration).unwrap_or(start),
+            _ => start,
+        };
+        let timings = (self.settings.timeline.finish(start, first_byte), first_byte);
+        Sent { response, attempts, timings }
+    }
+}
@@ -0,0 +1,408 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+use crate::content;
+use crate::protobuf::{ProtobufError, ProtobufSettings};
+
+#[derive(Debug)]
+pub enum SerializationError {
+    Decode(Serialization, String),
+    Encode(Serialization, String),
+    TrailingBytes(Serialization, usize),
+    Protobuf(ProtobufError),
+}
+
+impl fmt::Display for SerializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializationError::Decode(format, e) => write!(f, "Body is not valid {}: {}", format, e),
+            SerializationError::Encode(format, e) => write!(f, "Could not encode the body as {}: {}", format, e),
+            SerializationError::TrailingBytes(format, count) => {
+                let unit = if *count == 1 { "byte" } else { "bytes" };
+                write!(f, "Body has {} {} after the {} value", count, unit, format)
+            }
+            SerializationError::Protobuf(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SerializationError {}
+
+impl From<ProtobufError> for SerializationError {
+    fn from(error: ProtobufError) -> Self {
+        SerializationError::Protobuf(error)
+    }
+}
+
+/// Binary formats shown as JSON and encoded from JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Serialization {
+    Msgpack,
+    Cbor,
+    Protobuf,
+}
+
+impl fmt::Display for Serialization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Serialization::Msgpack => "MessagePack",
+            Serialization::Cbor => "CBOR",
+            Serialization::Protobuf => "Protobuf",
+        })
+    }
+}
+
+/// A decoded tree with map entries in the order they were read, which
+/// `serde_json::Value` does not keep.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ordered {
+    Scalar(Value),
+    Array(Vec<Ordered>),
+    Map(Vec<(String, Ordered)>),
+}
+
+impl Ordered {
+    /// A map of `entries`; a repeated key replaces the earlier value in place.
+    pub fn map(entries: impl IntoIterator<Item = (String, Ordered)>) -> Ordered {
+        let mut map: Vec<(String, Ordered)> = Vec::new();
+        for (key, value) in entries {
+            match map.iter_mut().find(|(existing, _)| *existing == key) {
+                Some(entry) => entry.1 = value,
+                None => map.push((key, value)),
+            }
+        }
+        Ordered::Map(map)
+    }
+
+    pub fn into_value(self) -> Value {
+        match self {
+            Ordered::Scalar(value) => value,
+            Ordered::Array(values) => Value::Array(values.into_iter().map(Ordered::into_value).collect()),
+            Ordered::Map(entries) => {
+                Value::Object(entries.into_iter().map(|(key, value)| (key, value.into_value())).collect())
+            }
+        }
+    }
+}
+
+impl From<Value> for Ordered {
+    fn from(value: Value) -> Ordered {
+        Ordered::Scalar(value)
+    }
+}
+
+impl Serialize for Ordered {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Ordered::Scalar(value) => value.serialize(serializer),
+            Ordered::Array(values) => serializer.collect_seq(values),
+            Ordered::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Reads JSON text, such as a serialized Protobuf message, keeping the order
+/// of object keys.
+impl<'de> Deserialize<'de> for Ordered {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Ordered, D::Error> {
+        struct OrderedVisitor;
+
+        impl<'de> Visitor<'de> for OrderedVisitor {
+            type Value = Ordered;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a JSON value")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Ordered, E> {
+                Ok(Value::Bool(value).into())
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Ordered, E> {
+                Ok(Value::from(value).into())
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Ordered, E> {
+                Ok(Value::from(value).into())
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Ordered, E> {
+                Ok(float(value).into())
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Ordered, E> {
+                Ok(Value::String(value.to_string()).into())
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Ordered, E> {
+                Ok(Value::Null.into())
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Ordered, A::Error> {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(Ordered::Array(values))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Ordered, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry::<String, Ordered>()? {
+                    entries.push(entry);
+                }
+                Ok(Ordered::map(entries))
+            }
+        }
+
+        deserializer.deserialize_any(OrderedVisitor)
+    }
+}
+
+/// A body decoded to a JSON tree.
+#[derive(Debug, Clone)]
+pub struct Decoded {
+    pub value: Value,
+    /// The tree as decoded, for showing map entries in their original order.
+    ordered: Ordered,
+    /// `MessagePack`, or `Protobuf (shop.v1.Order)` naming the message type.
+    pub description: String,
+}
+
+impl Decoded {
+    fn new(ordered: Ordered, description: String) -> Decoded {
+        Decoded { value: ordered.clone().into_value(), ordered, description }
+    }
+
+    pub fn pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.ordered).unwrap_or_else(|_| self.value.to_string())
+    }
+}
+
+impl Serialization {
+    pub fn from_content_type(content_type: &str) -> Option<Serialization> {
+        match content::media_type(content_type).as_str() {
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => Some(Serialization::Msgpack),
+            "application/cbor" => Some(Serialization::Cbor),
+            "application/protobuf" | "application/x-protobuf" | "application/vnd.google.protobuf" => {
+                Some(Serialization::Protobuf)
+            }
+            media_type if media_type.ends_with("+cbor") => Some(Serialization::Cbor),
+            media_type if media_type.ends_with("+msgpack") => Some(Serialization::Msgpack),
+            _ => None,
+        }
+    }
+
+    /// The Content-Type sent with bodies in this format.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Serialization::Msgpack => "application/msgpack",
+            Serialization::Cbor => "application/cbor",
+            Serialization::Protobuf => "application/x-protobuf",
+        }
+    }
+
+    /// Decodes a body. Byte strings become base64 strings and map keys that
+    /// are not strings are written as JSON.
+    pub fn decode(
+        &self,
+        bytes: &[u8],
+        content_type: &str,
+        protobuf: &ProtobufSettings,
+    ) -> Result<Decoded, SerializationError> {
+        let mut rest = bytes;
+        let value = match self {
+            Serialization::Msgpack => {
+                let value = rmpv::decode::read_value(&mut rest).map_err(|e| SerializationError::Decode(*self, e.to_string()))?;
+                msgpack_to_json(value)
+            }
+            Serialization::Cbor => {
+                let value: ciborium::Value =
+                    ciborium::from_reader(&mut rest).map_err(|e| SerializationError::Decode(*self, e.to_string()))?;
+                cbor_to_json(value)
+            }
+            Serialization::Protobuf => {
+                let (value, message) = protobuf.decode(bytes, content_type)?;
+                let description = match message {
+                    Some(message) => format!("Protobuf ({})", message),
+                    None => "Protobuf without a schema, keys are field numbers".to_string(),
+                };
+                return Ok(Decoded::new(value, description));
+            }
+        };
+        if !rest.is_empty() {
+            return Err(SerializationError::TrailingBytes(*self, rest.len()));
+        }
+        Ok(Decoded::new(value, self.to_string()))
+    }
+
+    /// Encodes a JSON body in this format.
+    pub fn encode(&self, json: &str, protobuf: &ProtobufSettings) -> Result<Vec<u8>, SerializationError> {
+        let error = |e: &dyn fmt::Display| SerializationError::Encode(*self, e.to_string());
+        let mut encoded = Vec::new();
+        match self {
+            Serialization::Msgpack => {
+                let value: Value = serde_json::from_str(json).map_err(|e| error(&e))?;
+                let value = rmpv::ext::to_value(&value).map_err(|e| error(&e))?;
+                rmpv::encode::write_value(&mut encoded, &value).map_err(|e| error(&e))?;
+            }
+            Serialization::Cbor => {
+                let value: Value = serde_json::from_str(json).map_err(|e| error(&e))?;
+                ciborium::into_writer(&value, &mut encoded).map_err(|e| error(&e))?;
+            }
+            Serialization::Protobuf => encoded = protobuf.encode(json)?,
+        }
+        Ok(encoded)
+    }
+}
+
+fn msgpack_to_json(value: rmpv::Value) -> Ordered {
+    let value = match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(value) => Value::Bool(value),
+        rmpv::Value::Integer(value) => match value.as_i64() {
+            Some(value) => Value::from(value),
+            None => Value::from(value.as_u64()),
+        },
+        rmpv::Value::F32(value) => float(f64::from(value)),
+        rmpv::Value::F64(value) => float(value),
+        rmpv::Value::String(value) => match value.as_str() {
+            Some(text) => Value::String(text.to_string()),
+            None => Value::String(String::from_utf8_lossy(value.as_bytes()).into_owned()),
+        },
+        rmpv::Value::Binary(bytes) => Value::String(STANDARD.encode(bytes)),
+        rmpv::Value::Array(values) => return Ordered::Array(values.into_iter().map(msgpack_to_json).collect()),
+        rmpv::Value::Map(entries) => {
+            return Ordered::map(
+                entries.into_iter().map(|(key, value)| (map_key(msgpack_to_json(key)), msgpack_to_json(value))),
+            );
+        }
+        rmpv::Value::Ext(kind, data) => {
+            return Ordered::map([
+                ("ext".to_string(), Value::from(kind).into()),
+                ("data".to_string(), Value::String(STANDARD.encode(data)).into()),
+            ]);
+        }
+    };
+    value.into()
+}
+
+fn cbor_to_json(value: ciborium::Value) -> Ordered {
+    let value = match value {
+        ciborium::Value::Null => Value::Null,
+        ciborium::Value::Bool(value) => Value::Bool(value),
+        ciborium::Value::Integer(value) => {
+            let value = i128::from(value);
+            match (i64::try_from(value), u64::try_from(value)) {
+                (Ok(value), _) => Value::from(value),
+                (_, Ok(value)) => Value::from(value),
+                _ => Value::String(value.to_string()),
+            }
+        }
+        ciborium::Value::Float(value) => float(value),
+        ciborium::Value::Text(text) => Value::String(text),
+        ciborium::Value::Bytes(bytes) => Value::String(STANDARD.encode(bytes)),
+        ciborium::Value::Array(values) => return Ordered::Array(values.into_iter().map(cbor_to_json).collect()),
+        ciborium::Value::Map(entries) => {
+            return Ordered::map(
+                entries.into_iter().map(|(key, value)| (map_key(cbor_to_json(key)), cbor_to_json(value))),
+            );
+        }
+        // Date-times are shown as their string or epoch value.
+        ciborium::Value::Tag(0 | 1, value) => return cbor_to_json(*value),
+        ciborium::Value::Tag(tag, value) => {
+            return Ordered::map([
+                ("tag".to_string(), Value::from(tag).into()),
+                ("value".to_string(), cbor_to_json(*value)),
+            ]);
+        }
+        _ => Value::Null,
+    };
+    value.into()
+}
+
+/// NaN and infinities have no JSON number, so they are shown as strings.
+fn float(value: f64) -> Value {
+    serde_json::Number::from_f64(value).map_or_else(|| Value::String(value.to_string()), Value::Number)
+}
+
+fn map_key(key: Ordered) -> String {
+    match key {
+        Ordered::Scalar(Value::String(key)) => key,
+        key => serde_json::to_string(&key).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(format: Serialization, bytes: &[u8]) -> Result<Decoded, SerializationError> {
+        format.decode(bytes, format.content_type(), &ProtobufSettings::default())
+    }
+
+    #[test]
+    fn picks_the_format_from_the_content_type() {
+        assert_eq!(Serialization::from_content_type("application/x-msgpack"), Some(Serialization::Msgpack));
+        assert_eq!(Serialization::from_content_type("application/problem+cbor"), Some(Serialization::Cbor));
+        assert_eq!(Serialization::from_content_type("application/protobuf; proto=a.B"), Some(Serialization::Protobuf));
+        assert_eq!(Serialization::from_content_type("application/json"), None);
+    }
+
+    #[test]
+    fn keeps_msgpack_map_order() {
+        // {"b": 1, "a": [true, nil], "c": bin 0xff}
+        let bytes = [0x83, 0xa1, b'b', 1, 0xa1, b'a', 0x92, 0xc3, 0xc0, 0xa1, b'c', 0xc4, 1, 0xff];
+        let decoded = decode(Serialization::Msgpack, &bytes).unwrap();
+        assert_eq!(decoded.pretty(), "{\n  \"b\": 1,\n  \"a\": [\n    true,\n    null\n  ],\n  \"c\": \"/w==\"\n}");
+        assert_eq!(decoded.value, serde_json::json!({"a": [true, null], "b": 1, "c": "/w=="}));
+        assert_eq!(decoded.description, "MessagePack");
+    }
+
+    #[test]
+    fn keeps_cbor_map_order() {
+        // {"z": 1, 2: "two", "a": 1.5}
+        let bytes = [0xa3, 0x61, b'z', 1, 2, 0x63, b't', b'w', b'o', 0x61, b'a', 0xf9, 0x3e, 0x00];
+        let decoded = decode(Serialization::Cbor, &bytes).unwrap();
+        assert_eq!(serde_json::to_string(&decoded.ordered).unwrap(), r#"{"z":1,"2":"two","a":1.5}"#);
+    }
+
+    #[test]
+    fn repeated_keys_keep_their_first_position() {
+        let map = Ordered::map([
+            ("a".to_string(), Value::from(1).into()),
+            ("b".to_string(), Value::from(2).into()),
+            ("a".to_string(), Value::from(3).into()),
+        ]);
+        assert_eq!(serde_json::to_string(&map).unwrap(), r#"{"a":3,"b":2}"#);
+    }
+
+    #[test]
+    fn reads_json_in_order() {
+        let ordered: Ordered = serde_json::from_str(r#"{"b": {"y": 1, "x": 2.5}, "a": ["s", null, -1]}"#).unwrap();
+        assert_eq!(serde_json::to_string(&ordered).unwrap(), r#"{"b":{"y":1,"x":2.5},"a":["s",null,-1]}"#);
+    }
+
+    #[test]
+    fn round_trips_and_rejects_trailing_bytes() {
+        let settings = ProtobufSettings::default();
+        for format in [Serialization::Msgpack, Serialization::Cbor] {
+            let mut encoded = format.encode(r#"{"a": [1, "x"]}"#, &settings).unwrap();
+            assert_eq!(decode(format, &encoded).unwrap().value, serde_json::json!({"a": [1, "x"]}));
+            encoded.push(0);
+            assert!(matches!(decode(format, &encoded), Err(SerializationError::TrailingBytes(_, 1))));
+            assert!(matches!(format.encode("{", &settings), Err(SerializationError::Encode(..))));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::auth::Auth;
+use crate::config::{config_dir, write_private, ConfigError};
+use crate::cookies::{CookieEntry, CookieJar};
+
+/// Request-specific headers that make no sense to replay on later requests.
+const TRANSIENT_HEADERS: [&str; 2] = ["content-type", "content-length"];
+
+/// Cookies, auth and default headers persisted between invocations with
+/// `--session <name>`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(default)]
+    pub headers: Vec<String>,
+    #[serde(default)]
+    pub auth: Auth,
+    #[serde(default)]
+    pub cookies: Vec<CookieEntry>,
+}
+
+impl Session {
+    /// Loads `sessions/<name>.json` from the config dir, or a session file
+    /// directly when `name` looks like a path. A missing file is an empty session.
+    pub fn load(name: &str) -> Result<Session, ConfigError> {
+        let path = if name.contains('/') || name.ends_with(".json") {
+            PathBuf::from(name)
+        } else {
+            config_dir().join("sessions").join(format!("{}.json", name))
+        };
+
+        let mut session = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| ConfigError::Parse(path.clone(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Session::default(),
+            Err(e) => return Err(ConfigError::Io(path, e)),
+        };
+        session.path = path;
+        Ok(session)
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| ConfigError::Parse(self.path.clone(), e))?;
+        // Sessions hold credentials and cookies.
+        write_private(&self.path, text.as_bytes()).map_err(|e| ConfigError::Io(self.path.clone(), e))
+    }
+
+    /// Returns the headers to send: the session's defaults overridden by
+    /// `headers`. The new values are remembered for the next request.
+    pub fn merge_headers(&mut self, headers: &[String]) -> Vec<String> {
+        let mut merged: Vec<String> = self
+            .headers
+            .iter()
+            .filter(|stored| !headers.iter().any(|header| header_name(header) == header_name(stored)))
+            .cloned()
+            .collect();
+        merged.extend(headers.iter().cloned());
+
+        self.remember_headers(&merged);
+        merged
+    }
+
+    /// Replaces the stored default headers, dropping request-specific ones.
+    pub fn remember_headers(&mut self, headers: &[String]) {
+        self.headers = headers
+            .iter()
+            .filter(|header| {
+                let name = header_name(header);
+                !TRANSIENT_HEADERS.contains(&name.as_str()) && !name.starts_with("if-")
+            })
+            .cloned()
+            .collect();
+    }
+
+    pub fn cookie_jar(&self) -> Result<CookieJar, crate::cookies::CookieError> {
+        CookieJar::with_entries(&self.cookies)
+    }
+
+    pub fn store_cookies(&mut self, jar: &CookieJar) {
+        self.cookies = jar.entries();
+    }
+}
+
+fn header_name(header: &str) -> String {
+    header
+        .split_once(':')
+        .map(|(name, _)| name.trim().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_and_remembers_headers() {
+        let mut session = Session {
+            headers: vec!["Accept: text/plain".to_string(), "X-Team: core".to_string()],
+            ..Session::default()
+        };
+        let merged = session.merge_headers(&[
+            "accept: application/json".to_string(),
+            "Content-Type: application/json".to_string(),
+            "If-None-Match: \"abc\"".to_string(),
+        ]);
+        assert_eq!(
+            merged,
+            ["X-Team: core", "accept: application/json", "Content-Type: application/json", "If-None-Match: \"abc\""]
+        );
+        assert_eq!(session.headers, ["X-Team: core", "accept: application/json"]);
+    }
+
+    #[test]
+    fn saves_and_loads_sessions() {
+        let path = std::env::temp_dir().join(format!("http-client-session-{}.json", std::process::id()));
+        let mut session = Session::load(path.to_str().unwrap()).unwrap();
+        assert!(session.headers.is_empty());
+        session.auth = Auth::Bearer { token: "t".to_string() };
+        session.remember_headers(&["X-Team: core".to_string()]);
+        session.save().unwrap();
+
+        let loaded = Session::load(path.to_str().unwrap()).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(loaded.auth, Auth::Bearer { token } if token == "t"));
+        assert_eq!(loaded.headers, ["X-Team: core"]);
+    }
+}
@@ -0,0 +1,437 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{EncodingKey, Header};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256, Sha512};
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum SigningError {
+    MissingKey,
+    KeyFile(PathBuf, std::io::Error),
+    InvalidTemplate(String),
+    InvalidClaim(String),
+    InvalidHeader(String),
+    AuthorizationSet,
+    Jwt(jsonwebtoken::errors::Error),
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningError::MissingKey => {
+                write!(f, "HMAC signing needs a key or key_file")
+            }
+            SigningError::KeyFile(path, e) => {
+                write!(f, "Could not read key file '{}': {}", path.display(), e)
+            }
+            SigningError::InvalidTemplate(text) => {
+                write!(f, "Invalid signing template placeholder: '{{{}}}'", text)
+            }
+            SigningError::InvalidClaim(text) => {
+                write!(f, "Invalid JWT claim: '{}'. Use 'name=value' format", text)
+            }
+            SigningError::InvalidHeader(text) => {
+                write!(f, "Invalid signature header: '{}'", text)
+            }
+            SigningError::AuthorizationSet => {
+                write!(f, "The signature would replace the Authorization header another auth option set; choose another header for it")
+            }
+            SigningError::Jwt(e) => {
+                write!(f, "Could not create JWT: {}", e)
+            }
+        }
+    }
+}
+
+impl Error for SigningError {}
+
+impl From<jsonwebtoken::errors::Error> for SigningError {
+    fn from(error: jsonwebtoken::errors::Error) -> Self {
+        SigningError::Jwt(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HmacAlgorithm {
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+pub const DEFAULT_HMAC_TEMPLATE: &str = "{method}\\n{path}\\n{timestamp}\\n{body}";
+
+/// HMAC signature over a canonical string built from `template`.
+///
+/// Placeholders: `{method}`, `{path}` (with query), `{query}`, `{host}`,
+/// `{timestamp}` (Unix seconds), `{date}` (RFC 3339), `{body}`,
+/// `{body_sha256}` and `{header:Name}`. `\n` in the template is a newline.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HmacConfig {
+    pub key: Option<String>,
+    pub key_file: Option<PathBuf>,
+    #[serde(default)]
+    pub algorithm: HmacAlgorithm,
+    #[serde(default = "default_hmac_template")]
+    pub template: String,
+    #[serde(default = "default_signature_header")]
+    pub header: String,
+    #[serde(default = "default_timestamp_header")]
+    pub timestamp_header: Option<String>,
+    #[serde(default)]
+    pub encoding: SignatureEncoding,
+}
+
+fn default_hmac_template() -> String {
+    DEFAULT_HMAC_TEMPLATE.to_string()
+}
+
+fn default_signature_header() -> String {
+    "X-Signature".to_string()
+}
+
+fn default_timestamp_header() -> Option<String> {
+    Some("X-Timestamp".to_string())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "UPPERCASE")]
+#[value(rename_all = "UPPER")]
+pub enum JwtAlgorithm {
+    #[default]
+    HS256,
+    RS256,
+    ES256,
+}
+
+/// A JWT minted for every request. HS256 reads the raw secret from
+/// `key_file`, RS256/ES256 read a PEM private key.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtConfig {
+    pub key_file: PathBuf,
+    #[serde(default)]
+    pub algorithm: JwtAlgorithm,
+    #[serde(default)]
+    pub claims: Map<String, Value>,
+    #[serde(default = "default_jwt_expiry")]
+    pub expiry_secs: u64,
+    #[serde(default = "default_jwt_header")]
+    pub header: String,
+}
+
+fn default_jwt_expiry() -> u64 {
+    300
+}
+
+fn default_jwt_header() -> String {
+    "Authorization".to_string()
+}
+
+/// Parses `--jwt-claim name=value`; values that are valid JSON keep their type.
+pub fn parse_claim(spec: &str) -> Result<(String, Value), SigningError> {
+    let (name, value) = spec
+        .split_once('=')
+        .filter(|(name, _)| !name.trim().is_empty())
+        .ok_or_else(|| SigningError::InvalidClaim(spec.to_string()))?;
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((name.trim().to_string(), value))
+}
+
+/// A signing step applied to the fully built request, just before sending.
+#[derive(Debug, Clone)]
+pub enum RequestSigner {
+    Hmac(HmacConfig),
+    Jwt(JwtConfig),
+}
+
+impl RequestSigner {
+    pub fn sign(&self, request: &mut reqwest::Request) -> Result<(), SigningError> {
+        let header = match self {
+            RequestSigner::Hmac(config) => &config.header,
+            RequestSigner::Jwt(config) => &config.header,
+        };
+        if header.eq_ignore_ascii_case(AUTHORIZATION.as_str()) && request.headers().contains_key(AUTHORIZATION) {
+            return Err(SigningError::AuthorizationSet);
+        }
+        match self {
+            RequestSigner::Hmac(config) => sign_hmac(config, request),
+            RequestSigner::Jwt(config) => sign_jwt(config, request),
+        }
+    }
+}
+
+fn sign_hmac(config: &HmacConfig, request: &mut reqwest::Request) -> Result<(), SigningError> {
+    let key = match (&config.key, &config.key_file) {
+        (Some(key), _) => key.as_bytes().to_vec(),
+        (None, Some(path)) => std::fs::read(path).map_err(|e| SigningError::KeyFile(path.clone(), e))?,
+        (None, None) => return Err(SigningError::MissingKey),
+    };
+
+    let now = chrono::Utc::now();
+    let timestamp = now.timestamp().to_string();
+    if let Some(name) = &config.timestamp_header {
+        insert_header(request, name, &timestamp)?;
+    }
+
+    let canonical = render_template(&config.template, request, &timestamp, &now.to_rfc3339())?;
+    let signature = match config.algorithm {
+        HmacAlgorithm::Sha256 => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts keys of any length");
+            mac.update(canonical.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+        HmacAlgorithm::Sha512 => {
+            let mut mac = Hmac::<Sha512>::new_from_slice(&key).expect("HMAC accepts keys of any length");
+            mac.update(canonical.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        }
+    };
+
+    let encoded = match config.encoding {
+        SignatureEncoding::Hex => signature.iter().map(|b| format!("{:02x}", b)).collect(),
+        SignatureEncoding::Base64 => STANDARD.encode(&signature),
+    };
+    insert_header(request, &config.header, &encoded)
+}
+
+fn render_template(
+    template: &str,
+    request: &reqwest::Request,
+    timestamp: &str,
+    date: &str,
+) -> Result<String, SigningError> {
+    let url = request.url();
+    let body = request.body().and_then(|body| body.as_bytes()).unwrap_or_default();
+    let template = template.replace("\\n", "\n");
+
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| SigningError::InvalidTemplate(rest[start + 1..].to_string()))?;
+        let placeholder = &rest[start + 1..end];
+
+        let value = match placeholder {
+            "method" => request.method().as_str().to_string(),
+            "path" => match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            },
+            "query" => url.query().unwrap_or_default().to_string(),
+            "host" => url.host_str().unwrap_or_default().to_string(),
+            "timestamp" => timestamp.to_string(),
+            "date" => date.to_string(),
+            "body" => String::from_utf8_lossy(body).into_owned(),
+            "body_sha256" => Sha256::digest(body).iter().map(|b| format!("{:02x}", b)).collect(),
+            _ => match placeholder.strip_prefix("header:") {
+                Some(name) => request
+                    .headers()
+                    .get(name.trim())
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string(),
+                None => return Err(SigningError::InvalidTemplate(placeholder.to_string())),
+            },
+        };
+        output.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn sign_jwt(config: &JwtConfig, request: &mut reqwest::Request) -> Result<(), SigningError> {
+    let key_data = std::fs::read(&config.key_file).map_err(|e| SigningError::KeyFile(config.key_file.clone(), e))?;
+    let (algorithm, key) = match config.algorithm {
+        JwtAlgorithm::HS256 => (jsonwebtoken::Algorithm::HS256, EncodingKey::from_secret(&key_data)),
+        JwtAlgorithm::RS256 => (jsonwebtoken::Algorithm::RS256, EncodingKey::from_rsa_pem(&key_data)?),
+        JwtAlgorithm::ES256 => (jsonwebtoken::Algorithm::ES256, EncodingKey::from_ec_pem(&key_data)?),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let mut claims = Map::new();
+    claims.insert("iat".to_string(), Value::from(now));
+    claims.insert("exp".to_string(), Value::from(now + config.expiry_secs as i64));
+    let jti: String = rand::thread_rng().sample_iter(&Alphanumeric).take(16).map(char::from).collect();
+    claims.insert("jti".to_string(), Value::from(jti));
+    // Configured claims may override the generated ones.
+    claims.extend(config.claims.clone());
+
+    let token = jsonwebtoken::encode(&Header::new(algorithm), &claims, &key)?;
+    let value = if config.header.eq_ignore_ascii_case("authorization") {
+        format!("Bearer {}", token)
+    } else {
+        token
+    };
+    insert_header(request, &config.header, &value)
+}
+
+fn insert_header(request: &mut reqwest::Request, name: &str, value: &str) -> Result<(), SigningError> {
+    let name: HeaderName = name
+        .parse()
+        .map_err(|_| SigningError::InvalidHeader(name.to_string()))?;
+    let value = HeaderValue::from_str(value).map_err(|_| SigningError::InvalidHeader(value.to_string()))?;
+    request.headers_mut().insert(name, value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Method;
+
+    fn request() -> reqwest::Request {
+        let mut request = reqwest::Request::new(Method::POST, "https://api.example.com/orders?x=1".parse().unwrap());
+        request.headers_mut().insert("x-request-id", HeaderValue::from_static("42"));
+        *request.body_mut() = Some(r#"{"id":1}"#.into());
+        request
+    }
+
+    fn hmac_config(template: &str) -> HmacConfig {
+        HmacConfig {
+            key: Some("key".to_string()),
+            key_file: None,
+            algorithm: HmacAlgorithm::Sha256,
+            template: template.to_string(),
+            header: default_signature_header(),
+            timestamp_header: default_timestamp_header(),
+            encoding: SignatureEncoding::Hex,
+        }
+    }
+
+    fn key_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("http-client-signing-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn renders_template_placeholders() {
+        let template = "{method} {path} {query} {host} {timestamp} {date}\\n{header:X-Request-Id} {body} {body_sha256}";
+        let rendered = render_template(template, &request(), "1700000000", "2023-11-14T22:13:20+00:00").unwrap();
+        assert_eq!(
+            rendered,
+            "POST /orders?x=1 x=1 api.example.com 1700000000 2023-11-14T22:13:20+00:00\n42 {\"id\":1} \
+             037c9214eef74cc3887f3a4f085b4e17d76280dafd273b0ee160c09c4ba1cfd4"
+        );
+        assert!(matches!(
+            render_template("{nope}", &request(), "", ""),
+            Err(SigningError::InvalidTemplate(name)) if name == "nope"
+        ));
+        assert!(matches!(render_template("{method", &request(), "", ""), Err(SigningError::InvalidTemplate(_))));
+    }
+
+    #[test]
+    fn signs_with_hmac() {
+        let mut request = request();
+        RequestSigner::Hmac(hmac_config(DEFAULT_HMAC_TEMPLATE)).sign(&mut request).unwrap();
+
+        let timestamp = request.headers()["x-timestamp"].to_str().unwrap().to_string();
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"key").unwrap();
+        mac.update(format!("POST\n/orders?x=1\n{}\n{{\"id\":1}}", timestamp).as_bytes());
+        let expected: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(request.headers()["x-signature"], expected.as_str());
+    }
+
+    #[test]
+    fn encodes_hmac_signatures_in_base64() {
+        let mut config = hmac_config("{method}");
+        config.algorithm = HmacAlgorithm::Sha512;
+        config.encoding = SignatureEncoding::Base64;
+        config.timestamp_header = None;
+        let mut request = request();
+        RequestSigner::Hmac(config).sign(&mut request).unwrap();
+
+        let mut mac = Hmac::<Sha512>::new_from_slice(b"key").unwrap();
+        mac.update(b"POST");
+        assert_eq!(request.headers()["x-signature"], STANDARD.encode(mac.finalize().into_bytes()).as_str());
+        assert!(!request.headers().contains_key("x-timestamp"));
+    }
+
+    #[test]
+    fn mints_jwt_with_claims() {
+        let path = key_file("jwt", b"secret");
+        let mut claims = Map::new();
+        claims.insert("iss".to_string(), Value::from("me"));
+        claims.insert("jti".to_string(), Value::from("fixed"));
+        let config = JwtConfig {
+            key_file: path.clone(),
+            algorithm: JwtAlgorithm::HS256,
+            claims,
+            expiry_secs: 60,
+            header: default_jwt_header(),
+        };
+        let mut request = request();
+        RequestSigner::Jwt(config).sign(&mut request).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let header = request.headers()["authorization"].to_str().unwrap();
+        let token = header.strip_prefix("Bearer ").unwrap();
+        let decoded = jsonwebtoken::decode::<Map<String, Value>>(
+            token,
+            &jsonwebtoken::DecodingKey::from_secret(b"secret"),
+            &jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256),
+        )
+        .unwrap();
+        let claims = decoded.claims;
+        assert_eq!(claims["iss"], "me");
+        assert_eq!(claims["jti"], "fixed");
+        assert_eq!(claims["exp"].as_i64().unwrap() - claims["iat"].as_i64().unwrap(), 60);
+    }
+
+    #[test]
+    fn jwt_in_other_header_has_no_bearer_prefix() {
+        let path = key_file("jwt-header", b"secret");
+        let config = JwtConfig {
+            key_file: path.clone(),
+            algorithm: JwtAlgorithm::HS256,
+            claims: Map::new(),
+            expiry_secs: 60,
+            header: "X-Jwt".to_string(),
+        };
+        let mut request = request();
+        request.headers_mut().insert(AUTHORIZATION, HeaderValue::from_static("Basic x"));
+        RequestSigner::Jwt(config).sign(&mut request).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(request.headers()["x-jwt"].to_str().unwrap().split('.').count(), 3);
+        assert_eq!(request.headers()[AUTHORIZATION], "Basic x");
+    }
+
+    #[test]
+    fn refuses_to_replace_authorization() {
+        let mut config = hmac_config("{method}");
+        config.header = "Authorization".to_string();
+        let mut request = request();
+        request.headers_mut().insert(AUTHORIZATION, HeaderValue::from_static("Basic x"));
+        assert!(matches!(RequestSigner::Hmac(config).sign(&mut request), Err(SigningError::AuthorizationSet)));
+    }
+
+    #[test]
+    fn parses_claims() {
+        assert_eq!(parse_claim("aud=partner").unwrap(), ("aud".to_string(), Value::from("partner")));
+        assert_eq!(parse_claim("n=42").unwrap(), ("n".to_string(), Value::from(42)));
+        assert_eq!(parse_claim("roles=[\"a\"]").unwrap().1, serde_json::json!(["a"]));
+        assert!(parse_claim("=x").is_err());
+        assert!(parse_claim("novalue").is_err());
+    }
+}
@@ -0,0 +1,496 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, HOST};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Headers that intermediaries or the HTTP stack may rewrite, so they are
+/// never part of the signature.
+const UNSIGNED_HEADERS: [&str; 4] = ["authorization", "user-agent", "content-length", "expect"];
+
+#[derive(Debug)]
+pub enum SigV4Error {
+    InvalidSpec(String),
+    MissingCredentials(String),
+    InvalidHeader(String),
+    AuthorizationSet,
+}
+
+impl fmt::Display for SigV4Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigV4Error::InvalidSpec(spec) => {
+                write!(f, "Invalid --aws-sigv4 value: '{}'. Use 'service:region' format", spec)
+            }
+            SigV4Error::MissingCredentials(reason) => {
+                write!(f, "No AWS credentials: {}", reason)
+            }
+            SigV4Error::InvalidHeader(value) => {
+                write!(f, "Could not build signed header: '{}'", value)
+            }
+            SigV4Error::AuthorizationSet => {
+                write!(f, "--aws-sigv4 needs the Authorization header, but another auth option already set it")
+            }
+        }
+    }
+}
+
+impl Error for SigV4Error {}
+
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl Credentials {
+    /// Reads `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY`/`AWS_SESSION_TOKEN`,
+    /// falling back to the shared credentials file.
+    pub fn load(profile: Option<&str>) -> Result<Credentials, SigV4Error> {
+        if profile.is_none()
+            && let (Ok(access_key_id), Ok(secret_access_key)) = (
+                std::env::var("AWS_ACCESS_KEY_ID"),
+                std::env::var("AWS_SECRET_ACCESS_KEY"),
+            )
+        {
+            return Ok(Credentials {
+                access_key_id,
+                secret_access_key,
+                session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+            });
+        }
+
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| std::env::var("AWS_PROFILE").ok())
+            .unwrap_or_else(|| "default".to_string());
+        let path = std::env::var_os("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".aws").join("credentials")))
+            .ok_or_else(|| SigV4Error::MissingCredentials("no home directory".to_string()))?;
+        let text = std::fs::read_to_string(&path).map_err(|e| {
+            SigV4Error::MissingCredentials(format!("could not read '{}': {}", path.display(), e))
+        })?;
+
+        let section = ini_section(&text, &profile).ok_or_else(|| {
+            SigV4Error::MissingCredentials(format!("profile '{}' not found in '{}'", profile, path.display()))
+        })?;
+        let field = |key: &str| {
+            section
+                .get(key)
+                .cloned()
+                .ok_or_else(|| SigV4Error::MissingCredentials(format!("profile '{}' has no {}", profile, key)))
+        };
+
+        Ok(Credentials {
+            access_key_id: field("aws_access_key_id")?,
+            secret_access_key: field("aws_secret_access_key")?,
+            session_token: section.get("aws_session_token").cloned(),
+        })
+    }
+}
+
+fn ini_section(text: &str, name: &str) -> Option<BTreeMap<String, String>> {
+    let mut current: Option<String> = None;
+    let mut values = BTreeMap::new();
+    let mut found = false;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let header = header.trim();
+            // The config file spells non-default profiles as `[profile name]`.
+            current = Some(header.strip_prefix("profile ").unwrap_or(header).trim().to_string());
+            found |= current.as_deref() == Some(name);
+            continue;
+        }
+        if current.as_deref() == Some(name)
+            && let Some((key, value)) = line.split_once('=')
+        {
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+
+    found.then_some(values)
+}
+
+#[derive(Debug, Clone)]
+pub struct Signer {
+    pub service: String,
+    pub region: String,
+    pub credentials: Credentials,
+    /// Sign with `UNSIGNED-PAYLOAD` instead of hashing the body.
+    pub unsigned_payload: bool,
+}
+
+impl Signer {
+    pub fn from_spec(spec: &str, profile: Option<&str>, unsigned_payload: bool) -> Result<Signer, SigV4Error> {
+        let (service, region) = spec
+            .split_once(':')
+            .filter(|(service, region)| !service.is_empty() && !region.is_empty())
+            .ok_or_else(|| SigV4Error::InvalidSpec(spec.to_string()))?;
+
+        Ok(Signer {
+            service: service.to_string(),
+            region: region.to_string(),
+            credentials: Credentials::load(profile)?,
+            unsigned_payload,
+        })
+    }
+
+    /// Adds `x-amz-*` and `Authorization` headers to a fully built request.
+    /// Must run last, after every other header and query change.
+    pub fn sign(&self, request: &mut reqwest::Request) -> Result<(), SigV4Error> {
+        if request.headers().contains_key(AUTHORIZATION) {
+            return Err(SigV4Error::AuthorizationSet);
+        }
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        // Streaming bodies cannot be hashed up front.
+        let payload_hash = match request.body().map(|body| body.as_bytes()) {
+            _ if self.unsigned_payload => UNSIGNED_PAYLOAD.to_string(),
+            Some(Some(bytes)) => hex_sha256(bytes),
+            Some(None) => UNSIGNED_PAYLOAD.to_string(),
+            None => hex_sha256(b""),
+        };
+
+        let headers = request.headers_mut();
+        insert_header(headers, "x-amz-date", &amz_date)?;
+        insert_header(headers, "x-amz-content-sha256", &payload_hash)?;
+        if let Some(token) = &self.credentials.session_token {
+            insert_header(headers, "x-amz-security-token", token)?;
+        }
+
+        let authorization = self.authorization(request, &amz_date, &payload_hash);
+        let value = HeaderValue::from_str(&authorization)
+            .map_err(|_| SigV4Error::InvalidHeader(authorization.clone()))?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+        Ok(())
+    }
+
+    /// The `Authorization` value for a request whose `x-amz-*` headers are set.
+    fn authorization(&self, request: &reqwest::Request, amz_date: &str, payload_hash: &str) -> String {
+        let (canonical_request, signed_headers) = canonical_request(request, &self.service, payload_hash);
+        let date = &amz_date[..8];
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            amz_date,
+            scope,
+            hex_sha256(canonical_request.as_bytes())
+        );
+        let signature = hex(&hmac_sha256(&self.signing_key(date), string_to_sign.as_bytes()));
+
+        format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, self.credentials.access_key_id, scope, signed_headers, signature
+        )
+    }
+
+    fn signing_key(&self, date: &str) -> Vec<u8> {
+        let key = format!("AWS4{}", self.credentials.secret_access_key);
+        let key = hmac_sha256(key.as_bytes(), date.as_bytes());
+        let key = hmac_sha256(&key, self.region.as_bytes());
+        let key = hmac_sha256(&key, self.service.as_bytes());
+        hmac_sha256(&key, b"aws4_request")
+    }
+}
+
+/// The canonical request and its signed header list.
+fn canonical_request(request: &reqwest::Request, service: &str, payload_hash: &str) -> (String, String) {
+    let mut canonical_headers: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    if !request.headers().contains_key(HOST) {
+        canonical_headers.insert("host".to_string(), vec![host_header(request.url())]);
+    }
+    for (name, value) in request.headers() {
+        let name = name.as_str().to_ascii_lowercase();
+        if UNSIGNED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value.as_bytes());
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        canonical_headers.entry(name).or_default().push(value);
+    }
+
+    let signed_headers = canonical_headers.keys().cloned().collect::<Vec<_>>().join(";");
+    let header_block: String = canonical_headers
+        .iter()
+        .map(|(name, values)| format!("{}:{}\n", name, values.join(",")))
+        .collect();
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method().as_str(),
+        canonical_uri(request.url(), service),
+        canonical_query(request.url()),
+        header_block,
+        signed_headers,
+        payload_hash
+    );
+    (canonical_request, signed_headers)
+}
+
+fn insert_header(headers: &mut reqwest::header::HeaderMap, name: &'static str, value: &str) -> Result<(), SigV4Error> {
+    let value = HeaderValue::from_str(value).map_err(|_| SigV4Error::InvalidHeader(value.to_string()))?;
+    headers.insert(HeaderName::from_static(name), value);
+    Ok(())
+}
+
+/// The `Host` value hyper will send when none is set: the port is only present when it is not
+/// the scheme's default.
+fn host_header(url: &reqwest::Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+/// S3 signs the path encoded once; every other service encodes it twice.
+fn canonical_uri(url: &reqwest::Url, service: &str) -> String {
+    let path = url.path();
+    if path.is_empty() {
+        return "/".to_string();
+    }
+
+    path.split('/')
+        .map(|segment| {
+            let decoded = percent_decode(segment);
+            let encoded = aws_encode(&decoded);
+            if service == "s3" {
+                encoded
+            } else {
+                aws_encode(encoded.as_bytes())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn canonical_query(url: &reqwest::Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (aws_encode(key.as_bytes()), aws_encode(value.as_bytes())))
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encodes everything except the RFC 3986 unreserved characters.
+fn aws_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    decoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Method;
+
+    // Vectors from the AWS Signature Version 4 test suite.
+    const AMZ_DATE: &str = "20150830T123600Z";
+    const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn signer(service: &str) -> Signer {
+        Signer {
+            service: service.to_string(),
+            region: "us-east-1".to_string(),
+            credentials: Credentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+                session_token: None,
+            },
+            unsigned_payload: false,
+        }
+    }
+
+    fn request(method: Method, url: &str, headers: &[(&'static str, &str)]) -> reqwest::Request {
+        let mut request = reqwest::Request::new(method, url.parse().unwrap());
+        for (name, value) in headers {
+            request.headers_mut().append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        request
+    }
+
+    fn signature(authorization: &str) -> &str {
+        authorization.rsplit_once("Signature=").unwrap().1
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let request = request(Method::GET, "https://example.amazonaws.com/", &[("x-amz-date", AMZ_DATE)]);
+        let (canonical, signed_headers) = canonical_request(&request, "service", EMPTY_HASH);
+        assert_eq!(
+            canonical,
+            format!("GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:{}\n\nhost;x-amz-date\n{}", AMZ_DATE, EMPTY_HASH)
+        );
+        assert_eq!(signed_headers, "host;x-amz-date");
+        assert_eq!(
+            signer("service").authorization(&request, AMZ_DATE, EMPTY_HASH),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn post_vanilla() {
+        let request = request(Method::POST, "https://example.amazonaws.com/", &[("x-amz-date", AMZ_DATE)]);
+        assert_eq!(
+            signature(&signer("service").authorization(&request, AMZ_DATE, EMPTY_HASH)),
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key_case() {
+        let request = request(
+            Method::GET,
+            "https://example.amazonaws.com/?Param2=value2&Param1=value1",
+            &[("x-amz-date", AMZ_DATE)],
+        );
+        let (canonical, _) = canonical_request(&request, "service", EMPTY_HASH);
+        assert_eq!(canonical.lines().nth(2), Some("Param1=value1&Param2=value2"));
+        assert_eq!(
+            signature(&signer("service").authorization(&request, AMZ_DATE, EMPTY_HASH)),
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+
+    #[test]
+    fn iam_list_users_example() {
+        let request = request(
+            Method::GET,
+            "https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08",
+            &[
+                ("content-type", "application/x-www-form-urlencoded; charset=utf-8"),
+                ("x-amz-date", AMZ_DATE),
+            ],
+        );
+        let (canonical, signed_headers) = canonical_request(&request, "iam", EMPTY_HASH);
+        assert_eq!(signed_headers, "content-type;host;x-amz-date");
+        assert_eq!(
+            hex_sha256(canonical.as_bytes()),
+            "f536975d06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59"
+        );
+        assert_eq!(
+            signature(&signer("iam").authorization(&request, AMZ_DATE, EMPTY_HASH)),
+            "5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn derives_signing_key() {
+        assert_eq!(
+            hex(&signer("iam").signing_key("20120215")),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn trims_and_joins_header_values() {
+        let request = request(
+            Method::GET,
+            "https://example.amazonaws.com/",
+            &[("my-header1", "  value1  "), ("my-header2", "a   b   c"), ("my-header1", "value2")],
+        );
+        let (canonical, signed_headers) = canonical_request(&request, "service", EMPTY_HASH);
+        assert!(canonical.contains("\nmy-header1:value1,value2\nmy-header2:a b c\n"), "{}", canonical);
+        assert_eq!(signed_headers, "host;my-header1;my-header2");
+    }
+
+    #[test]
+    fn encodes_paths_per_service() {
+        let url: reqwest::Url = "https://example.amazonaws.com/documents%20and%20settings/a~b".parse().unwrap();
+        assert_eq!(canonical_uri(&url, "service"), "/documents%2520and%2520settings/a~b");
+        assert_eq!(canonical_uri(&url, "s3"), "/documents%20and%20settings/a~b");
+        let url: reqwest::Url = "https://example.amazonaws.com/?b=%20x&a=1&a=0".parse().unwrap();
+        assert_eq!(canonical_query(&url), "a=0&a=1&b=%20x");
+    }
+
+    #[test]
+    fn sign_adds_amz_headers() {
+        let mut signer = signer("service");
+        signer.credentials.session_token = Some("token".to_string());
+        let mut request = request(Method::PUT, "https://example.amazonaws.com/x", &[]);
+        *request.body_mut() = Some("hello".into());
+        signer.sign(&mut request).unwrap();
+
+        let header = |name: &str| request.headers()[name].to_str().unwrap().to_string();
+        assert_eq!(header("x-amz-content-sha256"), hex_sha256(b"hello"));
+        assert_eq!(header("x-amz-security-token"), "token");
+        assert!(header("authorization").contains(
+            "SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token,"
+        ));
+    }
+
+    #[test]
+    fn refuses_to_replace_authorization() {
+        let mut request = request(Method::GET, "https://example.amazonaws.com/", &[("authorization", "Bearer x")]);
+        assert!(matches!(signer("service").sign(&mut request), Err(SigV4Error::AuthorizationSet)));
+    }
+
+    #[test]
+    fn reads_profiles_from_ini() {
+        let text = "[default]\naws_access_key_id = A\n\n[profile dev]\naws_access_key_id=B\n; comment\naws_secret_access_key = S\n";
+        assert_eq!(ini_section(text, "dev").unwrap()["aws_secret_access_key"], "S");
+        assert_eq!(ini_section(text, "default").unwrap()["aws_access_key_id"], "A");
+        assert!(ini_section(text, "prod").is_none());
+    }
+}
@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::config_dir;
+use crate::diff::{self, Difference, Snapshot, SnapshotBody};
+use crate::tree::Segment;
+
+/// Headers kept in every snapshot; others are only kept when asked for.
+const DEFAULT_HEADERS: [&str; 1] = ["content-type"];
+
+/// Stands in for values matched by an ignore rule.
+const IGNORED: &str = "<ignored>";
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Read(PathBuf, io::Error),
+    Write(PathBuf, io::Error),
+    Parse(PathBuf, serde_json::Error),
+    Pattern(String, String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Read(path, e) => write!(f, "Could not read '{}': {}", path.display(), e),
+            SnapshotError::Write(path, e) => write!(f, "Could not write '{}': {}", path.display(), e),
+            SnapshotError::Parse(path, e) => write!(f, "Invalid snapshot file '{}': {}", path.display(), e),
+            SnapshotError::Pattern(pattern, reason) => write!(f, "Invalid ignore rule '{}': {}", pattern, reason),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+/// One step of an ignore rule.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+    /// `*` or `[*]`: any key or index.
+    Any,
+    /// `..`: any number of levels, including none.
+    Descend,
+}
+
+/// A JSON path naming values that change on every response, such as
+/// `$.meta.requestId`, `$.items[*].id` or `$..updatedAt`.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    text: String,
+    steps: Vec<Step>,
+}
+
+impl Pattern {
+    pub fn parse(text: &str) -> Result<Pattern, SnapshotError> {
+        let error = |reason: &str| SnapshotError::Pattern(text.to_string(), reason.to_string());
+        let mut rest = text.trim().strip_prefix('$').unwrap_or(text.trim());
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                steps.push(Step::Descend);
+                rest = after;
+                if rest.starts_with('[') {
+                    continue;
+                }
+            } else if let Some(after) = rest.strip_prefix('.') {
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| error("missing ']'"))?;
+                let inside = after[..end].trim();
+                steps.push(match inside {
+                    "*" => Step::Any,
+                    _ if inside.starts_with(['"', '\'']) && inside.len() >= 2 && inside.ends_with(&inside[..1]) => {
+                        Step::Key(inside[1..inside.len() - 1].to_string())
+                    }
+                    _ => Step::Index(inside.parse().map_err(|_| error("expected an index, * or a quoted key in []"))?),
+                });
+                rest = &after[end + 1..];
+                continue;
+            } else if !steps.is_empty() {
+                return Err(error("expected '.' or '['"));
+            }
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            match &rest[..end] {
+                "" => return Err(error("expected a key after '.'")),
+                "*" => steps.push(Step::Any),
+                key => steps.push(Step::Key(key.to_string())),
+            }
+            rest = &rest[end..];
+        }
+        if steps.is_empty() {
+            return Err(error("the rule names no value"));
+        }
+        Ok(Pattern { text: text.to_string(), steps })
+    }
+
+    fn matches(&self, path: &[Segment]) -> bool {
+        matches(&self.steps, path)
+    }
+}
+
+fn matches(steps: &[Step], path: &[Segment]) -> bool {
+    match steps.split_first() {
+        None => path.is_empty(),
+        Some((Step::Descend, rest)) => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+        Some((step, rest)) => path.split_first().is_some_and(|(segment, path)| {
+            let step_matches = match (step, segment) {
+                (Step::Any, _) => true,
+                (Step::Key(key), Segment::Key(name)) => key == name,
+                (Step::Index(index), Segment::Index(position)) => index == position,
+                _ => false,
+            };
+            step_matches && matches(rest, path)
+        }),
+    }
+}
+
+/// Replaces the values the patterns match, so they compare equal whatever
+/// they hold.
+fn mask(value: &mut Value, patterns: &[Pattern], path: &mut Vec<Segment>) {
+    if !path.is_empty() && patterns.iter().any(|pattern| pattern.matches(path)) {
+        *value = Value::String(IGNORED.to_string());
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                path.push(Segment::Key(key.clone()));
+                mask(child, patterns, path);
+                path.pop();
+            }
+        }
+        Value::Array(values) => {
+            for (index, child) in values.iter_mut().enumerate() {
+                path.push(Segment::Index(index));
+                mask(child, patterns, path);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A recorded response and the rules it was recorded with, so later runs
+/// need not repeat them.
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ignore: Vec<String>,
+    #[serde(flatten)]
+    response: Snapshot,
+}
+
+/// Which headers are kept and which body values are ignored.
+#[derive(Debug, Default)]
+pub struct Rules {
+    pub headers: Vec<String>,
+    pub ignore: Vec<Pattern>,
+}
+
+impl Rules {
+    pub fn new(headers: &[String], ignore: &[String]) -> Result<Rules, SnapshotError> {
+        let mut rules = Rules::default();
+        rules.add_headers(DEFAULT_HEADERS.iter().map(|name| name.to_string()));
+        rules.add_headers(headers.iter().cloned());
+        for text in ignore {
+            rules.add_ignore(text)?;
+        }
+        Ok(rules)
+    }
+
+    fn add_headers(&mut self, names: impl IntoIterator<Item = String>) {
+        for name in names {
+            let name = name.to_ascii_lowercase();
+            if !self.headers.contains(&name) {
+                self.headers.push(name);
+            }
+        }
+    }
+
+    fn add_ignore(&mut self, text: &str) -> Result<(), SnapshotError> {
+        if !self.ignore.iter().any(|pattern| pattern.text == text) {
+            self.ignore.push(Pattern::parse(text)?);
+        }
+        Ok(())
+    }
+
+    /// Keeps the chosen headers, masks ignored values and sorts object keys.
+    fn normalize(&self, mut snapshot: Snapshot) -> Snapshot {
+        if let Some(headers) = &mut snapshot.headers {
+            headers.retain(|name, _| self.headers.contains(name));
+        }
+        if let SnapshotBody::Json(value) = &mut snapshot.body {
+            mask(value, &self.ignore, &mut Vec::new());
+            *value = diff::sorted(value);
+        }
+        snapshot
+    }
+}
+
+/// What became of a response checked against its snapshot.
+#[derive(Debug)]
+pub enum Outcome {
+    Recorded(PathBuf),
+    Updated(PathBuf),
+    Matched(PathBuf),
+    Mismatched(PathBuf, Difference),
+}
+
+/// `snapshots/<name>.json` in the config dir, or the file itself when
+/// `name` looks like a path.
+pub fn path(name: &str) -> PathBuf {
+    if name.contains('/') || name.ends_with(".json") {
+        PathBuf::from(name)
+    } else {
+        config_dir().join("snapshots").join(format!("{}.json", name))
+    }
+}
+
+/// Compares a response with the snapshot called `name`. The first run, and
+/// every run with `update`, records the response instead. Rules saved with
+/// the snapshot apply along with the given ones.
+pub fn check(name: &str, update: bool, mut rules: Rules, response: Snapshot) -> Result<Outcome, SnapshotError> {
+    let path = path(name);
+    let stored = match fs::read_to_string(&path) {
+        Ok(text) => Some(serde_json::from_str::<SnapshotFile>(&text).map_err(|e| SnapshotError::Parse(path.clone(), e))?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(SnapshotError::Read(path, e)),
+    };
+    if let Some(stored) = &stored {
+        rules.add_headers(stored.response.headers.iter().flat_map(|headers| headers.keys().cloned()));
+        for text in &stored.ignore {
+            rules.add_ignore(text)?;
+        }
+    }
+    let response = rules.normalize(response);
+
+    match stored {
+        Some(stored) if !update => {
+            let difference = Difference::new(&rules.normalize(stored.response), &response);
+            if difference.is_empty() {
+                Ok(Outcome::Matched(path))
+            } else {
+                Ok(Outcome::Mismatched(path, difference))
+            }
+        }
+        stored => {
+            let file = SnapshotFile { ignore: rules.ignore.iter().map(|pattern| pattern.text.clone()).collect(), response };
+            save(&path, &file)?;
+            Ok(if stored.is_some() { Outcome::Updated(path) } else { Outcome::Recorded(path) })
+        }
+    }
+}
+
+fn save(path: &PathBuf, file: &SnapshotFile) -> Result<(), SnapshotError> {
+    let io_error = |e| SnapshotError::Write(path.clone(), e);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let text = serde_json::to_string_pretty(file).map_err(|e| SnapshotError::Parse(path.clone(), e))?;
+    fs::write(path, text + "\n").map_err(io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::ProtobufSettings;
+    use serde_json::json;
+
+    fn path(text: &str) -> Vec<Segment> {
+        text.split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| match segment.parse() {
+                Ok(index) => Segment::Index(index),
+                Err(_) => Segment::Key(segment.to_string()),
+            })
+            .collect()
+    }
+
+    fn matches(pattern: &str, at: &str) -> bool {
+        Pattern::parse(pattern).unwrap().matches(&path(at))
+    }
+
+    fn error(pattern: &str) -> String {
+        Pattern::parse(pattern).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_ignore_rules() {
+        let steps = |text: &str| Pattern::parse(text).unwrap().steps;
+        assert_eq!(steps("$.meta.id"), [Step::Key("meta".to_string()), Step::Key("id".to_string())]);
+        assert_eq!(steps("meta.id"), steps("$.meta.id"));
+        assert_eq!(steps("$.items[*].id"), [Step::Key("items".to_string()), Step::Any, Step::Key("id".to_string())]);
+        assert_eq!(steps("$.items.*"), [Step::Key("items".to_string()), Step::Any]);
+        assert_eq!(steps("$[2]"), [Step::Index(2)]);
+        assert_eq!(steps("$['a.b'][\"c\"]"), [Step::Key("a.b".to_string()), Step::Key("c".to_string())]);
+        assert_eq!(steps("$..updatedAt"), [Step::Descend, Step::Key("updatedAt".to_string())]);
+        assert_eq!(steps("$..[0]"), [Step::Descend, Step::Index(0)]);
+        assert_eq!(steps(" $.a "), [Step::Key("a".to_string())]);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        assert_eq!(error("$"), "Invalid ignore rule '$': the rule names no value");
+        assert_eq!(error("$.items[0"), "Invalid ignore rule '$.items[0': missing ']'");
+        assert_eq!(error("$.items[x]"), "Invalid ignore rule '$.items[x]': expected an index, * or a quoted key in []");
+        assert_eq!(error("$.a."), "Invalid ignore rule '$.a.': expected a key after '.'");
+        assert_eq!(error("$[0]x"), "Invalid ignore rule '$[0]x': expected '.' or '['");
+        assert!(Pattern::parse("$['a]").is_err());
+    }
+
+    #[test]
+    fn matches_paths() {
+        assert!(matches("$.meta.id", "meta/id"));
+        assert!(!matches("$.meta.id", "meta"));
+        assert!(!matches("$.meta.id", "meta/id/x"));
+        assert!(matches("$.items[*].id", "items/3/id"));
+        assert!(!matches("$.items[*].id", "items/id"));
+        assert!(matches("$.items[1]", "items/1"));
+        assert!(!matches("$.items[1]", "items/2"));
+        assert!(!matches("$.items[1]", "items/1x"));
+        assert!(matches("$.*", "anything"));
+    }
+
+    #[test]
+    fn matches_any_depth_after_dot_dot() {
+        assert!(matches("$..updatedAt", "updatedAt"));
+        assert!(matches("$..updatedAt", "items/0/meta/updatedAt"));
+        assert!(!matches("$..updatedAt", "items/0/updatedAt/x"));
+        assert!(matches("$.items..id", "items/2/owner/id"));
+        assert!(!matches("$.items..id", "owner/id"));
+        assert!(matches("$..[0]", "a/b/0"));
+    }
+
+    #[test]
+    fn masks_ignored_values_and_keeps_chosen_headers() {
+        let rules = Rules::new(&["X-Version".to_string()], &["$..id".to_string(), "$.list[1]".to_string()]).unwrap();
+        let headers = [
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("X-Version".to_string(), "3".to_string()),
+            ("Date".to_string(), "today".to_string()),
+        ];
+        let body = br#"{"id": 1, "list": [{"id": 2, "n": 1}, 5], "b": true}"#;
+        let response = rules.normalize(Snapshot::from_response(200, &headers, body, &ProtobufSettings::default()));
+        assert_eq!(response.headers.unwrap().keys().collect::<Vec<_>>(), ["content-type", "x-version"]);
+        assert_eq!(
+            response.body,
+            SnapshotBody::Json(json!({"b": true, "id": IGNORED, "list": [{"id": IGNORED, "n": 1}, IGNORED]}))
+        );
+    }
+
+    #[test]
+    fn records_then_compares() {
+        let file = std::env::temp_dir().join(format!("http-client-snapshot-{}.json", std::process::id()));
+        let name = file.to_str().unwrap();
+        let response = |body: &str| {
+            let headers = [("Content-Type".to_string(), "application/json".to_string())];
+            Snapshot::from_response(200, &headers, body.as_bytes(), &ProtobufSettings::default())
+        };
+        let rules = || Rules::new(&[], &["$.at".to_string()]).unwrap();
+
+        let outcome = check(name, false, rules(), response(r#"{"at": 1, "n": 1}"#)).unwrap();
+        assert!(matches!(outcome, Outcome::Recorded(_)));
+        // The saved rule applies without being given again.
+        let outcome = check(name, false, Rules::default(), response(r#"{"n": 1, "at": 2}"#)).unwrap();
+        assert!(matches!(outcome, Outcome::Matched(_)));
+        let outcome = check(name, false, rules(), response(r#"{"at": 3, "n": 2}"#)).unwrap();
+        assert!(matches!(&outcome, Outcome::Mismatched(_, difference) if difference.summary() == "1 body value differs"));
+        let outcome = check(name, true, rules(), response(r#"{"at": 3, "n": 2}"#)).unwrap();
+        assert!(matches!(outcome, Outcome::Updated(_)));
+        assert!(matches!(check(name, false, rules(), response(r#"{"n": 2}"#)).unwrap(), Outcome::Mismatched(..)));
+        fs::remove_file(&file).unwrap();
+    }
+}
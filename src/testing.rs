@@ -0,0 +1,106 @@
+//! A stand-in HTTP server for tests that exercise real connections.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// A request as the server received it.
+#[derive(Debug, Clone)]
+pub struct Received {
+    /// The request line and headers, without the blank line.
+    pub head: String,
+    pub body: Vec<u8>,
+}
+
+impl Received {
+    pub fn request_line(&self) -> &str {
+        self.head.lines().next().unwrap_or_default()
+    }
+
+    /// The value of header `name`, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+}
+
+/// Answers one connection per reply, in order, and records what it was sent.
+pub struct Server {
+    pub addr: SocketAddr,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl Server {
+    pub async fn start(replies: Vec<Vec<u8>>) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        tokio::spawn(async move {
+            for reply in replies {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                // Logged before replying, so the client never sees a reply
+                // to a request the log does not hold yet.
+                if let Some(request) = read_request(&mut stream).await {
+                    log.lock().unwrap().push(request);
+                    respond(&mut stream, &reply).await;
+                }
+            }
+        });
+        Server { addr, received }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn received(&self) -> Vec<Received> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+/// Reads one request with a Content-Length body, if any.
+pub async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Option<Received> {
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        let read = stream.read(&mut buffer).await.ok().filter(|read| *read > 0)?;
+        data.extend_from_slice(&buffer[..read]);
+    };
+    let head = String::from_utf8_lossy(&data[..head_end]).into_owned();
+    let mut request = Received { head, body: Vec::new() };
+    let length: usize = request.header("content-length").and_then(|value| value.parse().ok()).unwrap_or(0);
+    while data.len() < head_end + 4 + length {
+        let read = stream.read(&mut buffer).await.ok().filter(|read| *read > 0)?;
+        data.extend_from_slice(&buffer[..read]);
+    }
+    request.body = data[head_end + 4..head_end + 4 + length].to_vec();
+    Some(request)
+}
+
+/// Writes `reply` and closes the connection.
+pub async fn respond<S: AsyncWrite + Unpin>(stream: &mut S, reply: &[u8]) {
+    if stream.write_all(reply).await.is_ok() {
+        let _ = stream.shutdown().await;
+    }
+}
+
+/// An HTTP/1.1 response that closes the connection.
+pub fn reply(status: u16, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+    let mut head = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let mut reply = head.into_bytes();
+    reply.extend_from_slice(body);
+    reply
+}
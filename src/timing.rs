@@ -0,0 +1,250 @@
+use hyper::http::uri::Scheme;
+use hyper::service::Service;
+use hyper::Uri;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Connection events reported while a request is sent.
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    DnsStart,
+    DnsEnd,
+    ConnectStart,
+    ConnectEnd,
+    /// The handshake starts as soon as the TCP connection is up.
+    TlsEnd,
+}
+
+#[derive(Debug, Default)]
+struct Events {
+    dns_start: Option<Instant>,
+    dns_end: Option<Instant>,
+    connect_start: Option<Instant>,
+    connect_end: Option<Instant>,
+    tls_end: Option<Instant>,
+}
+
+/// Collects events from the resolver and the connector of one client.
+/// Requests are sent one at a time, so every event since the last `finish`
+/// belongs to the request being timed.
+#[derive(Debug, Default)]
+pub struct Timeline(Mutex<Events>);
+
+impl Timeline {
+    /// Records `event`. Later events replace earlier ones of the same kind,
+    /// so a token request made first does not hide the request's own
+    /// connection.
+    pub fn record(&self, event: Event) {
+        let mut events = self.0.lock().unwrap();
+        let now = Some(Instant::now());
+        match event {
+            Event::DnsStart => {
+                events.dns_start = now;
+                events.dns_end = None;
+            }
+            Event::DnsEnd => events.dns_end = now,
+            // The connector resolves the host itself, so every event of a
+            // new connection follows this one.
+            Event::ConnectStart => {
+                *events = Events { connect_start: now, ..Events::default() };
+            }
+            Event::ConnectEnd => events.connect_end = now,
+            Event::TlsEnd => events.tls_end = now,
+        }
+    }
+
+    /// Splits the time from `start` to the response headers at `first_byte`
+    /// into phases and clears the recorded events. A reused connection has
+    /// no DNS, connect or TLS phase.
+    pub fn finish(&self, start: Instant, first_byte: Instant) -> Timings {
+        let events = std::mem::take(&mut *self.0.lock().unwrap());
+        let between = |from: Option<Instant>, to: Option<Instant>| match (from, to) {
+            (Some(from), Some(to)) if from >= start => Some(to.saturating_duration_since(from)),
+            _ => None,
+        };
+
+        let dns = between(events.dns_start, events.dns_end);
+        let connect_start = if dns.is_some() { events.dns_end } else { events.connect_start };
+        let connect = between(connect_start, events.connect_end);
+        let tls = between(events.connect_end, events.tls_end);
+
+        let ready = [dns, connect, tls].into_iter().flatten().sum::<Duration>();
+        Timings {
+            dns,
+            connect,
+            tls,
+            wait: first_byte.saturating_duration_since(start).saturating_sub(ready),
+            transfer: Duration::ZERO,
+        }
+    }
+}
+
+/// What a wrapped connector does: open the TCP connection, or add TLS on
+/// top of a wrapped TCP connector.
+#[derive(Debug, Clone, Copy)]
+pub enum Layer {
+    Tcp,
+    Tls,
+}
+
+/// A hyper connector that reports when its connections start and become
+/// ready, so connecting is timed for plain HTTP as well as HTTPS.
+#[derive(Debug, Clone)]
+pub struct TimedConnector<C> {
+    inner: C,
+    layer: Layer,
+    timeline: Arc<Timeline>,
+}
+
+impl<C> TimedConnector<C> {
+    pub fn new(inner: C, layer: Layer, timeline: Arc<Timeline>) -> TimedConnector<C> {
+        TimedConnector { inner, layer, timeline }
+    }
+}
+
+impl<C> Service<Uri> for TimedConnector<C>
+where
+    C: Service<Uri>,
+    C::Response: Send + 'static,
+    C::Error: Send + 'static,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<C::Response, C::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), C::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let end = match self.layer {
+            Layer::Tcp => {
+                self.timeline.record(Event::ConnectStart);
+                Some(Event::ConnectEnd)
+            }
+            Layer::Tls => (uri.scheme() == Some(&Scheme::HTTPS)).then_some(Event::TlsEnd),
+        };
+        let timeline = self.timeline.clone();
+        let connecting = self.inner.call(uri);
+        Box::pin(async move {
+            let connection = connecting.await?;
+            if let Some(end) = end {
+                timeline.record(end);
+            }
+            Ok(connection)
+        })
+    }
+}
+
+/// How long each phase of a request took.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timings {
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    /// From the connection being ready to the response headers.
+    pub wait: Duration,
+    /// Reading the response body.
+    pub transfer: Duration,
+}
+
+impl Timings {
+    /// Time from the start of the request to the response headers.
+    pub fn first_byte(&self) -> Duration {
+        [self.dns, self.connect, self.tls].into_iter().flatten().sum::<Duration>() + self.wait
+    }
+
+    pub fn total(&self) -> Duration {
+        self.first_byte() + self.transfer
+    }
+
+    /// Every phase as `(label, offset, length)`, for waterfalls.
+    pub fn phases(&self) -> Vec<(&'static str, Duration, Duration)> {
+        let mut offset = Duration::ZERO;
+        let mut phases = Vec::new();
+        let steps = [
+            ("DNS lookup", self.dns),
+            ("TCP connect", self.connect),
+            ("TLS handshake", self.tls),
+            ("Waiting (TTFB)", Some(self.wait)),
+            ("Download", Some(self.transfer)),
+        ];
+        for (label, length) in steps {
+            if let Some(length) = length {
+                phases.push((label, offset, length));
+                offset += length;
+            }
+        }
+        phases
+    }
+}
+
+/// Formats `duration` in milliseconds with two decimals.
+pub fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientSettings;
+    use crate::cookies::CookieJar;
+    use crate::dns::{DnsConfig, DnsSettings};
+    use crate::testing::{reply, Server};
+
+    #[tokio::test]
+    async fn times_plain_http_connects() {
+        let server = Server::start(vec![reply(200, &[], b"ok")]).await;
+        let port = server.addr.port();
+        let dns = DnsConfig { resolve: vec![format!("timed.test:{}:127.0.0.1", port)], ..DnsConfig::default() };
+        let settings = ClientSettings { dns: DnsSettings::new(&dns).unwrap(), ..ClientSettings::default() };
+        let client = settings.build(Arc::new(CookieJar::new())).unwrap();
+
+        let start = Instant::now();
+        let request = client.request(reqwest::Method::GET, &format!("http://timed.test:{}/", port)).build().unwrap();
+        let response = client.execute(request).await.unwrap();
+        let first_byte = Instant::now();
+        assert_eq!(response.remote_addr(), Some(server.addr));
+
+        let timings = settings.timeline.finish(start, first_byte);
+        assert!(timings.dns.is_some());
+        assert!(timings.connect.is_some());
+        assert!(timings.tls.is_none());
+        assert!(timings.first_byte() <= first_byte - start);
+
+        // Nothing was recorded since, as for a reused connection.
+        let timings = settings.timeline.finish(first_byte, Instant::now());
+        assert!(timings.dns.is_none() && timings.connect.is_none() && timings.tls.is_none());
+    }
+
+    #[test]
+    fn ignores_events_before_the_start() {
+        let timeline = Timeline::default();
+        timeline.record(Event::ConnectStart);
+        timeline.record(Event::ConnectEnd);
+        let start = Instant::now();
+        let timings = timeline.finish(start, start + Duration::from_millis(5));
+        assert!(timings.connect.is_none());
+        assert_eq!(timings.wait, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn lays_phases_end_to_end() {
+        let timings = Timings {
+            dns: Some(Duration::from_millis(2)),
+            connect: None,
+            tls: Some(Duration::from_millis(3)),
+            wait: Duration::from_millis(10),
+            transfer: Duration::from_millis(4),
+        };
+        let phases: Vec<_> = timings.phases().into_iter().map(|(label, offset, _)| (label, offset.as_millis())).collect();
+        assert_eq!(phases, [("DNS lookup", 0), ("TLS handshake", 2), ("Waiting (TTFB)", 5), ("Download", 15)]);
+        assert_eq!(timings.first_byte(), Duration::from_millis(15));
+        assert_eq!(timings.total(), Duration::from_millis(19));
+        assert_eq!(millis(Duration::from_micros(1500)), "1.50ms");
+    }
+}
@@ -0,0 +1,510 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerName};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+const PIN_PREFIX: &str = "sha256//";
+
+#[derive(Debug)]
+pub enum TlsError {
+    Io(PathBuf, std::io::Error),
+    NoCertificates(PathBuf),
+    NoPrivateKey(PathBuf),
+    Pkcs12(PathBuf, String),
+    InvalidPin(String),
+    NativeRoots(std::io::Error),
+    Config(rustls::Error),
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::Io(path, e) => {
+                write!(f, "Could not read '{}': {}", path.display(), e)
+            }
+            TlsError::NoCertificates(path) => {
+                write!(f, "No certificates found in '{}'", path.display())
+            }
+            TlsError::NoPrivateKey(path) => {
+                write!(f, "No private key found in '{}'", path.display())
+            }
+            TlsError::Pkcs12(path, e) => {
+                write!(f, "Could not read PKCS#12 file '{}': {}", path.display(), e)
+            }
+            TlsError::InvalidPin(pin) => {
+                write!(f, "Invalid public key pin: '{}'. Use 'sha256//<base64>' format", pin)
+            }
+            TlsError::NativeRoots(e) => {
+                write!(f, "Could not load system root certificates: {}", e)
+            }
+            TlsError::Config(e) => {
+                write!(f, "Invalid TLS configuration: {}", e)
+            }
+        }
+    }
+}
+
+impl Error for TlsError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+pub enum TlsVersion {
+    #[value(name = "1.2")]
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[value(name = "1.3")]
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+/// TLS options from the command line or an environment's `tls` settings.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TlsConfig {
+    /// PEM bundle that replaces the system trust store.
+    pub cacert: Option<PathBuf>,
+    /// PEM client certificate, optionally with the key, or a PKCS#12 file.
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub cert_password: Option<String>,
+    #[serde(default)]
+    pub insecure: bool,
+    pub min_version: Option<TlsVersion>,
+    /// `sha256//<base64>` hashes of accepted server public keys.
+    #[serde(default)]
+    pub pins: Vec<String>,
+}
+
+impl TlsConfig {
+    pub fn rustls_config(&self, log: Arc<CertificateLog>) -> Result<ClientConfig, TlsError> {
+        let roots = match &self.cacert {
+            Some(path) => {
+                let mut roots = RootCertStore::empty();
+                roots.add_parsable_certificates(&read_certificates(path)?);
+                roots
+            }
+            None => {
+                let mut roots = RootCertStore::empty();
+                let native = rustls_native_certs::load_native_certs().map_err(TlsError::NativeRoots)?;
+                let native: Vec<Vec<u8>> = native.into_iter().map(|cert| cert.0).collect();
+                roots.add_parsable_certificates(&native);
+                roots
+            }
+        };
+
+        let pins = self
+            .pins
+            .iter()
+            .flat_map(|pins| pins.split(';'))
+            .filter(|pin| !pin.trim().is_empty())
+            .map(parse_pin)
+            .collect::<Result<_, _>>()?;
+
+        let verifier = Verifier {
+            webpki: (!self.insecure).then(|| WebPkiVerifier::new(roots, None)),
+            pins,
+            log,
+        };
+
+        let versions: &[&rustls::SupportedProtocolVersion] = match self.min_version {
+            Some(TlsVersion::Tls13) => &[&rustls::version::TLS13],
+            _ => &[&rustls::version::TLS13, &rustls::version::TLS12],
+        };
+        let builder = ClientConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(versions)
+            .map_err(TlsError::Config)?
+            .with_custom_certificate_verifier(Arc::new(verifier));
+
+        let config = match &self.cert {
+            Some(path) => {
+                let (chain, key) = self.client_identity(path)?;
+                builder.with_client_auth_cert(chain, key).map_err(TlsError::Config)?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        Ok(config)
+    }
+
+    /// Loads `--cert`/`--key`. Files that are not PEM are read as PKCS#12.
+    fn client_identity(&self, path: &Path) -> Result<(Vec<Certificate>, PrivateKey), TlsError> {
+        let data = std::fs::read(path).map_err(|e| TlsError::Io(path.to_path_buf(), e))?;
+        if !String::from_utf8_lossy(&data).contains("-----BEGIN") {
+            return read_pkcs12(path, &data, self.cert_password.as_deref().unwrap_or_default());
+        }
+
+        let chain: Vec<Certificate> = read_certificates(path)?.into_iter().map(Certificate).collect();
+        let key_path = self.key.as_deref().unwrap_or(path);
+        let key = read_private_key(key_path)?;
+        Ok((chain, key))
+    }
+}
+
+fn read_pem(path: &Path) -> Result<Vec<rustls_pemfile::Item>, TlsError> {
+    let data = std::fs::read(path).map_err(|e| TlsError::Io(path.to_path_buf(), e))?;
+    rustls_pemfile::read_all(&mut data.as_slice()).map_err(|e| TlsError::Io(path.to_path_buf(), e))
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<Vec<u8>>, TlsError> {
+    let certificates: Vec<Vec<u8>> = read_pem(path)?
+        .into_iter()
+        .filter_map(|item| match item {
+            rustls_pemfile::Item::X509Certificate(der) => Some(der),
+            _ => None,
+        })
+        .collect();
+    if certificates.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+    Ok(certificates)
+}
+
+fn read_private_key(path: &Path) -> Result<PrivateKey, TlsError> {
+    read_pem(path)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(der)
+            | rustls_pemfile::Item::PKCS8Key(der)
+            | rustls_pemfile::Item::ECKey(der) => Some(PrivateKey(der)),
+            _ => None,
+        })
+        .ok_or_else(|| TlsError::NoPrivateKey(path.to_path_buf()))
+}
+
+fn read_pkcs12(path: &Path, data: &[u8], password: &str) -> Result<(Vec<Certificate>, PrivateKey), TlsError> {
+    let error = |e: openssl::error::ErrorStack| TlsError::Pkcs12(path.to_path_buf(), e.to_string());
+    let parsed = openssl::pkcs12::Pkcs12::from_der(data)
+        .and_then(|pkcs12| pkcs12.parse2(password))
+        .map_err(error)?;
+
+    let certificate = parsed.cert.ok_or_else(|| TlsError::NoCertificates(path.to_path_buf()))?;
+    let key = parsed.pkey.ok_or_else(|| TlsError::NoPrivateKey(path.to_path_buf()))?;
+
+    let mut chain = vec![Certificate(certificate.to_der().map_err(error)?)];
+    for ca in parsed.ca.into_iter().flatten() {
+        chain.push(Certificate(ca.to_der().map_err(error)?));
+    }
+    Ok((chain, PrivateKey(key.private_key_to_pkcs8().map_err(error)?)))
+}
+
+fn parse_pin(pin: &str) -> Result<[u8; 32], TlsError> {
+    let invalid = || TlsError::InvalidPin(pin.to_string());
+    let encoded = pin.trim().strip_prefix(PIN_PREFIX).ok_or_else(invalid)?;
+    let digest = STANDARD.decode(encoded).map_err(|_| invalid())?;
+    digest.try_into().map_err(|_| invalid())
+}
+
+/// `sha256//<base64>` of the certificate's SubjectPublicKeyInfo, as used by `--pin`.
+pub fn public_key_pin(der: &[u8]) -> Option<String> {
+    let (_, certificate) = X509Certificate::from_der(der).ok()?;
+    Some(format!("{}{}", PIN_PREFIX, STANDARD.encode(Sha256::digest(certificate.public_key().raw))))
+}
+
+/// Certificate chains presented by servers, recorded for `--show-cert`.
+#[derive(Debug, Default)]
+pub struct CertificateLog(Mutex<Vec<(String, Vec<Vec<u8>>)>>);
+
+impl CertificateLog {
+    fn record(&self, server: String, chain: Vec<Vec<u8>>) {
+        let mut chains = self.0.lock().unwrap();
+        chains.retain(|(name, _)| *name != server);
+        chains.push((server, chain));
+    }
+
+    pub fn chains(&self) -> Vec<(String, Vec<Vec<u8>>)> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+struct Verifier {
+    /// `None` in insecure mode.
+    webpki: Option<WebPkiVerifier>,
+    pins: Vec<[u8; 32]>,
+    log: Arc<CertificateLog>,
+}
+
+impl ServerCertVerifier for Verifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let server = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_string(),
+            ServerName::IpAddress(address) => address.to_string(),
+            _ => String::new(),
+        };
+        let chain = std::iter::once(end_entity)
+            .chain(intermediates)
+            .map(|certificate| certificate.0.clone())
+            .collect();
+        self.log.record(server, chain);
+
+        if let Some(webpki) = &self.webpki {
+            webpki.verify_server_cert(end_entity, intermediates, server_name, scts, ocsp_response, now)?;
+        }
+
+        // Pins are enforced even in insecure mode, as curl does.
+        if !self.pins.is_empty() {
+            let (_, certificate) = X509Certificate::from_der(&end_entity.0)
+                .map_err(|_| rustls::Error::InvalidCertificate(rustls::CertificateError::BadEncoding))?;
+            let digest: [u8; 32] = Sha256::digest(certificate.public_key().raw).into();
+            if !self.pins.contains(&digest) {
+                return Err(rustls::Error::General("server public key does not match any pin".to_string()));
+            }
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Human-readable summary of a DER certificate for `--show-cert`.
+pub fn describe_certificate(der: &[u8]) -> Vec<(&'static str, String)> {
+    let Ok((_, certificate)) = X509Certificate::from_der(der) else {
+        return vec![("error", "could not parse certificate".to_string())];
+    };
+
+    let sans = certificate
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|extension| {
+            extension
+                .value
+                .general_names
+                .iter()
+                .map(|name| match name {
+                    GeneralName::DNSName(dns) => format!("DNS:{}", dns),
+                    GeneralName::IPAddress(bytes) => match bytes.len() {
+                        4 => format!("IP:{}", std::net::Ipv4Addr::from(<[u8; 4]>::try_from(*bytes).unwrap())),
+                        16 => format!("IP:{}", std::net::Ipv6Addr::from(<[u8; 16]>::try_from(*bytes).unwrap())),
+                        _ => "IP:?".to_string(),
+                    },
+                    GeneralName::RFC822Name(email) => format!("email:{}", email),
+                    GeneralName::URI(uri) => format!("URI:{}", uri),
+                    other => format!("{:?}", other),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+
+    let validity = certificate.validity();
+    vec![
+        ("subject", certificate.subject().to_string()),
+        ("issuer", certificate.issuer().to_string()),
+        ("SANs", sans),
+        ("valid", format!("{} to {}", validity.not_before, validity.not_after)),
+        ("pin", public_key_pin(der).unwrap_or_default()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientSettings;
+    use crate::cookies::CookieJar;
+    use crate::testing::{read_request, reply, respond};
+    use rustls::server::{AllowAnyAuthenticatedClient, ServerConfig};
+    use std::net::SocketAddr;
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    /// A test CA, a server certificate for `localhost` and `127.0.0.1` and a
+    /// client certificate, all issued by it, plus the client certificate as
+    /// PKCS#12.
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/tls").join(name)
+    }
+
+    struct ServerOptions {
+        client_auth: bool,
+        versions: &'static [&'static rustls::SupportedProtocolVersion],
+    }
+
+    const DEFAULT_SERVER: ServerOptions = ServerOptions { client_auth: false, versions: rustls::ALL_VERSIONS };
+
+    /// An HTTPS server answering every connection with `secure`.
+    async fn server(options: ServerOptions) -> SocketAddr {
+        let chain = read_certificates(&fixture("server.pem")).unwrap().into_iter().map(Certificate).collect();
+        let key = read_private_key(&fixture("server.key")).unwrap();
+        let builder = ServerConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(options.versions)
+            .unwrap();
+        let builder = if options.client_auth {
+            let mut roots = RootCertStore::empty();
+            roots.add_parsable_certificates(&read_certificates(&fixture("ca.pem")).unwrap());
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        } else {
+            builder.with_no_client_auth()
+        };
+        let acceptor = TlsAcceptor::from(Arc::new(builder.with_single_cert(chain, key).unwrap()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    continue;
+                };
+                if read_request(&mut stream).await.is_some() {
+                    respond(&mut stream, &reply(200, &[], b"secure")).await;
+                }
+            }
+        });
+        addr
+    }
+
+    async fn get(addr: SocketAddr, tls: TlsConfig) -> (Result<String, String>, Arc<CertificateLog>) {
+        let settings = ClientSettings { tls, ..ClientSettings::default() };
+        let client = match settings.build(Arc::new(CookieJar::new())) {
+            Ok(client) => client,
+            Err(e) => return (Err(e.to_string()), settings.certificates),
+        };
+        let request = client.request(reqwest::Method::GET, &format!("https://{}/", addr)).build().unwrap();
+        let result = match client.execute(request).await {
+            Ok(response) => Ok(response.text().await.unwrap()),
+            Err(e) => Err(error_chain(&e)),
+        };
+        (result, settings.certificates)
+    }
+
+    fn error_chain(error: &dyn Error) -> String {
+        let mut text = error.to_string();
+        let mut source = error.source();
+        while let Some(error) = source {
+            text.push_str(&format!(": {}", error));
+            source = error.source();
+        }
+        text
+    }
+
+    fn trusting_ca() -> TlsConfig {
+        TlsConfig { cacert: Some(fixture("ca.pem")), ..TlsConfig::default() }
+    }
+
+    fn server_pin() -> String {
+        public_key_pin(&read_certificates(&fixture("server.pem")).unwrap()[0]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn trusts_ca_bundle() {
+        let addr = server(DEFAULT_SERVER).await;
+        let (result, log) = get(addr, trusting_ca()).await;
+        assert_eq!(result.unwrap(), "secure");
+
+        let chains = log.chains();
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].0, "127.0.0.1");
+        let description = describe_certificate(&chains[0].1[0]);
+        assert_eq!(description[0], ("subject", "CN=localhost".to_string()));
+        assert_eq!(description[1], ("issuer", "CN=http-client test CA".to_string()));
+        assert_eq!(description[2], ("SANs", "DNS:localhost, IP:127.0.0.1".to_string()));
+        assert_eq!(description[4], ("pin", server_pin()));
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_issuer_unless_insecure() {
+        let addr = server(DEFAULT_SERVER).await;
+        let other_ca = TlsConfig { cacert: Some(fixture("client.pem")), ..TlsConfig::default() };
+        let (result, log) = get(addr, other_ca).await;
+        assert!(result.unwrap_err().contains("UnknownIssuer"));
+        // The chain is recorded even though verification failed.
+        assert_eq!(log.chains().len(), 1);
+
+        let insecure = TlsConfig { insecure: true, ..TlsConfig::default() };
+        assert_eq!(get(addr, insecure).await.0.unwrap(), "secure");
+    }
+
+    #[tokio::test]
+    async fn enforces_public_key_pins() {
+        let addr = server(DEFAULT_SERVER).await;
+        let pinned = TlsConfig { pins: vec![server_pin()], ..trusting_ca() };
+        assert_eq!(get(addr, pinned).await.0.unwrap(), "secure");
+
+        let client_pin = public_key_pin(&read_certificates(&fixture("client.pem")).unwrap()[0]).unwrap();
+        let multiple = TlsConfig { pins: vec![format!("{};{}", client_pin, server_pin())], ..trusting_ca() };
+        assert_eq!(get(addr, multiple).await.0.unwrap(), "secure");
+
+        // Pins apply even when verification is off.
+        let wrong = TlsConfig { pins: vec![client_pin], insecure: true, ..TlsConfig::default() };
+        assert!(get(addr, wrong).await.0.unwrap_err().contains("does not match any pin"));
+    }
+
+    #[tokio::test]
+    async fn presents_client_certificates() {
+        let addr = server(ServerOptions { client_auth: true, ..DEFAULT_SERVER }).await;
+        assert!(get(addr, trusting_ca()).await.0.is_err());
+
+        let separate_key =
+            TlsConfig { cert: Some(fixture("client.pem")), key: Some(fixture("client.key")), ..trusting_ca() };
+        assert_eq!(get(addr, separate_key).await.0.unwrap(), "secure");
+
+        let bundle = TlsConfig { cert: Some(fixture("client-bundle.pem")), ..trusting_ca() };
+        assert_eq!(get(addr, bundle).await.0.unwrap(), "secure");
+
+        let pkcs12 =
+            TlsConfig { cert: Some(fixture("client.p12")), cert_password: Some("test".into()), ..trusting_ca() };
+        assert_eq!(get(addr, pkcs12).await.0.unwrap(), "secure");
+    }
+
+    #[tokio::test]
+    async fn honours_minimum_version() {
+        const TLS12: &[&rustls::SupportedProtocolVersion] = &[&rustls::version::TLS12];
+        let addr = server(ServerOptions { versions: TLS12, ..DEFAULT_SERVER }).await;
+        assert_eq!(get(addr, trusting_ca()).await.0.unwrap(), "secure");
+        let tls13 = TlsConfig { min_version: Some(TlsVersion::Tls13), ..trusting_ca() };
+        assert!(get(addr, tls13).await.0.is_err());
+    }
+
+    #[test]
+    fn rejects_unusable_files() {
+        let wrong_password =
+            TlsConfig { cert: Some(fixture("client.p12")), cert_password: Some("wrong".into()), ..trusting_ca() };
+        assert!(matches!(wrong_password.rustls_config(Arc::default()), Err(TlsError::Pkcs12(..))));
+
+        let no_key = TlsConfig { cert: Some(fixture("client.pem")), ..trusting_ca() };
+        assert!(matches!(no_key.rustls_config(Arc::default()), Err(TlsError::NoPrivateKey(_))));
+
+        let no_certificates = TlsConfig { cacert: Some(fixture("server.key")), ..TlsConfig::default() };
+        assert!(matches!(
+            no_certificates.rustls_config(Arc::default()),
+            Err(TlsError::NoCertificates(_))
+        ));
+    }
+
+    #[test]
+    fn parses_pins() {
+        assert_eq!(parse_pin(&server_pin()).unwrap().len(), 32);
+        for pin in ["sha1//AAAA", "sha256//not base64!", "sha256//AAAA"] {
+            assert!(matches!(parse_pin(pin), Err(TlsError::InvalidPin(_))), "{}", pin);
+        }
+    }
+
+    #[tokio::test]
+    async fn times_the_handshake() {
+        let addr = server(DEFAULT_SERVER).await;
+        let settings = ClientSettings { tls: trusting_ca(), ..ClientSettings::default() };
+        let client = settings.build(Arc::new(CookieJar::new())).unwrap();
+        let start = std::time::Instant::now();
+        let request = client.request(reqwest::Method::GET, &format!("https://{}/", addr)).build().unwrap();
+        let response = client.execute(request).await.unwrap();
+        let timings = settings.timeline.finish(start, std::time::Instant::now());
+        assert_eq!(response.version(), reqwest::Version::HTTP_11);
+        assert!(timings.dns.is_none());
+        assert!(timings.connect.is_some());
+        assert!(timings.tls.is_some());
+    }
+}
@@ -0,0 +1,372 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Levels of a new tree that start expanded: the root and its children.
+const EXPANDED_DEPTH: usize = 2;
+
+/// One step from a node to its child.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Writes a path as JSONPath, which `--filter` and the filter bar accept.
+pub fn json_path(path: &[Segment]) -> String {
+    let mut text = "$".to_string();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if is_identifier(key) => {
+                text.push('.');
+                text.push_str(key);
+            }
+            Segment::Key(key) => text.push_str(&format!("[{}]", Value::String(key.clone()))),
+            Segment::Index(index) => text.push_str(&format!("[{}]", index)),
+        }
+    }
+    text
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A visible node of the tree.
+#[derive(Debug)]
+pub struct Row<'a> {
+    pub path: &'a [Segment],
+    pub value: &'a Value,
+    pub expanded: bool,
+}
+
+impl Row<'_> {
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    pub fn is_container(&self) -> bool {
+        has_children(self.value)
+    }
+}
+
+fn has_children(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(values) => !values.is_empty(),
+        _ => false,
+    }
+}
+
+/// The children of an object or array, in order.
+fn children(value: &Value) -> Box<dyn Iterator<Item = (Segment, &Value)> + '_> {
+    match value {
+        Value::Object(map) => Box::new(map.iter().map(|(key, value)| (Segment::Key(key.clone()), value))),
+        Value::Array(values) => Box::new(values.iter().enumerate().map(|(index, value)| (Segment::Index(index), value))),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[Segment]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, segment| match (segment, value) {
+        (Segment::Key(key), Value::Object(map)) => map.get(key),
+        (Segment::Index(index), Value::Array(values)) => values.get(*index),
+        _ => None,
+    })
+}
+
+#[derive(Debug, Clone)]
+struct Visible {
+    path: Vec<Segment>,
+    expanded: bool,
+}
+
+/// Which nodes of a JSON body are expanded and which row is selected. Only
+/// expanded nodes are walked, so large bodies stay quick while collapsed.
+#[derive(Debug, Clone, Default)]
+pub struct JsonTree {
+    expanded: HashSet<String>,
+    /// The visible nodes, rebuilt only when a node is expanded or collapsed.
+    visible: Vec<Visible>,
+    pub selected: usize,
+    /// Key fragment of the last jump-to-key search.
+    pub search: String,
+}
+
+impl JsonTree {
+    pub fn new(value: &Value) -> Self {
+        let mut tree = JsonTree::default();
+        tree.expand_to_depth(value, &mut Vec::new(), EXPANDED_DEPTH);
+        tree.refresh(value);
+        tree
+    }
+
+    fn expand_to_depth(&mut self, value: &Value, path: &mut Vec<Segment>, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+        if has_children(value) {
+            self.expanded.insert(json_path(path));
+            for (segment, child) in children(value) {
+                path.push(segment);
+                self.expand_to_depth(child, path, depth - 1);
+                path.pop();
+            }
+        }
+    }
+
+    fn refresh(&mut self, value: &Value) {
+        let mut visible = Vec::new();
+        self.push_visible(value, &mut Vec::new(), &mut visible);
+        self.visible = visible;
+    }
+
+    fn push_visible(&self, value: &Value, path: &mut Vec<Segment>, visible: &mut Vec<Visible>) {
+        let expanded = has_children(value) && self.expanded.contains(&json_path(path));
+        visible.push(Visible { path: path.clone(), expanded });
+        if expanded {
+            for (segment, child) in children(value) {
+                path.push(segment);
+                self.push_visible(child, path, visible);
+                path.pop();
+            }
+        }
+    }
+
+    /// The visible nodes in document order, starting with the root. `value`
+    /// must be the body the tree was made for.
+    pub fn rows<'a>(&'a self, value: &'a Value) -> Vec<Row<'a>> {
+        self.visible
+            .iter()
+            .filter_map(|visible| {
+                let value = lookup(value, &visible.path)?;
+                Some(Row { path: &visible.path, value, expanded: visible.expanded })
+            })
+            .collect()
+    }
+
+    pub fn selected_path(&self) -> Option<Vec<Segment>> {
+        self.visible.get(self.selected).map(|visible| visible.path.clone())
+    }
+
+    /// Moves the selection, stopping at the first and last rows.
+    pub fn select(&mut self, offset: isize) {
+        let last = self.visible.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(offset).min(last);
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected = self.visible.len().saturating_sub(1);
+    }
+
+    /// The selected row when it is an object or array with children.
+    fn selected_container(&self, value: &Value) -> Option<&Visible> {
+        let visible = self.visible.get(self.selected)?;
+        lookup(value, &visible.path).filter(|value| has_children(value)).map(|_| visible)
+    }
+
+    /// Expands the selected node, or moves to its first child when it is
+    /// already expanded.
+    pub fn expand(&mut self, value: &Value) {
+        let Some(visible) = self.selected_container(value) else {
+            return;
+        };
+        if visible.expanded {
+            self.selected += 1;
+        } else {
+            self.expanded.insert(json_path(&visible.path));
+            self.refresh(value);
+        }
+    }
+
+    /// Collapses the selected node, or moves to its parent when it is
+    /// collapsed or has no children.
+    pub fn collapse(&mut self, value: &Value) {
+        let Some(visible) = self.visible.get(self.selected) else {
+            return;
+        };
+        if visible.expanded {
+            self.expanded.remove(&json_path(&visible.path));
+            self.refresh(value);
+        } else if let Some((_, parent)) = visible.path.split_last() {
+            self.selected = self.visible.iter().position(|visible| visible.path == parent).unwrap_or(0);
+        }
+    }
+
+    pub fn toggle(&mut self, value: &Value) {
+        let Some(visible) = self.selected_container(value) else {
+            return;
+        };
+        let path = json_path(&visible.path);
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.refresh(value);
+    }
+
+    /// Selects the next node after the selected one whose key contains the
+    /// search text, ignoring case and wrapping around at the end. Collapsed
+    /// nodes are searched too and expanded to show the match. Returns the
+    /// path of the match.
+    pub fn find_next(&mut self, value: &Value) -> Option<Vec<Segment>> {
+        if self.search.is_empty() {
+            return None;
+        }
+        let after = self.selected_path().unwrap_or_default();
+        let mut search = KeySearch { query: self.search.to_lowercase(), after: &after, passed: false, first: None };
+        let found = search.visit(value, &mut Vec::new()).or(search.first)?;
+
+        for depth in 0..found.len() {
+            self.expanded.insert(json_path(&found[..depth]));
+        }
+        self.refresh(value);
+        self.selected = self.visible.iter().position(|visible| visible.path == found).unwrap_or(0);
+        Some(found)
+    }
+}
+
+/// Walks the whole document in order for keys containing `query`.
+struct KeySearch<'a> {
+    query: String,
+    /// The selected node: the first match after it wins.
+    after: &'a [Segment],
+    passed: bool,
+    /// The first match before it, used when nothing follows.
+    first: Option<Vec<Segment>>,
+}
+
+impl KeySearch<'_> {
+    fn visit(&mut self, value: &Value, path: &mut Vec<Segment>) -> Option<Vec<Segment>> {
+        if let Some(Segment::Key(key)) = path.last()
+            && key.to_lowercase().contains(&self.query)
+        {
+            if self.passed {
+                return Some(path.clone());
+            }
+            self.first.get_or_insert_with(|| path.clone());
+        }
+        if path.as_slice() == self.after {
+            self.passed = true;
+        }
+        for (segment, child) in children(value) {
+            path.push(segment);
+            let found = self.visit(child, path);
+            path.pop();
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn body() -> Value {
+        json!({"user": {"name": "Ann", "roles": ["admin", {"scope": "all"}]}, "count": 2, "empty": {}})
+    }
+
+    fn paths(tree: &JsonTree, value: &Value) -> Vec<String> {
+        tree.rows(value).iter().map(|row| json_path(row.path)).collect()
+    }
+
+    fn select(tree: &mut JsonTree, value: &Value, path: &str) {
+        tree.selected = paths(tree, value).iter().position(|row| row == path).unwrap();
+    }
+
+    #[test]
+    fn writes_json_paths() {
+        let path = [Segment::Key("a".to_string()), Segment::Index(2), Segment::Key("b c".to_string())];
+        assert_eq!(json_path(&path), r#"$.a[2]["b c"]"#);
+        assert_eq!(json_path(&[Segment::Key("1st".to_string())]), r#"$["1st"]"#);
+        assert_eq!(json_path(&[]), "$");
+    }
+
+    #[test]
+    fn starts_with_two_levels_expanded() {
+        let value = body();
+        let tree = JsonTree::new(&value);
+        assert_eq!(paths(&tree, &value), ["$", "$.count", "$.empty", "$.user", "$.user.name", "$.user.roles"]);
+        let rows = tree.rows(&value);
+        assert!(rows[0].expanded && rows[3].expanded && !rows[5].expanded);
+        // Empty objects have nothing to expand.
+        assert!(!rows[2].is_container() && !rows[2].expanded);
+        assert_eq!(rows[4].depth(), 2);
+        assert_eq!(rows[4].value, &json!("Ann"));
+    }
+
+    #[test]
+    fn expands_and_collapses_the_selected_node() {
+        let value = body();
+        let mut tree = JsonTree::new(&value);
+        select(&mut tree, &value, "$.user.roles");
+        tree.expand(&value);
+        assert_eq!(paths(&tree, &value)[5..], ["$.user.roles", "$.user.roles[0]", "$.user.roles[1]"]);
+        // Expanding again moves to the first child.
+        tree.expand(&value);
+        assert_eq!(tree.selected_path().map(|path| json_path(&path)).as_deref(), Some("$.user.roles[0]"));
+        // Collapsing a scalar moves to its parent, then collapses that.
+        tree.collapse(&value);
+        assert_eq!(tree.selected, 5);
+        tree.collapse(&value);
+        assert_eq!(paths(&tree, &value).len(), 6);
+
+        select(&mut tree, &value, "$.user");
+        tree.toggle(&value);
+        assert_eq!(paths(&tree, &value), ["$", "$.count", "$.empty", "$.user"]);
+        tree.toggle(&value);
+        assert_eq!(paths(&tree, &value).len(), 6);
+        // Scalars do not toggle.
+        select(&mut tree, &value, "$.count");
+        tree.toggle(&value);
+        assert_eq!(paths(&tree, &value).len(), 6);
+    }
+
+    #[test]
+    fn keeps_the_selection_within_the_rows() {
+        let value = body();
+        let mut tree = JsonTree::new(&value);
+        tree.select(-3);
+        assert_eq!(tree.selected, 0);
+        tree.select(100);
+        assert_eq!(tree.selected, 5);
+        tree.selected = 0;
+        tree.select_last();
+        assert_eq!(tree.selected, 5);
+        assert!(JsonTree::default().selected_path().is_none());
+    }
+
+    #[test]
+    fn finds_keys_in_collapsed_nodes_and_wraps_around() {
+        let value = body();
+        let mut tree = JsonTree::new(&value);
+        tree.search = "SCO".to_string();
+        let found = tree.find_next(&value).unwrap();
+        assert_eq!(json_path(&found), "$.user.roles[1].scope");
+        assert_eq!(tree.selected_path(), Some(found));
+        assert!(paths(&tree, &value).contains(&"$.user.roles[1].scope".to_string()));
+
+        tree.search = "n".to_string();
+        assert_eq!(tree.find_next(&value).map(|path| json_path(&path)).as_deref(), Some("$.count"));
+        assert_eq!(tree.find_next(&value).map(|path| json_path(&path)).as_deref(), Some("$.user.name"));
+        tree.search = "missing".to_string();
+        assert!(tree.find_next(&value).is_none());
+    }
+
+    #[test]
+    fn only_rebuilds_rows_when_nodes_open_or_close() {
+        let value = body();
+        let mut tree = JsonTree::new(&value);
+        let before = tree.visible.as_ptr();
+        tree.select(2);
+        tree.select_last();
+        assert_eq!(tree.rows(&value).len(), 6);
+        assert_eq!(tree.visible.as_ptr(), before);
+        tree.toggle(&value);
+        assert_ne!(tree.visible.len(), 6);
+    }
+}
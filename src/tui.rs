@@ -1,5 +1,5 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -9,14 +9,51 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
 
-use std::io;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 
+use crate::auth::{self, ApiKeyLocation, Auth, AuthError, AuthType};
+use crate::charset;
+use crate::client::{Client, ClientSettings, ConnectionTracker};
+use crate::compression::Body;
+use crate::content;
+use crate::cookies::{CookieEntry, CookieJar};
+use crate::diff::{self, Difference, Snapshot};
 use crate::headers::add_headers_to_request;
-use crate::json::{pretty_print_json_safe, validate_json};
+use crate::filter::{self, Filter, FilterError};
+use crate::format::{self, Format};
+use crate::html;
+use crate::json::validate_json;
+use crate::oauth::OAuthSession;
+use crate::pager::{self, Pager};
+use crate::protobuf::ProtobufSettings;
+use crate::proxy::Route;
+use crate::redirect::{self, Hop, RedirectPolicy};
+use crate::retry::Attempt;
+use crate::schema::{Schemas, Violation};
+use crate::sender::Sender;
+use crate::serialization::Serialization;
+use crate::session::Session;
+use crate::signing::RequestSigner;
+use crate::timing::{self, Timings};
+use crate::tree::{self, JsonTree, Row, Segment};
+
+/// Bytes of a body shown in the hex view.
+const HEX_PREVIEW_LENGTH: usize = 4096;
+
+/// Rows scrolled by one step of the mouse wheel.
+const WHEEL_ROWS: isize = 3;
+
+/// Columns scrolled sideways by one key press.
+const SIDEWAYS_COLUMNS: isize = 8;
 
 #[derive(Debug, Clone)]
 pub enum HttpMethodType {
@@ -37,6 +74,31 @@ impl std::fmt::Display for HttpMethodType {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMode {
+    None,
+    Basic,
+    Bearer,
+    Digest,
+    ApiKeyHeader,
+    ApiKeyQuery,
+    OAuth2,
+}
+
+impl std::fmt::Display for AuthMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthMode::None => write!(f, "None"),
+            AuthMode::Basic => write!(f, "Basic"),
+            AuthMode::Bearer => write!(f, "Bearer"),
+            AuthMode::Digest => write!(f, "Digest"),
+            AuthMode::ApiKeyHeader => write!(f, "API Key (header)"),
+            AuthMode::ApiKeyQuery => write!(f, "API Key (query)"),
+            AuthMode::OAuth2 => write!(f, "OAuth 2.0"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HttpRequest {
     pub method: HttpMethodType,
@@ -44,6 +106,8 @@ pub struct HttpRequest {
     pub headers: Vec<String>,
     pub body: Option<String>,
     pub is_json: bool,
+    pub auth_mode: AuthMode,
+    pub auth_input: String,
 }
 
 #[derive(Debug, Clone)]
@@ -53,6 +117,45 @@ pub struct HttpResponse {
     pub headers: Vec<(String, String)>,
     pub body: String,
     pub duration_ms: u64,
+    pub redirects: Vec<Hop>,
+    /// How the final request reached the server.
+    pub route: Route,
+    /// Address the final request was sent to.
+    pub remote_addr: Option<SocketAddr>,
+    /// Unix socket the final request was sent over.
+    pub unix_socket: Option<PathBuf>,
+    pub version: reqwest::Version,
+    /// How many requests the connection has carried, when known.
+    pub connection_requests: Option<usize>,
+    /// Phases of the final request.
+    pub timings: Timings,
+    /// Every attempt, including retries and those of earlier hops.
+    pub attempts: Vec<Attempt>,
+    /// Content-Encoding and sizes, when the body was compressed.
+    pub encoding: Option<String>,
+    /// Charset and where it came from, unless the body was plain UTF-8.
+    pub charset: Option<String>,
+    /// MessagePack, CBOR or Protobuf, when the body was decoded to JSON.
+    pub format: Option<String>,
+    /// Readable text of HTML bodies, for the text view.
+    pub html_text: Option<String>,
+    /// JSON bodies and decoded MessagePack, CBOR and Protobuf, for the tree
+    /// view and the filter.
+    pub json: Option<serde_json::Value>,
+    /// The decoded body as received, for the hex view.
+    pub bytes: Vec<u8>,
+    /// Size, type and file signature of binary bodies, which are not shown
+    /// as text.
+    pub binary: Option<String>,
+    /// Where the body breaks the response schema, when there is one.
+    pub schema_violations: Option<Vec<Violation>>,
+}
+
+impl HttpResponse {
+    /// How many attempts were retries.
+    pub fn retries(&self) -> usize {
+        self.attempts.iter().filter(|attempt| attempt.number > 1).count()
+    }
 }
 
 pub enum InputMode {
@@ -60,12 +163,86 @@ pub enum InputMode {
     EditingUrl,
     EditingHeaders,
     EditingBody,
+    EditingAuth,
+    EditingCookie,
+    EditingFilter,
+    FindingKey,
+    SearchingText,
+}
+
+pub enum ResponseTab {
+    Body,
+    Headers,
+}
+
+/// Two history entries compared line by line: status, headers and body,
+/// with JSON keys sorted so that their order does not count.
+pub struct DiffView {
+    /// History numbers, from 1.
+    pub old: usize,
+    pub new: usize,
+    pub rows: Vec<(Option<String>, Option<String>)>,
+    /// `2 headers, 3 body values differ`.
+    pub summary: String,
+    pub top: usize,
+    /// Rows shown at the last draw.
+    pub height: usize,
+}
+
+impl DiffView {
+    fn new(old: (usize, &HttpResponse), new: (usize, &HttpResponse), protobuf: &ProtobufSettings) -> Self {
+        let ignored: Vec<String> = diff::VOLATILE_HEADERS.iter().map(|name| name.to_string()).collect();
+        let [old_snapshot, new_snapshot] = [old.1, new.1].map(|response| {
+            Snapshot::from_response(response.status, &response.headers, &response.bytes, protobuf).without_headers(&ignored)
+        });
+        let (old_text, new_text) = (old_snapshot.render(), new_snapshot.render());
+        let lines = diff::diff_lines(&old_text, &new_text);
+        let rows = diff::side_by_side(&lines)
+            .into_iter()
+            .map(|(left, right)| (left.map(str::to_string), right.map(str::to_string)))
+            .collect();
+        DiffView {
+            old: old.0 + 1,
+            new: new.0 + 1,
+            rows,
+            summary: Difference::new(&old_snapshot, &new_snapshot).summary(),
+            top: 0,
+            height: 20,
+        }
+    }
+
+    fn scroll(&mut self, rows: isize) {
+        let last = self.rows.len().saturating_sub(self.height);
+        self.top = self.top.saturating_add_signed(rows).min(last);
+    }
+
+    /// Scrolls to the start of the next or previous run of changed rows.
+    fn next_change(&mut self, forward: bool) -> bool {
+        let changed = |index: usize| self.rows.get(index).is_some_and(|(left, right)| left != right);
+        let starts = (0..self.rows.len()).filter(|index| changed(*index) && (*index == 0 || !changed(index - 1)));
+        let found = if forward {
+            starts.into_iter().find(|index| *index > self.top)
+        } else {
+            starts.into_iter().rev().find(|index| *index < self.top)
+        };
+        let Some(found) = found else {
+            return false;
+        };
+        // Changes near the end cannot all scroll to the top.
+        let top = self.top;
+        self.top = 0;
+        self.scroll(found as isize);
+        self.top != top
+    }
 }
 
 pub enum ActivePanel {
     Request,
     Response,
     History,
+    Cookies,
+    /// Two history entries side by side, over the whole screen.
+    Diff,
 }
 
 pub struct App {
@@ -78,21 +255,56 @@ pub struct App {
     pub headers_input: String,
     pub body_input: String,
     pub is_json_body: bool,
+    pub auth_mode_index: usize,
+    pub auth_input: String,
+    pub oauth: Option<OAuthSession>,
+
+    /// Shared by every request so cookies carry over between them.
+    pub client: Client,
+    pub client_settings: ClientSettings,
+    pub connections: ConnectionTracker,
+    pub cookie_jar: Arc<CookieJar>,
+    pub cookie_state: ListState,
+    pub cookie_input: String,
+    /// The cookie being replaced by the one in `cookie_input`, if editing.
+    pub editing_cookie: Option<CookieEntry>,
+    pub session: Option<Session>,
+    pub redirect_policy: RedirectPolicy,
+    /// HMAC and JWT signing steps of the environment, applied to every hop.
+    pub signers: Vec<RequestSigner>,
+    /// Schema for Protobuf bodies, from the command line.
+    pub protobuf: ProtobufSettings,
+    /// JSON Schemas for request and response bodies, from the command line.
+    pub schemas: Schemas,
 
     pub current_response: Option<HttpResponse>,
+    pub response_tab: ResponseTab,
+    pub body_pager: Pager,
+    pub headers_pager: Pager,
+    /// Show response bodies as a hex and ASCII dump.
+    pub hex_view: bool,
+    /// Show HTML bodies as plain text.
+    pub html_text_view: bool,
+    /// JSONPath or jq filter applied to JSON response bodies as it is typed.
+    pub filter_input: String,
+    /// Show JSON bodies as a collapsible tree instead of text.
+    pub tree_view: bool,
+    pub json_tree: JsonTree,
     pub history_state: ListState,
+    /// History entry marked to be compared with another.
+    pub diff_mark: Option<usize>,
+    pub diff: Option<DiffView>,
     pub status_message: String,
     pub request_history: Vec<(HttpRequest, Option<HttpResponse>)>,
-
-    pub url_cursor_position: usize,
-    pub headers_cursor_position: usize,
-    pub body_cursor_position: usize,
 }
 
 impl Default for App {
     fn default() -> Self {
         let mut history_state = ListState::default();
         history_state.select(Some(0));
+        let mut cookie_state = ListState::default();
+        cookie_state.select(Some(0));
+        let cookie_jar = Arc::new(CookieJar::new());
 
         App {
             should_quit: false,
@@ -103,18 +315,132 @@ impl Default for App {
             headers_input: String::new(),
             body_input: String::new(),
             is_json_body: false,
+            auth_mode_index: 0,
+            auth_input: String::new(),
+            oauth: None,
+            client: ClientSettings::default().build(cookie_jar.clone()).unwrap_or_default(),
+            client_settings: ClientSettings::default(),
+            connections: ConnectionTracker::default(),
+            cookie_jar,
+            cookie_state,
+            cookie_input: String::new(),
+            editing_cookie: None,
+            session: None,
+            redirect_policy: RedirectPolicy::default(),
+            signers: Vec::new(),
+            protobuf: ProtobufSettings::default(),
+            schemas: Schemas::default(),
             current_response: None,
+            response_tab: ResponseTab::Body,
+            body_pager: Pager::default(),
+            headers_pager: Pager::default(),
+            hex_view: false,
+            html_text_view: false,
+            filter_input: String::new(),
+            tree_view: true,
+            json_tree: JsonTree::default(),
             status_message: "Ready".to_string(),
             request_history: Vec::new(),
             history_state,
-            url_cursor_position: 0,
-            headers_cursor_position: 0,
-            body_cursor_position: 0,
+            diff_mark: None,
+            diff: None,
         }
     }
 }
 
 impl App {
+    pub fn use_client_settings(&mut self, settings: ClientSettings) -> Result<(), Box<dyn std::error::Error>> {
+        self.client = settings.build(self.cookie_jar.clone())?;
+        self.client_settings = settings;
+        self.connections = ConnectionTracker::default();
+        Ok(())
+    }
+
+    /// Starts from a saved session: its cookies, headers and credentials.
+    pub fn load_session(&mut self, session: Session) -> Result<(), Box<dyn std::error::Error>> {
+        self.cookie_jar = Arc::new(session.cookie_jar()?);
+        self.client = self.client_settings.build(self.cookie_jar.clone())?;
+        self.connections = ConnectionTracker::default();
+        self.headers_input = session.headers.join("\n");
+
+        let (mode, input) = match &session.auth {
+            Auth::None => (None, String::new()),
+            Auth::Basic { username, password } => (Some(AuthMode::Basic), format!("{}:{}", username, password)),
+            Auth::Bearer { token } => (Some(AuthMode::Bearer), token.clone()),
+            Auth::Digest { username, password } => (Some(AuthMode::Digest), format!("{}:{}", username, password)),
+        };
+        if let Some(mode) = mode {
+            self.auth_mode_index = Self::get_auth_modes().iter().position(|m| *m == mode).unwrap_or(0);
+            self.auth_input = input;
+        }
+        self.session = Some(session);
+        Ok(())
+    }
+
+    /// Writes the cookie jar back to the session, if one is open.
+    fn save_session(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(session) = &mut self.session {
+            session.store_cookies(&self.cookie_jar);
+            session.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn selected_cookie(&self) -> Option<CookieEntry> {
+        let selected = self.cookie_state.selected()?;
+        self.cookie_jar.entries().into_iter().nth(selected)
+    }
+
+    pub fn select_cookie(&mut self, offset: isize) {
+        let len = self.cookie_jar.entries().len() as isize;
+        if len == 0 {
+            return;
+        }
+        let current = self.cookie_state.selected().unwrap_or(0) as isize;
+        self.cookie_state.select(Some((current + offset).rem_euclid(len) as usize));
+    }
+
+    pub fn new_cookie(&mut self) {
+        self.editing_cookie = None;
+        self.cookie_input = String::new();
+        self.input_mode = InputMode::EditingCookie;
+    }
+
+    pub fn edit_cookie(&mut self) {
+        if let Some(cookie) = self.selected_cookie() {
+            self.cookie_input = cookie.to_spec();
+            self.editing_cookie = Some(cookie);
+            self.input_mode = InputMode::EditingCookie;
+        }
+    }
+
+    pub fn delete_cookie(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(cookie) = self.selected_cookie() {
+            self.cookie_jar.remove(&cookie);
+            self.select_cookie(0);
+            self.save_session()?;
+            self.status_message = format!("Deleted cookie {}", cookie.name);
+        }
+        Ok(())
+    }
+
+    /// Stores the cookie typed in the editor. Cookies without a Domain
+    /// attribute belong to the host of the current URL.
+    pub fn save_cookie(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let host = reqwest::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        let cookie = CookieEntry::parse(&self.cookie_input, host.as_deref())?;
+
+        if let Some(previous) = self.editing_cookie.take() {
+            self.cookie_jar.remove(&previous);
+        }
+        self.cookie_jar.insert(&cookie)?;
+        self.save_session()?;
+        self.status_message = format!("Saved cookie {}", cookie.name);
+        Ok(())
+    }
+
     pub fn get_methods() -> Vec<HttpMethodType> {
         vec![HttpMethodType::Get, HttpMethodType::Post, HttpMethodType::Put, HttpMethodType::Delete]
     }
@@ -137,8 +463,268 @@ impl App {
         };
     }
 
+    pub fn get_auth_modes() -> Vec<AuthMode> {
+        vec![
+            AuthMode::None,
+            AuthMode::Basic,
+            AuthMode::Bearer,
+            AuthMode::Digest,
+            AuthMode::ApiKeyHeader,
+            AuthMode::ApiKeyQuery,
+            AuthMode::OAuth2,
+        ]
+    }
+
+    pub fn current_auth_mode(&self) -> AuthMode {
+        Self::get_auth_modes()[self.auth_mode_index]
+    }
+
+    pub fn next_auth_mode(&mut self) {
+        self.auth_mode_index = (self.auth_mode_index + 1) % Self::get_auth_modes().len();
+    }
+
+    /// Auth input with secrets hidden, keeping the user or key name visible.
+    pub fn masked_auth_input(&self) -> String {
+        let separator = match self.current_auth_mode() {
+            AuthMode::None => return String::new(),
+            AuthMode::OAuth2 => {
+                return match &self.oauth {
+                    Some(session) => format!("({})", session.environment),
+                    None => "(start with --env to configure)".to_string(),
+                };
+            }
+            AuthMode::Bearer => return "*".repeat(self.auth_input.len()),
+            AuthMode::ApiKeyQuery => '=',
+            _ => ':',
+        };
+        match self.auth_input.split_once(separator) {
+            Some((name, secret)) => format!("{}{}{}", name, separator, "*".repeat(secret.len())),
+            None => self.auth_input.clone(),
+        }
+    }
+
+    fn build_auth(&self) -> Result<(Auth, Option<auth::ApiKey>), AuthError> {
+        let input = self.auth_input.trim();
+        // The TUI owns the terminal, so missing passwords cannot be prompted for.
+        let require_password = || {
+            if input.contains(':') {
+                Ok(())
+            } else {
+                Err(AuthError::InvalidCredentials(input.to_string()))
+            }
+        };
+
+        Ok(match self.current_auth_mode() {
+            AuthMode::None | AuthMode::OAuth2 => (Auth::None, None),
+            AuthMode::Basic => {
+                require_password()?;
+                (auth::parse_auth(input, AuthType::Basic)?, None)
+            }
+            AuthMode::Digest => {
+                require_password()?;
+                (auth::parse_auth(input, AuthType::Digest)?, None)
+            }
+            AuthMode::Bearer => {
+                if input.is_empty() {
+                    return Err(AuthError::InvalidCredentials(input.to_string()));
+                }
+                (auth::parse_auth(input, AuthType::Bearer)?, None)
+            }
+            AuthMode::ApiKeyHeader => {
+                (Auth::None, Some(auth::parse_api_key(input, ApiKeyLocation::Header)?))
+            }
+            AuthMode::ApiKeyQuery => {
+                (Auth::None, Some(auth::parse_api_key(input, ApiKeyLocation::Query)?))
+            }
+        })
+    }
+
+    /// Whether the response body is shown as a tree: a JSON body, with no
+    /// hex view or filter replacing it.
+    pub fn tree_shown(&self) -> bool {
+        matches!(self.response_tab, ResponseTab::Body)
+            && self.tree_view
+            && !self.hex_view
+            && self.filter_input.is_empty()
+            && self.current_response.as_ref().is_some_and(|response| response.json.is_some())
+    }
+
+    /// Whether keys go to the tree: it is shown in the active panel.
+    fn in_tree(&self) -> bool {
+        matches!(self.active_panel, ActivePanel::Response) && self.tree_shown()
+    }
+
+    /// Whether keys go to the pager: the response is shown as text.
+    fn in_pager(&self) -> bool {
+        matches!(self.active_panel, ActivePanel::Response) && self.current_response.is_some() && !self.tree_shown()
+    }
+
+    /// Text of the response tab shown.
+    fn shown_text(&self) -> Option<String> {
+        let response = self.current_response.as_ref()?;
+        Some(match self.response_tab {
+            ResponseTab::Headers => header_text(response),
+            ResponseTab::Body => {
+                let filtered = (!self.filter_input.is_empty()).then(|| filtered_body(response, &self.filter_input));
+                body_text(self, response, filtered.as_ref(), self.body_pager.width as u16 + 2).0
+            }
+        })
+    }
+
+    pub fn with_pager(&mut self, action: impl FnOnce(&mut Pager, &str)) {
+        let Some(text) = self.shown_text() else {
+            return;
+        };
+        match self.response_tab {
+            ResponseTab::Body => action(&mut self.body_pager, &text),
+            ResponseTab::Headers => action(&mut self.headers_pager, &text),
+        }
+    }
+
+    /// Selects the next or previous match of the text search.
+    pub fn find_text(&mut self, forward: bool) {
+        let mut missing = None;
+        self.with_pager(|pager, text| {
+            if pager.find(text, forward).is_none() {
+                missing = Some(pager.search.clone());
+            }
+        });
+        if let Some(search) = missing {
+            self.status_message = if search.is_empty() {
+                "Press / to search".to_string()
+            } else {
+                format!("No matches for '{}'", search)
+            };
+        }
+    }
+
+    /// Scrolls the tree or the text with the mouse wheel.
+    pub fn scroll_response(&mut self, rows: isize) {
+        if self.tree_shown() {
+            self.json_tree.select(rows);
+        } else {
+            self.with_pager(|pager, text| pager.scroll(text, rows));
+        }
+    }
+
+    pub fn navigate_tree(&mut self, action: impl FnOnce(&mut JsonTree, &serde_json::Value)) {
+        if let Some(value) = self.current_response.as_ref().and_then(|response| response.json.as_ref()) {
+            action(&mut self.json_tree, value);
+        }
+    }
+
+    /// Selects the next node whose key contains the search text.
+    pub fn find_key(&mut self) {
+        let Some(value) = self.current_response.as_ref().and_then(|response| response.json.as_ref()) else {
+            return;
+        };
+        if self.json_tree.search.is_empty() {
+            return;
+        }
+        self.status_message = match self.json_tree.find_next(value) {
+            Some(path) => format!("Found {}", tree::json_path(&path)),
+            None => format!("No key contains '{}'", self.json_tree.search),
+        };
+    }
+
+    /// Copies the JSONPath of the selected node to the clipboard.
+    pub fn copy_tree_path(&mut self) {
+        let Some(path) = self.json_tree.selected_path() else {
+            return;
+        };
+        let path = tree::json_path(&path);
+        self.status_message = match copy_to_clipboard(&path) {
+            Ok(()) => format!("Copied {}", path),
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    /// Loads the selected history entry back into the request editor.
+    pub fn recall_history(&mut self) {
+        let selected = self.history_state.selected().unwrap_or(0);
+        let Some((request, _)) = self.request_history.get(selected) else {
+            return;
+        };
+
+        let request = request.clone();
+        self.method_index = Self::get_methods()
+            .iter()
+            .position(|m| m.to_string() == request.method.to_string())
+            .unwrap_or(0);
+        self.url = request.url;
+        self.headers_input = request.headers.join("\n");
+        self.body_input = request.body.unwrap_or_default();
+        self.is_json_body = request.is_json;
+        self.auth_mode_index = Self::get_auth_modes()
+            .iter()
+            .position(|m| *m == request.auth_mode)
+            .unwrap_or(0);
+        self.auth_input = request.auth_input;
+        self.active_panel = ActivePanel::Request;
+        self.status_message = format!("Loaded request #{} from history", selected + 1);
+    }
+
+    /// Marks the selected history entry, or compares it with the marked one.
+    pub fn mark_for_diff(&mut self) {
+        let selected = self.history_state.selected().unwrap_or(0);
+        if selected >= self.request_history.len() {
+            return;
+        }
+        let marked = match self.diff_mark {
+            Some(marked) if marked != selected => marked,
+            Some(_) => {
+                self.diff_mark = None;
+                self.status_message = "Diff mark cleared".to_string();
+                return;
+            }
+            None => {
+                self.diff_mark = Some(selected);
+                self.status_message = format!("Marked #{}; select another entry and press d to compare", selected + 1);
+                return;
+            }
+        };
+        let (Some(old), Some(new)) = (&self.request_history[marked].1, &self.request_history[selected].1) else {
+            self.status_message = "Only entries with a response can be compared".to_string();
+            return;
+        };
+        let view = DiffView::new((marked, old), (selected, new), &self.protobuf);
+        self.status_message = format!("#{} -> #{}: {}", view.old, view.new, view.summary);
+        self.diff = Some(view);
+        self.diff_mark = None;
+        self.active_panel = ActivePanel::Diff;
+    }
+
+    pub fn scroll_diff(&mut self, rows: isize) {
+        if let Some(view) = &mut self.diff {
+            view.scroll(rows);
+        }
+    }
+
+    pub fn next_change(&mut self, forward: bool) {
+        if let Some(view) = &mut self.diff
+            && !view.next_change(forward)
+        {
+            self.status_message = "No more changes".to_string();
+        }
+    }
+
+    /// Where a JSON body being edited breaks the request schema.
+    pub fn body_violations(&self) -> Option<Vec<Violation>> {
+        let schema = self.schemas.request.as_ref()?;
+        (self.is_json_body && !self.body_input.trim().is_empty()).then(|| schema.validate_text(&self.body_input))
+    }
+
+    pub fn select_history(&mut self, offset: isize) {
+        if self.request_history.is_empty() {
+            return;
+        }
+        let len = self.request_history.len() as isize;
+        let current = self.history_state.selected().unwrap_or(0) as isize;
+        self.history_state.select(Some((current + offset).rem_euclid(len) as usize));
+    }
+
     pub async fn send_request(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let client = reqwest::Client::new();
+        let client = self.client.clone();
         let start = std::time::Instant::now();
 
         self.status_message = "Sending request...".to_string();
@@ -150,44 +736,157 @@ impl App {
             .collect();
 
         let mut request = match self.current_method() {
-            HttpMethodType::Get => client.get(&self.url),
-            HttpMethodType::Post => client.post(&self.url),
-            HttpMethodType::Put => client.put(&self.url),
-            HttpMethodType::Delete => client.delete(&self.url),
+            HttpMethodType::Get => client.http().get(&self.url),
+            HttpMethodType::Post => client.http().post(&self.url),
+            HttpMethodType::Put => client.http().put(&self.url),
+            HttpMethodType::Delete => client.http().delete(&self.url),
         };
 
         request = add_headers_to_request(request, &headers)?;
 
+        let (auth, api_key) = self.build_auth()?;
+        request = auth::apply_auth(request, &auth);
+        if let Some(key) = &api_key {
+            request = auth::apply_api_key(request, key);
+        }
+
+        // A JSON body is encoded when the headers ask for a binary format.
+        let send_as = headers
+            .iter()
+            .filter_map(|header| header.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-type"))
+            .and_then(|(_, value)| Serialization::from_content_type(value.trim()));
         if !self.body_input.trim().is_empty() {
             if self.is_json_body {
                 validate_json(&self.body_input)?;
-                request = request
-                    .header("Content-Type", "application/json")
-                    .body(self.body_input.clone());
+                if let Some(violations) = self.body_violations()
+                    && let Some(first) = violations.first()
+                {
+                    return Err(match violations.len() {
+                        1 => format!("the body breaks the schema: {}", first),
+                        count => format!("the body breaks the schema: {} (and {} more)", first, count - 1),
+                    }
+                    .into());
+                }
+                request = match send_as {
+                    Some(format) => request.body(format.encode(&self.body_input, &self.protobuf)?),
+                    None => request
+                        .header("Content-Type", "application/json")
+                        .body(self.body_input.clone()),
+                };
             } else {
                 request = request.body(self.body_input.clone());
             }
         }
 
-        let response = request.send().await?;
+        let request = request.build()?;
+        let oauth = if self.current_auth_mode() == AuthMode::OAuth2 {
+            let session = self
+                .oauth
+                .as_ref()
+                .ok_or("No OAuth 2.0 settings: start the TUI with --env <name>")?;
+            Some(session)
+        } else {
+            None
+        };
+        let sender = Sender {
+            client: &client,
+            settings: &self.client_settings,
+            auth: &auth,
+            oauth,
+            signers: &self.signers,
+            sigv4: None,
+        };
+        let mut timings = (Timings::default(), std::time::Instant::now());
+        let mut attempts = Vec::new();
+        let mut hop_attempts = 0;
+        let send = async |request: reqwest::Request, with_auth: bool| -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+            let sent = sender.send(request, with_auth).await;
+            timings = sent.timings;
+            hop_attempts = sent.attempts.len();
+            attempts.extend(sent.attempts);
+            sent.response
+        };
+        let result = redirect::follow(request, &self.redirect_policy, send).await;
+        let (response, redirects) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                return Err(match hop_attempts {
+                    0 | 1 => e,
+                    failed => format!("{} (after {} attempts)", e, failed).into(),
+                });
+            }
+        };
+        let route = self.client_settings.proxy.route(response.url());
+        let remote_addr = response.remote_addr();
+        let unix_socket = client.unix_socket(response.url());
+        let version = response.version();
+        let resolutions = self.client_settings.dns.take_resolutions();
+        let connection_requests = match route {
+            Route::Proxy { .. } => None,
+            _ => self.connections.record(response.url(), remote_addr, &resolutions),
+        };
         let duration = start.elapsed();
 
+        if let Some(session) = &mut self.session {
+            session.remember_headers(&headers);
+            if !matches!(auth, Auth::None) {
+                session.auth = auth.clone();
+            }
+        }
+        self.save_session()?;
+
         let status = response.status().as_u16();
         let status_text = response.status().to_string();
         let response_headers: Vec<(String, String)> = response
             .headers()
             .iter()
             .map(|(name, value)| {
-                (name.to_string(), value.to_str().unwrap_or("").to_string())
+                (name.to_string(), charset::header_value(value))
             })
             .collect();
 
-        let body = response.text().await?;
-        let pretty_body = if body.trim().starts_with('{') || body.trim().starts_with('[') {
-            pretty_print_json_safe(&body)
-        } else {
-            body
+        let header_map = response.headers().clone();
+        let bytes = response.bytes().await?;
+        let (mut timings, first_byte) = timings;
+        timings.transfer = first_byte.elapsed();
+        let decoded = Body::decode(&header_map, bytes.to_vec(), false)?;
+        let encoding = decoded.summary();
+        let content_type = header_map
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        let serialization = Serialization::from_content_type(content_type);
+        let (tree, binary) = match serialization.map(|format| format.decode(&decoded.bytes, content_type, &self.protobuf)) {
+            Some(Ok(tree)) => (Some(tree), None),
+            Some(Err(e)) => (None, Some(format!("{}; {}", content::describe(content_type, &decoded.bytes), e))),
+            None => (
+                None,
+                content::is_binary(content_type, &decoded.bytes).then(|| content::describe(content_type, &decoded.bytes)),
+            ),
+        };
+        let text = charset::decode(&decoded.bytes, content_type, None);
+        let charset = if binary.is_none() && tree.is_none() { text.summary() } else { None };
+        let body = text.text;
+        let format = Format::detect(content_type, &body);
+        let pretty_body = match &tree {
+            Some(tree) => tree.pretty(),
+            None => format::pretty_print(format, &body),
+        };
+        let html_text = (format == Format::Html).then(|| html::html_to_text(&body));
+        let json = match &tree {
+            Some(tree) => Some(tree.value.clone()),
+            None if format == Format::Json => serde_json::from_str(&body).ok(),
+            None => None,
         };
+        let schema_violations = self.schemas.response.as_ref().map(|schema| match &json {
+            Some(value) => schema.validate(value),
+            None => vec![Violation { pointer: String::new(), message: "the body is not JSON".to_string() }],
+        });
+
+        self.json_tree = json.as_ref().map(JsonTree::new).unwrap_or_default();
+        self.body_pager.reset();
+        self.headers_pager.reset();
 
         let http_response = HttpResponse {
             status,
@@ -195,6 +894,22 @@ impl App {
             headers: response_headers,
             body: pretty_body,
             duration_ms: duration.as_millis() as u64,
+            redirects,
+            route,
+            remote_addr,
+            unix_socket,
+            version,
+            connection_requests,
+            timings,
+            attempts,
+            encoding,
+            charset,
+            format: tree.map(|tree| tree.description),
+            html_text,
+            json,
+            bytes: decoded.bytes,
+            binary,
+            schema_violations,
         };
 
         let http_request = HttpRequest {
@@ -203,18 +918,27 @@ impl App {
             headers,
             body: if self.body_input.trim().is_empty() { None } else { Some(self.body_input.clone()) },
             is_json: self.is_json_body,
+            auth_mode: self.current_auth_mode(),
+            auth_input: self.auth_input.clone(),
         };
 
         self.request_history.push((http_request, Some(http_response.clone())));
         self.current_response = Some(http_response);
         self.active_panel = ActivePanel::Response;
-        self.status_message = format!("Request completed in {}ms", duration.as_millis());
+        self.status_message = match self.current_response.as_ref().and_then(|response| response.schema_violations.as_ref()) {
+            Some(violations) if !violations.is_empty() => format!(
+                "Request completed in {}ms; the response breaks the schema at {}",
+                duration.as_millis(),
+                violations[0]
+            ),
+            _ => format!("Request completed in {}ms", duration.as_millis()),
+        };
 
         Ok(())
     }
 }
 
-pub fn ui(f: &mut Frame, app: &App) {
+pub fn ui(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
@@ -227,7 +951,14 @@ pub fn ui(f: &mut Frame, app: &App) {
     // Status bar
     let status_style = Style::default().fg(Color::Blue);
     let status_text = format!(" Status: {} | Press 'q' to quit, 'h' for help", app.status_message);
-    let status = Paragraph::new(status_text).style(status_style);
+    let status = if app.client_settings.tls.insecure {
+        Paragraph::new(Line::from(vec![
+            Span::styled(" INSECURE TLS ", Style::default().fg(Color::White).bg(Color::Red)),
+            Span::styled(status_text, status_style),
+        ]))
+    } else {
+        Paragraph::new(status_text).style(status_style)
+    };
     f.render_widget(status, chunks[0]);
     
     // Main content split
@@ -247,6 +978,8 @@ pub fn ui(f: &mut Frame, app: &App) {
         ActivePanel::Request => draw_request_panel(f, app, main_chunks[0]),
         ActivePanel::Response => draw_response_panel(f, app, main_chunks[1]),
         ActivePanel::History => draw_history_panel(f, app, main_chunks[1]),
+        ActivePanel::Cookies => draw_cookies_panel(f, app, main_chunks[1]),
+        ActivePanel::Diff => draw_diff_panel(f, app, chunks[1]),
     }
 }
 
@@ -256,6 +989,7 @@ fn draw_request_panel(f: &mut Frame, app: &App, area: Rect) {
         .constraints([
             Constraint::Length(3), // Method + URL
             Constraint::Length(5), // Headers
+            Constraint::Length(3), // Auth
             Constraint::Min(0),    // Body
         ])
         .split(area);
@@ -267,9 +1001,10 @@ fn draw_request_panel(f: &mut Frame, app: &App, area: Rect) {
     };
     
     let method_url_text = format!("{} {}", app.current_method(), app.url);
+    let request_title = if app.redirect_policy.follow { "Request" } else { "Request (redirects not followed)" };
     let method_url = Paragraph::new(method_url_text)
         .style(if matches!(app.input_mode, InputMode::EditingUrl) { url_style } else { Style::default() })
-        .block(Block::default().borders(Borders::ALL).title("Request"));
+        .block(Block::default().borders(Borders::ALL).title(request_title));
     f.render_widget(method_url, chunks[0]);
     
     // Headers
@@ -282,29 +1017,66 @@ fn draw_request_panel(f: &mut Frame, app: &App, area: Rect) {
         .block(Block::default().borders(Borders::ALL).title("Headers"))
         .wrap(Wrap { trim: true });
     f.render_widget(headers, chunks[1]);
+
+    // Auth
+    let auth_style = match app.input_mode {
+        InputMode::EditingAuth => Style::default().fg(Color::Green),
+        _ => Style::default(),
+    };
+    let auth_text = format!("{} {}", app.current_auth_mode(), app.masked_auth_input());
+    let auth = Paragraph::new(auth_text)
+        .style(auth_style)
+        .block(Block::default().borders(Borders::ALL).title("Auth"));
+    f.render_widget(auth, chunks[2]);
     
     // Body
     let body_style = match app.input_mode {
         InputMode::EditingBody => Style::default().fg(Color::Green),
         _ => Style::default(),
     };
-    let body_title = if app.is_json_body { "Body (JSON)" } else { "Body" };
-    let body = Paragraph::new(app.body_input.as_str())
+    // Schema errors are listed under the text as it is typed.
+    let body_label = if app.is_json_body { "Body (JSON)" } else { "Body" };
+    let mut body_text = Text::from(app.body_input.as_str());
+    let body_title = match app.body_violations() {
+        None => Line::from(body_label),
+        Some(violations) if violations.is_empty() => Line::from(vec![
+            Span::raw(format!("{} - ", body_label)),
+            Span::styled("matches the schema", Style::default().fg(Color::Green)),
+        ]),
+        Some(violations) => {
+            let count = match violations.len() {
+                1 => "1 schema error".to_string(),
+                count => format!("{} schema errors", count),
+            };
+            body_text.lines.push(Line::default());
+            for violation in &violations {
+                body_text.lines.push(Line::styled(format!("✗ {}", violation), Style::default().fg(Color::Red)));
+            }
+            Line::from(vec![
+                Span::raw(format!("{} - ", body_label)),
+                Span::styled(count, Style::default().fg(Color::Red)),
+            ])
+        }
+    };
+    let body = Paragraph::new(body_text)
         .style(body_style)
         .block(Block::default().borders(Borders::ALL).title(body_title))
         .wrap(Wrap { trim: true });
-    f.render_widget(body, chunks[2]);
+    f.render_widget(body, chunks[3]);
 }
 
-fn draw_response_panel(f: &mut Frame, app: &App, area: Rect) {
+/// Records the size of the body area in the pagers, for scrolling by pages.
+fn draw_response_panel(f: &mut Frame, app: &mut App, area: Rect) {
     match &app.current_response {
         Some(response) => {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
                     Constraint::Length(3), // Status
-                    Constraint::Length(6), // Headers
-                    Constraint::Min(0),    // Body
+                    Constraint::Length(redirect_chain_height(response)), // Redirects
+                    Constraint::Length(attempts_height(response)), // Attempts
+                    Constraint::Length(response.timings.phases().len() as u16 + 2), // Timings
+                    Constraint::Min(0),    // Body or headers
                 ])
                 .split(area);
             
@@ -317,34 +1089,138 @@ fn draw_response_panel(f: &mut Frame, app: &App, area: Rect) {
                 Color::Yellow
             };
             
-            let status_text = format!("{} {} ({}ms)", response.status, response.status_text, response.duration_ms);
+            let mut status_text = format!(
+                "{:?} {} {} ({}ms)",
+                response.version, response.status, response.status_text, response.duration_ms
+            );
+            if let Some(address) = response.remote_addr {
+                status_text.push_str(&format!(" from {}", address));
+                if let Some(requests) = response.connection_requests.filter(|requests| *requests > 1) {
+                    status_text.push_str(&format!(", reused connection (request {})", requests));
+                }
+            } else if let Some(socket) = &response.unix_socket {
+                status_text.push_str(&format!(" via unix:{}", socket.display()));
+            }
+            let status_title = match &response.route {
+                Route::Direct => "Response Status".to_string(),
+                route => format!("Response Status - {}", route),
+            };
             let status = Paragraph::new(status_text)
                 .style(Style::default().fg(status_color))
-                .block(Block::default().borders(Borders::ALL).title("Response Status"));
+                .block(Block::default().borders(Borders::ALL).title(status_title));
             f.render_widget(status, chunks[0]);
-            
-            // Headers
-            let header_lines: Vec<Line> = response.headers.iter()
-                .take(4) // Limit to first 4 headers
-                .map(|(name, value)| {
-                    Line::from(vec![
-                        Span::styled(name, Style::default().fg(Color::Cyan)),
-                        Span::raw(": "),
-                        Span::raw(value),
-                    ])
-                })
-                .collect();
-            
-            let headers = Paragraph::new(Text::from(header_lines))
-                .block(Block::default().borders(Borders::ALL).title("Headers"))
-                .wrap(Wrap { trim: true });
-            f.render_widget(headers, chunks[1]);
-            
-            // Body
-            let body = Paragraph::new(response.body.as_str())
-                .block(Block::default().borders(Borders::ALL).title("Response Body"))
-                .wrap(Wrap { trim: true });
-            f.render_widget(body, chunks[2]);
+
+            // Redirect chain
+            if !response.redirects.is_empty() {
+                let hop_lines: Vec<Line> = response.redirects.iter()
+                    .map(|hop| {
+                        Line::from(vec![
+                            Span::styled(hop.status.as_u16().to_string(), Style::default().fg(Color::Yellow)),
+                            Span::raw(format!(" {} {} ", hop.method, hop.url)),
+                            Span::styled(format!("-> {}", hop.location), Style::default().fg(Color::Cyan)),
+                            Span::raw(format!(" ({}ms)", hop.duration.as_millis())),
+                        ])
+                    })
+                    .collect();
+                let redirects = Paragraph::new(Text::from(hop_lines))
+                    .block(Block::default().borders(Borders::ALL).title("Redirects"))
+                    .wrap(Wrap { trim: true });
+                f.render_widget(redirects, chunks[1]);
+            }
+
+            // Attempts
+            if response.retries() > 0 {
+                let attempt_lines: Vec<Line> = response.attempts.iter()
+                    .map(|attempt| {
+                        let color = if attempt.retry_in.is_some() { Color::Yellow } else { Color::Green };
+                        Line::from(vec![
+                            Span::styled(format!("#{} ", attempt.number), Style::default().fg(color)),
+                            Span::raw(format!("{} ({}ms)", attempt.outcome, attempt.duration.as_millis())),
+                            Span::styled(
+                                attempt.retry_in.map(|delay| format!(" retry in {:.1}s", delay.as_secs_f64())).unwrap_or_default(),
+                                Style::default().fg(Color::Cyan),
+                            ),
+                            Span::styled(format!(" {}", attempt.url), Style::default().fg(Color::Gray)),
+                        ])
+                    })
+                    .collect();
+                // Unwrapped, so every attempt keeps its own row.
+                let title = format!("Attempts - {} retries", response.retries());
+                let attempts = Paragraph::new(Text::from(attempt_lines))
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                f.render_widget(attempts, chunks[2]);
+            }
+
+            // Timings waterfall
+            let timings = Paragraph::new(Text::from(timing_waterfall(&response.timings, chunks[3].width)))
+                .block(Block::default().borders(Borders::ALL).title(format!(
+                    "Timings - {} total",
+                    timing::millis(response.timings.total())
+                )));
+            f.render_widget(timings, chunks[3]);
+
+            // Filter bar, above the body while a filter is typed or set
+            let body_tab = matches!(app.response_tab, ResponseTab::Body);
+            let filtered = (body_tab && !app.filter_input.is_empty()).then(|| filtered_body(response, &app.filter_input));
+            let body_area = if matches!(app.input_mode, InputMode::EditingFilter) || filtered.is_some() {
+                let areas = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(0)])
+                    .split(chunks[4]);
+                let title = match &filtered {
+                    None => Span::raw("Filter - JSONPath ($.a[0]) or jq (.a[] | .b), Enter: keep, Esc: clear"),
+                    Some(Ok((1, _))) => Span::raw("Filter - 1 result"),
+                    Some(Ok((count, _))) => Span::raw(format!("Filter - {} results", count)),
+                    Some(Err(e)) => Span::styled(format!("Filter - {}", e), Style::default().fg(Color::Red)),
+                };
+                let style = match app.input_mode {
+                    InputMode::EditingFilter => Style::default().fg(Color::Green),
+                    _ => Style::default(),
+                };
+                let bar = Paragraph::new(app.filter_input.as_str())
+                    .style(style)
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                f.render_widget(bar, areas[0]);
+                areas[1]
+            } else {
+                chunks[4]
+            };
+
+            // Body or headers
+            let width = body_area.width.saturating_sub(2) as usize;
+            let height = body_area.height.saturating_sub(2) as usize;
+            if let Some(value) = response.json.as_ref().filter(|_| app.tree_shown()) {
+                app.body_pager.height = height;
+                draw_json_tree(f, app, value, tab_title(app, response, " (tree, v: text)".to_string()), body_area);
+                return;
+            }
+            let (text, details) = match app.response_tab {
+                ResponseTab::Body => body_text(app, response, filtered.as_ref(), body_area.width),
+                ResponseTab::Headers => (header_text(response), String::new()),
+            };
+            // Hex lines have offsets instead of line numbers.
+            let gutter = if body_tab && app.hex_view { 0 } else { pager::gutter_width(&text) };
+            let pager = match app.response_tab {
+                ResponseTab::Body => &mut app.body_pager,
+                ResponseTab::Headers => &mut app.headers_pager,
+            };
+            pager.width = width.saturating_sub(gutter).max(1);
+            pager.height = height;
+            pager.clamp(&text);
+            let pager = match app.response_tab {
+                ResponseTab::Body => &app.body_pager,
+                ResponseTab::Headers => &app.headers_pager,
+            };
+            let matches = pager.matches(&text);
+            let footer = pager_footer(app, pager, &text, matches.len());
+            let lines = pager_lines(pager, &text, &matches, gutter, !body_tab);
+            let body = Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(tab_title(app, response, details))
+                    .title_bottom(footer),
+            );
+            f.render_widget(body, body_area);
         }
         None => {
             let placeholder = Paragraph::new("No response yet\n\nPress Enter to send request")
@@ -355,6 +1231,288 @@ fn draw_response_panel(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
+/// The Body and Headers tabs, the one shown highlighted, with details about
+/// the body after its tab.
+fn tab_title(app: &App, response: &HttpResponse, suffix: String) -> Line<'static> {
+    let body_tab = matches!(app.response_tab, ResponseTab::Body);
+    let tab = |label: String, active: bool| {
+        let style = if active { Style::default().add_modifier(Modifier::REVERSED) } else { Style::default().fg(Color::Gray) };
+        Span::styled(label, style)
+    };
+    let mut spans = vec![tab("Response Body".to_string(), body_tab)];
+    if body_tab {
+        let mut details = String::new();
+        for detail in [&response.encoding, &response.charset, &response.format].into_iter().flatten() {
+            details.push_str(&format!(" - {}", detail));
+        }
+        match response.schema_violations.as_ref().map(Vec::len) {
+            Some(0) => details.push_str(" - matches the schema"),
+            Some(1) => details.push_str(" - 1 schema violation"),
+            Some(count) => details.push_str(&format!(" - {} schema violations", count)),
+            None => {}
+        }
+        details.push_str(&suffix);
+        spans.push(Span::raw(details));
+    }
+    spans.push(Span::raw(" | "));
+    spans.push(tab(format!("Headers ({})", response.headers.len()), !body_tab));
+    Line::from(spans)
+}
+
+/// The body as shown: hex, filter results, a note about a binary body, the
+/// text of HTML, or the pretty-printed body. Also returns what the title
+/// adds about it.
+fn body_text(
+    app: &App,
+    response: &HttpResponse,
+    filtered: Option<&Result<(usize, String), FilterError>>,
+    width: u16,
+) -> (String, String) {
+    if app.hex_view {
+        (hex_preview(&response.bytes, width), " (hex, x: text)".to_string())
+    } else if let Some(Ok((_, results))) = filtered {
+        (results.clone(), " (filtered)".to_string())
+    } else if let Some(binary) = &response.binary {
+        (format!("Binary body not shown: {}\n\nPress x for a hex view", binary), String::new())
+    } else if let Some(text) = response.html_text.as_ref().filter(|_| app.html_text_view) {
+        (text.clone(), " (text, t: HTML)".to_string())
+    } else if response.json.is_some() {
+        (response.body.clone(), " (text, v: tree)".to_string())
+    } else {
+        (response.body.clone(), String::new())
+    }
+}
+
+/// Every response header, one per line.
+fn header_text(response: &HttpResponse) -> String {
+    response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The search being typed, the selected match, or where the view is.
+fn pager_footer(app: &App, pager: &Pager, text: &str, matches: usize) -> Span<'static> {
+    if matches!(app.input_mode, InputMode::SearchingText) {
+        return Span::styled(
+            format!("/{} ({} matches, Enter: keep, Esc: clear)", pager.search, matches),
+            Style::default().fg(Color::Green),
+        );
+    }
+    if let Some(current) = pager.current.filter(|_| !pager.search.is_empty() && matches > 0) {
+        return Span::styled(
+            format!("Match {} of {} for '{}' (n: next, N: previous)", current + 1, matches, pager.search),
+            Style::default().fg(Color::Yellow),
+        );
+    }
+    let rows = pager.rows(text);
+    let line = rows.get(pager.top).map_or(0, |row| row.line + 1);
+    let wrap = if pager.wrap { "w: unwrap" } else { "w: wrap, ←/→: scroll" };
+    let other_tab = match app.response_tab {
+        ResponseTab::Body => "headers",
+        ResponseTab::Headers => "body",
+    };
+    Span::styled(
+        format!("Line {} of {} ({}, /: search, H: {})", line, text.lines().count(), wrap, other_tab),
+        Style::default().fg(Color::Gray),
+    )
+}
+
+/// The rows in view with line numbers and the search matches highlighted.
+/// Header names are coloured like elsewhere in the panel.
+fn pager_lines(
+    pager: &Pager,
+    text: &str,
+    matches: &[pager::Match],
+    gutter: usize,
+    header_names: bool,
+) -> Vec<Line<'static>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let current = pager.current.and_then(|current| matches.get(current));
+    pager
+        .rows(text)
+        .into_iter()
+        .skip(pager.top)
+        .take(pager.height)
+        .map(|row| {
+            let mut spans = Vec::new();
+            if gutter > 0 {
+                let number = match row.start {
+                    0 => format!("{:>1$} ", row.line + 1, gutter - 1),
+                    _ => " ".repeat(gutter),
+                };
+                spans.push(Span::styled(number, Style::default().fg(Color::DarkGray)));
+            }
+
+            let content = lines[row.line];
+            let (start, end) = if pager.wrap { (row.start, row.end) } else { (pager.left, pager.left + pager.width) };
+            let name_end = content.find(':').filter(|_| header_names).map(|end| content[..end].chars().count());
+            let first = matches.partition_point(|found| found.line < row.line);
+            let in_line = matches[first..].iter().take_while(|found| found.line == row.line);
+            let in_line: Vec<&pager::Match> = in_line.collect();
+
+            let mut run = String::new();
+            let mut run_style = Style::default();
+            for (index, c) in content.chars().enumerate().skip(start).take(end.saturating_sub(start)) {
+                let found = in_line.iter().find(|found| found.start <= index && index < found.end);
+                let style = match found {
+                    Some(found) if Some(*found) == current => Style::default().fg(Color::Black).bg(Color::LightYellow),
+                    Some(_) => Style::default().fg(Color::Black).bg(Color::Yellow),
+                    None if name_end.is_some_and(|end| index < end) => Style::default().fg(Color::Cyan),
+                    None => Style::default(),
+                };
+                if style != run_style && !run.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut run), run_style));
+                }
+                run_style = style;
+                run.push(c);
+            }
+            if !run.is_empty() {
+                spans.push(Span::styled(run, run_style));
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// The visible nodes of a JSON body, one per line and indented by depth, with
+/// the selected node's path or the key search below.
+fn draw_json_tree(f: &mut Frame, app: &App, value: &serde_json::Value, title: Line, area: Rect) {
+    let rows = app.json_tree.rows(value);
+    let items: Vec<ListItem> = rows.iter().map(|row| ListItem::new(tree_line(row))).collect();
+    let footer = match app.input_mode {
+        InputMode::FindingKey => Span::styled(
+            format!("Find key: {} (Enter: next, Esc: done)", app.json_tree.search),
+            Style::default().fg(Color::Green),
+        ),
+        _ => {
+            let path = rows.get(app.json_tree.selected).map(|row| tree::json_path(row.path)).unwrap_or_default();
+            Span::styled(format!("{} (y: copy path, /: find key, H: headers)", path), Style::default().fg(Color::Gray))
+        }
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title).title_bottom(footer))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, area, &mut ListState::default().with_selected(Some(app.json_tree.selected)));
+}
+
+/// A node of the tree: key, then the value of scalars or the size of
+/// collapsed objects and arrays.
+fn tree_line<'a>(row: &Row<'a>) -> Line<'a> {
+    let marker = match (row.is_container(), row.expanded) {
+        (false, _) => "  ",
+        (true, true) => "▾ ",
+        (true, false) => "▸ ",
+    };
+    let mut spans = vec![Span::raw(format!("{}{}", "  ".repeat(row.depth()), marker))];
+    spans.push(match row.path.last() {
+        None => Span::styled("$", Style::default().fg(Color::Cyan)),
+        Some(Segment::Key(key)) => Span::styled(key.clone(), Style::default().fg(Color::Cyan)),
+        Some(Segment::Index(index)) => Span::styled(format!("[{}]", index), Style::default().fg(Color::Gray)),
+    });
+    let count = |count: usize, noun: &str| format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" });
+    let gray = Style::default().fg(Color::Gray);
+    match row.value {
+        serde_json::Value::Object(map) if row.is_container() && !row.expanded => {
+            spans.push(Span::styled(format!(" {{…}} {}", count(map.len(), "key")), gray));
+        }
+        serde_json::Value::Array(values) if row.is_container() && !row.expanded => {
+            spans.push(Span::styled(format!(" […] {}", count(values.len(), "item")), gray));
+        }
+        serde_json::Value::Object(_) if row.is_container() => spans.push(Span::styled(" {}", gray)),
+        serde_json::Value::Array(_) if row.is_container() => spans.push(Span::styled(" []", gray)),
+        value => {
+            let color = match value {
+                serde_json::Value::String(_) => Color::Green,
+                serde_json::Value::Number(_) => Color::Yellow,
+                _ => Color::Magenta,
+            };
+            spans.push(Span::raw(": "));
+            spans.push(Span::styled(value.to_string(), Style::default().fg(color)));
+        }
+    }
+    Line::from(spans)
+}
+
+/// Puts text on the system clipboard with the OSC 52 escape sequence, which
+/// reaches the local clipboard over SSH too. Terminals that do not support it
+/// ignore it.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+/// The results of the filter bar and how many there are, or why there are
+/// none.
+fn filtered_body(response: &HttpResponse, filter: &str) -> Result<(usize, String), FilterError> {
+    let value = response
+        .json
+        .as_ref()
+        .ok_or_else(|| FilterError::NotJson("this body is not JSON".to_string()))?;
+    let results = Filter::parse(filter)?.apply(value)?;
+    Ok((results.len(), filter::render(&results)))
+}
+
+/// One line per phase: label, duration and a bar placed on a shared time
+/// axis, so the phases line up like a browser's network waterfall.
+fn timing_waterfall(timings: &Timings, width: u16) -> Vec<Line<'static>> {
+    let bar_width = (width as usize).saturating_sub(2 + 27).max(1);
+    let total = timings.total().as_secs_f64().max(f64::EPSILON);
+
+    timings
+        .phases()
+        .into_iter()
+        .map(|(label, offset, length)| {
+            let color = match label {
+                "DNS lookup" => Color::Cyan,
+                "TCP connect" => Color::Yellow,
+                "TLS handshake" => Color::Magenta,
+                "Waiting (TTFB)" => Color::Green,
+                _ => Color::Blue,
+            };
+            let start = ((offset.as_secs_f64() / total) * bar_width as f64).round() as usize;
+            let cells = ((length.as_secs_f64() / total) * bar_width as f64).round().max(1.0) as usize;
+            let start = start.min(bar_width - 1);
+            let cells = cells.min(bar_width - start);
+            Line::from(vec![
+                Span::raw(format!("{:<15}{:>10}  ", label, timing::millis(length))),
+                Span::raw(" ".repeat(start)),
+                Span::styled("█".repeat(cells), Style::default().fg(color)),
+            ])
+        })
+        .collect()
+}
+
+/// Hex dump of the start of a body; the panel cannot show more. Narrow
+/// panels get eight bytes per line instead of sixteen.
+fn hex_preview(bytes: &[u8], width: u16) -> String {
+    let per_line = if (width as usize) < content::hex_line_width(16) + 2 { 8 } else { 16 };
+    let mut dump = content::hex_dump(&bytes[..bytes.len().min(HEX_PREVIEW_LENGTH)], per_line);
+    if bytes.len() > HEX_PREVIEW_LENGTH {
+        dump.push_str(&format!("... {} more bytes", bytes.len() - HEX_PREVIEW_LENGTH));
+    }
+    dump
+}
+
+/// Rows for the redirect chain box, capped so the body stays visible.
+fn redirect_chain_height(response: &HttpResponse) -> u16 {
+    match response.redirects.len() {
+        0 => 0,
+        hops => hops.min(5) as u16 + 2,
+    }
+}
+
+/// Rows for the attempts box, shown only when something was retried.
+fn attempts_height(response: &HttpResponse) -> u16 {
+    match response.retries() {
+        0 => 0,
+        _ => response.attempts.len().min(5) as u16 + 2,
+    }
+}
+
 fn draw_history_panel(f: &mut Frame, app: &App, area: Rect) {
     let items: Vec<ListItem> = app
         .request_history
@@ -362,21 +1520,133 @@ fn draw_history_panel(f: &mut Frame, app: &App, area: Rect) {
         .enumerate()
         .map(|(i, (req, resp))| {
             let status = match resp {
+                Some(r) if r.retries() > 0 => format!("{}, {} retries", r.status, r.retries()),
                 Some(r) => format!("{}", r.status),
                 None => "...".to_string(),
             };
-            ListItem::new(format!("{}: {} {} [{}]", i + 1, req.method, req.url, status))
+            let mark = if app.diff_mark == Some(i) { "* " } else { "" };
+            ListItem::new(format!("{}{}: {} {} [{}]", mark, i + 1, req.method, req.url, status))
         })
         .collect();
     
+    let title = match app.diff_mark {
+        Some(marked) => format!("History - d: compare with #{}", marked + 1),
+        None => "History - r: recall, d: mark for diff".to_string(),
+    };
     let history = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("History"))
+        .block(Block::default().borders(Borders::ALL).title(title))
         .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
     
     f.render_stateful_widget(history, area, &mut app.history_state.clone());
 }
 
-pub async fn run_tui() -> Result<(), Box<dyn std::error::Error>> {
+fn draw_diff_panel(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(view) = &mut app.diff else {
+        return;
+    };
+    f.render_widget(Clear, area);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    view.height = columns[0].height.saturating_sub(2) as usize;
+    view.scroll(0);
+
+    let rows = &view.rows[view.top..(view.top + view.height).min(view.rows.len())];
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for (old, new) in rows {
+        let (old_style, new_style) = match (old, new) {
+            (Some(old), Some(new)) if old == new => (Style::default(), Style::default()),
+            (Some(_), Some(_)) => (Style::default().fg(Color::Yellow), Style::default().fg(Color::Yellow)),
+            _ => (Style::default().fg(Color::Red), Style::default().fg(Color::Green)),
+        };
+        left.push(Line::styled(old.clone().unwrap_or_default(), old_style));
+        right.push(Line::styled(new.clone().unwrap_or_default(), new_style));
+    }
+
+    let entry = |number: usize| {
+        app.request_history
+            .get(number - 1)
+            .map_or(format!("#{}", number), |(request, _)| format!("#{} {} {}", number, request.method, request.url))
+    };
+    let position = format!("Row {} of {}", (view.top + 1).min(view.rows.len()), view.rows.len());
+    let old_block = Block::default().borders(Borders::ALL).title(entry(view.old)).title_bottom(view.summary.clone());
+    let new_block = Block::default()
+        .borders(Borders::ALL)
+        .title(entry(view.new))
+        .title_bottom(format!("{} (n/N: next/previous change, Esc: back)", position));
+    f.render_widget(Paragraph::new(left).block(old_block), columns[0]);
+    f.render_widget(Paragraph::new(right).block(new_block), columns[1]);
+}
+
+fn draw_cookies_panel(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),    // Cookie list
+            Constraint::Length(3), // Editor
+        ])
+        .split(area);
+
+    let items: Vec<ListItem> = app
+        .cookie_jar
+        .entries()
+        .iter()
+        .map(|cookie| {
+            let mut flags = Vec::new();
+            if cookie.secure {
+                flags.push("Secure");
+            }
+            if cookie.http_only {
+                flags.push("HttpOnly");
+            }
+            if cookie.expires.is_none() {
+                flags.push("Session");
+            }
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{}{} ", cookie.domain, cookie.path),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(format!("{}={} ", cookie.name, cookie.value)),
+                Span::styled(flags.join(" "), Style::default().fg(Color::Gray)),
+            ]))
+        })
+        .collect();
+
+    let title = match &app.session {
+        Some(_) => "Cookies (session) - n: new, e: edit, d: delete",
+        None => "Cookies - n: new, e: edit, d: delete",
+    };
+    let cookies = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(cookies, chunks[0], &mut app.cookie_state.clone());
+
+    let editor_style = match app.input_mode {
+        InputMode::EditingCookie => Style::default().fg(Color::Green),
+        _ => Style::default().fg(Color::Gray),
+    };
+    let editor = Paragraph::new(app.cookie_input.as_str())
+        .style(editor_style)
+        .block(Block::default().borders(Borders::ALL).title("name=value; Domain=...; Path=/; Secure; HttpOnly"));
+    f.render_widget(editor, chunks[1]);
+}
+
+pub async fn run_tui(
+    oauth: Option<OAuthSession>,
+    signers: Vec<RequestSigner>,
+    session: Option<Session>,
+    settings: ClientSettings,
+    protobuf: ProtobufSettings,
+    schemas: Schemas,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App { signers, protobuf, schemas, ..App::default() };
+    if let Some(session) = session {
+        app.load_session(session)?;
+    }
+    app.use_client_settings(settings)?;
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -385,7 +1655,14 @@ pub async fn run_tui() -> Result<(), Box<dyn std::error::Error>> {
     let mut terminal = Terminal::new(backend)?;
     
     // Create app and run
-    let mut app = App::default();
+    if let Some(mut session) = oauth {
+        session.quiet = true;
+        app.oauth = Some(session);
+        app.auth_mode_index = App::get_auth_modes()
+            .iter()
+            .position(|m| *m == AuthMode::OAuth2)
+            .unwrap_or(0);
+    }
     let res = run_app(&mut terminal, &mut app).await;
     
     // Restore terminal
@@ -411,62 +1688,256 @@ async fn run_app<B: Backend>(
     loop {
         terminal.draw(|f| ui(f, app))?;
         
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                match app.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('q') => {
-                            app.should_quit = true;
-                            break;
-                        }
-                        KeyCode::Char('u') => app.input_mode = InputMode::EditingUrl,
-                        KeyCode::Char('h') => app.input_mode = InputMode::EditingHeaders,
-                        KeyCode::Char('b') => app.input_mode = InputMode::EditingBody,
-                        KeyCode::Char('j') => app.is_json_body = !app.is_json_body,
-                        KeyCode::Char('m') => app.next_method(),
-                        KeyCode::Char('M') => app.previous_method(),
-                        KeyCode::Enter => {
-                            if let Err(e) = app.send_request().await {
-                                app.status_message = format!("Error: {}", e);
-                            }
-                        }
-                        KeyCode::Tab => {
-                            app.active_panel = match app.active_panel {
-                                ActivePanel::Request => ActivePanel::Response,
-                                ActivePanel::Response => ActivePanel::History,
-                                ActivePanel::History => ActivePanel::Request,
-                            };
-                        }
-                        _ => {}
-                    },
-                    InputMode::EditingUrl => match key.code {
-                        KeyCode::Esc => app.input_mode = InputMode::Normal,
-                        KeyCode::Enter => app.input_mode = InputMode::Normal,
-                        KeyCode::Char(c) => app.url.push(c),
-                        KeyCode::Backspace => {
-                            app.url.pop();
+        let event = event::read()?;
+        if let Event::Mouse(mouse) = &event {
+            match (&app.active_panel, mouse.kind) {
+                (ActivePanel::Response, MouseEventKind::ScrollUp) => app.scroll_response(-WHEEL_ROWS),
+                (ActivePanel::Response, MouseEventKind::ScrollDown) => app.scroll_response(WHEEL_ROWS),
+                (ActivePanel::Diff, MouseEventKind::ScrollUp) => app.scroll_diff(-WHEEL_ROWS),
+                (ActivePanel::Diff, MouseEventKind::ScrollDown) => app.scroll_diff(WHEEL_ROWS),
+                _ => {}
+            }
+        }
+        if let Event::Key(key) = event
+            && key.kind == KeyEventKind::Press
+        {
+            match app.input_mode {
+                InputMode::Normal => match key.code {
+                    KeyCode::Char('q') => {
+                        app.should_quit = true;
+                        break;
+                    }
+                    KeyCode::Char('u') => app.input_mode = InputMode::EditingUrl,
+                    KeyCode::Char('h') => app.input_mode = InputMode::EditingHeaders,
+                    KeyCode::Char('b') => app.input_mode = InputMode::EditingBody,
+                    KeyCode::Char('a') => app.input_mode = InputMode::EditingAuth,
+                    KeyCode::Char('A') => app.next_auth_mode(),
+                    KeyCode::Char('j') => app.is_json_body = !app.is_json_body,
+                    KeyCode::Char('f') => {
+                        app.redirect_policy.follow = !app.redirect_policy.follow;
+                        app.status_message = if app.redirect_policy.follow {
+                            "Following redirects".to_string()
+                        } else {
+                            "Not following redirects".to_string()
+                        };
+                    }
+                    KeyCode::Char('x') => app.hex_view = !app.hex_view,
+                    KeyCode::Char('t') => app.html_text_view = !app.html_text_view,
+                    KeyCode::Char('v') => app.tree_view = !app.tree_view,
+                    KeyCode::Char('F') => {
+                        app.input_mode = InputMode::EditingFilter;
+                        app.active_panel = ActivePanel::Response;
+                        app.response_tab = ResponseTab::Body;
+                    }
+                    KeyCode::Char('H') => {
+                        app.response_tab = match app.response_tab {
+                            ResponseTab::Body => ResponseTab::Headers,
+                            ResponseTab::Headers => ResponseTab::Body,
+                        };
+                        app.active_panel = ActivePanel::Response;
+                    }
+                    KeyCode::Char('m') => app.next_method(),
+                    KeyCode::Char('M') => app.previous_method(),
+                    KeyCode::Up if app.in_tree() => app.json_tree.select(-1),
+                    KeyCode::Down if app.in_tree() => app.json_tree.select(1),
+                    KeyCode::Left if app.in_tree() => app.navigate_tree(JsonTree::collapse),
+                    KeyCode::Right if app.in_tree() => app.navigate_tree(JsonTree::expand),
+                    KeyCode::Char(' ') if app.in_tree() => app.navigate_tree(JsonTree::toggle),
+                    KeyCode::PageUp if app.in_tree() => {
+                        let page = app.body_pager.height as isize;
+                        app.json_tree.select(-page)
+                    }
+                    KeyCode::PageDown if app.in_tree() => {
+                        let page = app.body_pager.height as isize;
+                        app.json_tree.select(page)
+                    }
+                    KeyCode::Home | KeyCode::Char('g') if app.in_tree() => app.json_tree.selected = 0,
+                    KeyCode::End | KeyCode::Char('G') if app.in_tree() => app.json_tree.select_last(),
+                    KeyCode::Char('y') if app.in_tree() => app.copy_tree_path(),
+                    KeyCode::Char('/') if app.in_tree() => {
+                        app.json_tree.search.clear();
+                        app.input_mode = InputMode::FindingKey;
+                    }
+                    KeyCode::Char('n') if app.in_tree() => app.find_key(),
+                    KeyCode::Up if app.in_pager() => app.with_pager(|pager, text| pager.scroll(text, -1)),
+                    KeyCode::Down if app.in_pager() => app.with_pager(|pager, text| pager.scroll(text, 1)),
+                    KeyCode::PageUp if app.in_pager() => app.with_pager(|pager, text| pager.scroll_page(text, -1)),
+                    KeyCode::PageDown if app.in_pager() => app.with_pager(|pager, text| pager.scroll_page(text, 1)),
+                    KeyCode::Char('g') if app.in_pager() => app.with_pager(|pager, _| pager.top = 0),
+                    KeyCode::Char('G') if app.in_pager() => app.with_pager(Pager::scroll_to_end),
+                    KeyCode::Left if app.in_pager() => {
+                        app.with_pager(|pager, text| pager.scroll_sideways(text, -SIDEWAYS_COLUMNS))
+                    }
+                    KeyCode::Right if app.in_pager() => {
+                        app.with_pager(|pager, text| pager.scroll_sideways(text, SIDEWAYS_COLUMNS))
+                    }
+                    KeyCode::Home if app.in_pager() => app.with_pager(|pager, _| pager.left = 0),
+                    KeyCode::Char('w') if app.in_pager() => app.with_pager(Pager::toggle_wrap),
+                    KeyCode::Char('/') if app.in_pager() => {
+                        app.with_pager(|pager, _| {
+                            pager.search.clear();
+                            pager.current = None;
+                        });
+                        app.input_mode = InputMode::SearchingText;
+                    }
+                    KeyCode::Char('n') if app.in_pager() => app.find_text(true),
+                    KeyCode::Char('N') if app.in_pager() => app.find_text(false),
+                    KeyCode::Up if matches!(app.active_panel, ActivePanel::History) => {
+                        app.select_history(-1)
+                    }
+                    KeyCode::Down if matches!(app.active_panel, ActivePanel::History) => {
+                        app.select_history(1)
+                    }
+                    KeyCode::Char('r') if matches!(app.active_panel, ActivePanel::History) => {
+                        app.recall_history()
+                    }
+                    KeyCode::Char('d') if matches!(app.active_panel, ActivePanel::History) => {
+                        app.mark_for_diff()
+                    }
+                    KeyCode::Up if matches!(app.active_panel, ActivePanel::Diff) => app.scroll_diff(-1),
+                    KeyCode::Down if matches!(app.active_panel, ActivePanel::Diff) => app.scroll_diff(1),
+                    KeyCode::PageUp if matches!(app.active_panel, ActivePanel::Diff) => {
+                        let page = app.diff.as_ref().map_or(1, |view| view.height.max(1)) as isize;
+                        app.scroll_diff(-page)
+                    }
+                    KeyCode::PageDown if matches!(app.active_panel, ActivePanel::Diff) => {
+                        let page = app.diff.as_ref().map_or(1, |view| view.height.max(1)) as isize;
+                        app.scroll_diff(page)
+                    }
+                    KeyCode::Char('g') if matches!(app.active_panel, ActivePanel::Diff) => app.scroll_diff(isize::MIN),
+                    KeyCode::Char('G') if matches!(app.active_panel, ActivePanel::Diff) => app.scroll_diff(isize::MAX),
+                    KeyCode::Char('n') if matches!(app.active_panel, ActivePanel::Diff) => app.next_change(true),
+                    KeyCode::Char('N') if matches!(app.active_panel, ActivePanel::Diff) => app.next_change(false),
+                    KeyCode::Esc if matches!(app.active_panel, ActivePanel::Diff) => {
+                        app.active_panel = ActivePanel::History
+                    }
+                    KeyCode::Up if matches!(app.active_panel, ActivePanel::Cookies) => {
+                        app.select_cookie(-1)
+                    }
+                    KeyCode::Down if matches!(app.active_panel, ActivePanel::Cookies) => {
+                        app.select_cookie(1)
+                    }
+                    KeyCode::Char('n') if matches!(app.active_panel, ActivePanel::Cookies) => {
+                        app.new_cookie()
+                    }
+                    KeyCode::Char('e') if matches!(app.active_panel, ActivePanel::Cookies) => {
+                        app.edit_cookie()
+                    }
+                    KeyCode::Char('d') if matches!(app.active_panel, ActivePanel::Cookies) => {
+                        if let Err(e) = app.delete_cookie() {
+                            app.status_message = format!("Error: {}", e);
                         }
-                        _ => {}
-                    },
-                    InputMode::EditingHeaders => match key.code {
-                        KeyCode::Esc => app.input_mode = InputMode::Normal,
-                        KeyCode::Char(c) => app.headers_input.push(c),
-                        KeyCode::Backspace => {
-                            app.headers_input.pop();
+                    }
+                    KeyCode::Enter => {
+                        if let Err(e) = app.send_request().await {
+                            app.status_message = format!("Error: {}", e);
                         }
-                        KeyCode::Enter => app.headers_input.push('\n'),
-                        _ => {}
-                    },
-                    InputMode::EditingBody => match key.code {
-                        KeyCode::Esc => app.input_mode = InputMode::Normal,
-                        KeyCode::Char(c) => app.body_input.push(c),
-                        KeyCode::Backspace => {
-                            app.body_input.pop();
+                    }
+                    KeyCode::Tab => {
+                        app.active_panel = match app.active_panel {
+                            ActivePanel::Request => ActivePanel::Response,
+                            ActivePanel::Response => ActivePanel::History,
+                            ActivePanel::History => ActivePanel::Cookies,
+                            ActivePanel::Cookies => ActivePanel::Request,
+                            ActivePanel::Diff => ActivePanel::History,
+                        };
+                    }
+                    _ => {}
+                },
+                InputMode::EditingUrl => match key.code {
+                    KeyCode::Esc => app.input_mode = InputMode::Normal,
+                    KeyCode::Enter => app.input_mode = InputMode::Normal,
+                    KeyCode::Char(c) => app.url.push(c),
+                    KeyCode::Backspace => {
+                        app.url.pop();
+                    }
+                    _ => {}
+                },
+                InputMode::EditingHeaders => match key.code {
+                    KeyCode::Esc => app.input_mode = InputMode::Normal,
+                    KeyCode::Char(c) => app.headers_input.push(c),
+                    KeyCode::Backspace => {
+                        app.headers_input.pop();
+                    }
+                    KeyCode::Enter => app.headers_input.push('\n'),
+                    _ => {}
+                },
+                InputMode::EditingFilter => match key.code {
+                    KeyCode::Enter => app.input_mode = InputMode::Normal,
+                    KeyCode::Esc => {
+                        app.filter_input.clear();
+                        app.input_mode = InputMode::Normal;
+                    }
+                    KeyCode::Char(c) => app.filter_input.push(c),
+                    KeyCode::Backspace => {
+                        app.filter_input.pop();
+                    }
+                    _ => {}
+                },
+                InputMode::FindingKey => match key.code {
+                    KeyCode::Enter => app.find_key(),
+                    KeyCode::Esc => app.input_mode = InputMode::Normal,
+                    KeyCode::Char(c) => app.json_tree.search.push(c),
+                    KeyCode::Backspace => {
+                        app.json_tree.search.pop();
+                    }
+                    _ => {}
+                },
+                InputMode::SearchingText => match key.code {
+                    KeyCode::Enter => app.input_mode = InputMode::Normal,
+                    KeyCode::Esc => {
+                        app.with_pager(|pager, _| {
+                            pager.search.clear();
+                            pager.current = None;
+                        });
+                        app.input_mode = InputMode::Normal;
+                    }
+                    KeyCode::Char(c) => app.with_pager(|pager, text| {
+                        pager.search.push(c);
+                        pager.search_from_top(text);
+                    }),
+                    KeyCode::Backspace => app.with_pager(|pager, text| {
+                        pager.search.pop();
+                        pager.search_from_top(text);
+                    }),
+                    _ => {}
+                },
+                InputMode::EditingBody => match key.code {
+                    KeyCode::Esc => app.input_mode = InputMode::Normal,
+                    KeyCode::Char(c) => app.body_input.push(c),
+                    KeyCode::Backspace => {
+                        app.body_input.pop();
+                    }
+                    KeyCode::Enter => app.body_input.push('\n'),
+                    _ => {}
+                },
+                InputMode::EditingAuth => match key.code {
+                    KeyCode::Esc | KeyCode::Enter => app.input_mode = InputMode::Normal,
+                    KeyCode::Char(c) => app.auth_input.push(c),
+                    KeyCode::Backspace => {
+                        app.auth_input.pop();
+                    }
+                    _ => {}
+                },
+                InputMode::EditingCookie => match key.code {
+                    KeyCode::Esc => {
+                        app.editing_cookie = None;
+                        app.cookie_input.clear();
+                        app.input_mode = InputMode::Normal;
+                    }
+                    KeyCode::Enter => {
+                        match app.save_cookie() {
+                            Ok(()) => app.cookie_input.clear(),
+                            Err(e) => app.status_message = format!("Error: {}", e),
                         }
-                        KeyCode::Enter => app.body_input.push('\n'),
-                        _ => {}
-                    },
-                }
+                        app.input_mode = InputMode::Normal;
+                    }
+                    KeyCode::Char(c) => app.cookie_input.push(c),
+                    KeyCode::Backspace => {
+                        app.cookie_input.pop();
+                    }
+                    _ => {}
+                },
             }
         }
         
@@ -476,4 +1947,26 @@ async fn run_app<B: Backend>(
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Environment;
+    use crate::testing::{reply, Server};
+
+    #[tokio::test]
+    async fn signs_every_hop_with_environment_signers() {
+        let server = Server::start(vec![reply(302, &[("Location", "/next")], b""), reply(200, &[], b"ok")]).await;
+        let environment: Environment = serde_json::from_str(r#"{"hmac": {"key": "secret"}}"#).unwrap();
+        let mut app = App { url: server.url("/start"), signers: environment.signers(), ..App::default() };
+
+        app.send_request().await.unwrap();
+        assert_eq!(app.current_response.as_ref().unwrap().status, 200);
+        let received = server.received();
+        assert_eq!(received.len(), 2);
+        for request in &received {
+            assert!(request.header("x-signature").is_some_and(|signature| signature.len() == 64));
+            assert!(request.header("x-timestamp").is_some());
+        }
+    }
+}
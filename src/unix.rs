@@ -0,0 +1,237 @@
+use hyper::client::conn;
+use reqwest::cookie::CookieStore;
+use reqwest::header::{HeaderValue, COOKIE, HOST, SET_COOKIE};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::UnixStream;
+
+use crate::cookies::CookieJar;
+
+/// Scheme of URLs that name their socket: `http+unix://%2Fvar%2Frun%2Fdocker.sock/info`.
+pub const UNIX_SCHEME: &str = "http+unix";
+
+#[derive(Debug)]
+pub enum UnixError {
+    Connect(PathBuf, io::Error),
+    Http(hyper::Error),
+    InvalidUrl(String),
+    StreamingBody,
+}
+
+impl fmt::Display for UnixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnixError::Connect(path, e) => {
+                write!(f, "Could not connect to Unix socket '{}': {}", path.display(), e)
+            }
+            UnixError::Http(e) => write!(f, "Unix socket request failed: {}", e),
+            UnixError::InvalidUrl(url) => {
+                write!(f, "Invalid {} URL: '{}'. Percent-encode the socket path as the host", UNIX_SCHEME, url)
+            }
+            UnixError::StreamingBody => {
+                write!(f, "Streaming request bodies cannot be sent over a Unix socket")
+            }
+        }
+    }
+}
+
+impl Error for UnixError {}
+
+/// Sends requests over a Unix domain socket, either the `--unix-socket` one
+/// for every request or the one named by an `http+unix://` URL.
+#[derive(Debug, Clone, Default)]
+pub struct UnixTransport {
+    socket: Option<PathBuf>,
+    /// Speak HTTP/2 with prior knowledge instead of HTTP/1.1.
+    http2: bool,
+    jar: Arc<CookieJar>,
+}
+
+impl UnixTransport {
+    pub fn new(socket: Option<PathBuf>, http2: bool, jar: Arc<CookieJar>) -> UnixTransport {
+        UnixTransport { socket, http2, jar }
+    }
+
+    /// The socket a request to `url` goes to and the `http://` URL it is sent
+    /// as, or `None` for requests over TCP.
+    pub fn target(&self, url: &Url) -> Result<Option<(PathBuf, Url)>, UnixError> {
+        if url.scheme() == UNIX_SCHEME {
+            let invalid = || UnixError::InvalidUrl(url.to_string());
+            let host = url.host_str().filter(|host| !host.is_empty()).ok_or_else(invalid)?;
+            let socket = percent_decode(host).ok_or_else(invalid)?;
+            let mut http_url = Url::parse("http://localhost/").map_err(|_| invalid())?;
+            http_url.set_path(url.path());
+            http_url.set_query(url.query());
+            return Ok(Some((PathBuf::from(socket), http_url)));
+        }
+        Ok(self.socket.clone().map(|socket| (socket, url.clone())))
+    }
+
+    /// Sends `request` to `socket` on a fresh connection, with
+    /// `url` giving the path and the default Host header. Cookies are read
+    /// from and stored into the same jar as TCP requests.
+    pub async fn execute(&self, socket: &Path, url: Url, mut request: Request) -> Result<Response, UnixError> {
+        let body = match request.body_mut().take() {
+            Some(body) => body.as_bytes().ok_or(UnixError::StreamingBody)?.to_vec(),
+            None => Vec::new(),
+        };
+
+        let mut headers = request.headers().clone();
+        let host = match headers.get(HOST).and_then(|host| host.to_str().ok()) {
+            Some(host) => host.to_string(),
+            None => match url.port() {
+                Some(port) => format!("{}:{}", url.host_str().unwrap_or("localhost"), port),
+                None => url.host_str().unwrap_or("localhost").to_string(),
+            },
+        };
+        if let Ok(value) = HeaderValue::from_str(&host) {
+            headers.insert(HOST, value);
+        }
+        if !headers.contains_key(COOKIE)
+            && let Some(cookies) = self.jar.cookies(&url)
+        {
+            headers.insert(COOKIE, cookies);
+        }
+
+        let mut target = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        // HTTP/2 takes the authority from the request target instead of Host.
+        if self.http2 {
+            target = format!("http://{}{}", host, target);
+        }
+        let mut builder = hyper::Request::builder().method(request.method().clone()).uri(target);
+        if let Some(outgoing) = builder.headers_mut() {
+            *outgoing = headers;
+        }
+        let outgoing = builder
+            .body(hyper::Body::from(body))
+            .map_err(|_| UnixError::InvalidUrl(url.to_string()))?;
+
+        let stream = UnixStream::connect(socket)
+            .await
+            .map_err(|e| UnixError::Connect(socket.to_path_buf(), e))?;
+        let (mut sender, connection) = conn::Builder::new()
+            .http2_only(self.http2)
+            .handshake(stream)
+            .await
+            .map_err(UnixError::Http)?;
+        tokio::spawn(connection);
+        let response = sender.send_request(outgoing).await.map_err(UnixError::Http)?;
+
+        let mut set_cookies = response.headers().get_all(SET_COOKIE).iter();
+        self.jar.set_cookies(&mut set_cookies, &url);
+
+        let (parts, body) = response.into_parts();
+        let mut builder = hyper::Response::builder().status(parts.status).version(parts.version).url(request.url().clone());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = parts.headers;
+        }
+        let response = builder.body(body).map_err(|_| UnixError::InvalidUrl(request.url().to_string()))?;
+        Ok(Response::from(response))
+    }
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{read_request, reply, respond, Received};
+    use reqwest::Method;
+    use tokio::net::UnixListener;
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("http-client-{}-{}.sock", std::process::id(), name))
+    }
+
+    /// Answers one request on a fresh socket at `path` and hands back what it read.
+    fn serve(path: &Path, reply: Vec<u8>) -> tokio::task::JoinHandle<Received> {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut stream).await.unwrap();
+            respond(&mut stream, &reply).await;
+            request
+        })
+    }
+
+    #[test]
+    fn targets_named_and_configured_sockets() {
+        let transport = UnixTransport::default();
+        let url = Url::parse("http+unix://%2Fvar%2Frun%2Fdocker.sock/v1/info?all=1").unwrap();
+        let (socket, http_url) = transport.target(&url).unwrap().unwrap();
+        assert_eq!(socket, PathBuf::from("/var/run/docker.sock"));
+        assert_eq!(http_url.as_str(), "http://localhost/v1/info?all=1");
+        assert!(transport.target(&Url::parse("http://example.com/").unwrap()).unwrap().is_none());
+
+        let transport = UnixTransport::new(Some(PathBuf::from("/tmp/api.sock")), false, Arc::default());
+        let url = Url::parse("http://example.com:8080/x").unwrap();
+        assert_eq!(transport.target(&url).unwrap(), Some((PathBuf::from("/tmp/api.sock"), url)));
+
+        let invalid = Url::parse("http+unix://%zz/info").unwrap();
+        assert!(matches!(transport.target(&invalid), Err(UnixError::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("%2Ftmp%2Fa%20b.sock").as_deref(), Some("/tmp/a b.sock"));
+        assert_eq!(percent_decode("plain").as_deref(), Some("plain"));
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%ff"), None);
+    }
+
+    #[tokio::test]
+    async fn sends_over_the_socket_with_cookies() {
+        let path = socket_path("send");
+        let server = serve(&path, reply(201, &[("Set-Cookie", "id=7")], b"created"));
+        let jar = Arc::new(CookieJar::new());
+        let transport = UnixTransport::new(Some(path.clone()), false, jar.clone());
+
+        let url = Url::parse("http://api.local:8080/items?x=1").unwrap();
+        let mut request = Request::new(Method::POST, url.clone());
+        *request.body_mut() = Some(b"{}".to_vec().into());
+        let (socket, target) = transport.target(&url).unwrap().unwrap();
+        let response = transport.execute(&socket, target, request).await.unwrap();
+        assert_eq!(response.status(), 201);
+        assert_eq!(response.url(), &url);
+        assert_eq!(response.text().await.unwrap(), "created");
+
+        let received = server.await.unwrap();
+        assert_eq!(received.request_line(), "POST /items?x=1 HTTP/1.1");
+        assert_eq!(received.header("host"), Some("api.local:8080"));
+        assert_eq!(received.body, b"{}");
+        assert_eq!(jar.cookies(&url).unwrap(), "id=7");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn reports_missing_sockets() {
+        let path = socket_path("missing");
+        let transport = UnixTransport::default();
+        let url = Url::parse("http://localhost/").unwrap();
+        let error = transport.execute(&path, url.clone(), Request::new(Method::GET, url)).await.unwrap_err();
+        assert!(matches!(error, UnixError::Connect(ref socket, _) if *socket == path));
+    }
+}
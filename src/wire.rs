@@ -0,0 +1,151 @@
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode, Version};
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::charset;
+
+/// A request or response head as it goes over the wire.
+#[derive(Debug, Clone)]
+pub enum Message {
+    Request {
+        method: Method,
+        /// Path and query, as in the request line.
+        target: String,
+        headers: HeaderMap,
+        body: Option<Vec<u8>>,
+    },
+    Response {
+        version: Version,
+        status: StatusCode,
+        headers: HeaderMap,
+    },
+}
+
+/// curl-style: `>` for what was sent, `<` for what came back. Requests are
+/// shown as HTTP/1.1; on HTTP/2 the Host header travels as `:authority`.
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Request { method, target, headers, body } => {
+                writeln!(f, "> {} {} HTTP/1.1", method, target)?;
+                write_headers(f, '>', headers)?;
+                write!(f, ">")?;
+                if let Some(body) = body.as_ref().filter(|body| !body.is_empty()) {
+                    match std::str::from_utf8(body) {
+                        Ok(text) => write!(f, "\n{}", text)?,
+                        Err(_) => write!(f, "\n({} bytes of binary data)", body.len())?,
+                    }
+                }
+                Ok(())
+            }
+            Message::Response { version, status, headers } => {
+                writeln!(f, "< {:?} {}", version, status)?;
+                write_headers(f, '<', headers)?;
+                write!(f, "<")
+            }
+        }
+    }
+}
+
+fn write_headers(f: &mut fmt::Formatter<'_>, prefix: char, headers: &HeaderMap) -> fmt::Result {
+    for (name, value) in headers {
+        writeln!(f, "{} {}: {}", prefix, name, charset::header_value(value))?;
+    }
+    Ok(())
+}
+
+/// Messages exchanged by a client, including retries made while answering
+/// authentication challenges.
+#[derive(Debug, Default)]
+pub struct WireLog(Mutex<Vec<Message>>);
+
+impl WireLog {
+    pub fn record(&self, message: Message) {
+        self.0.lock().unwrap().push(message);
+    }
+
+    /// Messages recorded since the last call.
+    pub fn take(&self) -> Vec<Message> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientSettings;
+    use crate::cookies::CookieJar;
+    use crate::testing::{reply, Server};
+    use reqwest::header::HeaderValue;
+    use std::sync::Arc;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn formats_requests_like_curl() {
+        let request = Message::Request {
+            method: Method::POST,
+            target: "/items?page=2".to_string(),
+            headers: headers(&[("host", "api.example.com"), ("accept", "*/*")]),
+            body: Some(b"{\"a\":1}".to_vec()),
+        };
+        assert_eq!(
+            request.to_string(),
+            "> POST /items?page=2 HTTP/1.1\n> host: api.example.com\n> accept: */*\n>\n{\"a\":1}"
+        );
+
+        let binary = Message::Request { method: Method::PUT, target: "/".to_string(), headers: HeaderMap::new(), body: Some(vec![0xff, 0, 1]) };
+        assert_eq!(binary.to_string(), "> PUT / HTTP/1.1\n>\n(3 bytes of binary data)");
+        let empty = Message::Request { method: Method::GET, target: "/".to_string(), headers: HeaderMap::new(), body: Some(Vec::new()) };
+        assert_eq!(empty.to_string(), "> GET / HTTP/1.1\n>");
+    }
+
+    #[test]
+    fn formats_responses_with_repeated_headers() {
+        let response = Message::Response {
+            version: Version::HTTP_2,
+            status: StatusCode::NOT_FOUND,
+            headers: headers(&[("set-cookie", "a=1"), ("set-cookie", "b=2")]),
+        };
+        assert_eq!(response.to_string(), "< HTTP/2.0 404 Not Found\n< set-cookie: a=1\n< set-cookie: b=2\n<");
+    }
+
+    #[tokio::test]
+    async fn records_what_the_client_sends() {
+        let server = Server::start(vec![reply(201, &[("Set-Cookie", "id=1")], b""), reply(200, &[], b"")]).await;
+        let wire = Arc::new(WireLog::default());
+        let settings = ClientSettings { wire: Some(wire.clone()), ..ClientSettings::default() };
+        let client = settings.build(Arc::new(CookieJar::new())).unwrap();
+
+        let request = client.request(Method::POST, &server.url("/a?b=c")).body("hello").build().unwrap();
+        client.execute(request).await.unwrap();
+        let request = client.request(Method::GET, &server.url("/next")).build().unwrap();
+        client.execute(request).await.unwrap();
+
+        let messages = wire.take();
+        assert_eq!(messages.len(), 4);
+        let Message::Request { target, headers, body, .. } = &messages[0] else {
+            panic!("expected the request first");
+        };
+        assert_eq!(target, "/a?b=c");
+        assert_eq!(headers["host"], server.addr.to_string().as_str());
+        assert_eq!(headers["content-length"], "5");
+        assert_eq!(body.as_deref(), Some(&b"hello"[..]));
+        assert!(matches!(&messages[1], Message::Response { status, .. } if *status == StatusCode::CREATED));
+        // The cookie set by the first response is shown on the second request,
+        // as it is sent.
+        let Message::Request { headers, .. } = &messages[2] else {
+            panic!("expected the second request");
+        };
+        assert_eq!(headers["cookie"], "id=1");
+        assert_eq!(server.received()[1].header("cookie"), Some("id=1"));
+        assert!(wire.take().is_empty());
+    }
+}
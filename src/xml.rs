@@ -0,0 +1,77 @@
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum XmlError {
+    ParseError(quick_xml::Error),
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XmlError::ParseError(e) => write!(f, "XML parse error: {}", e),
+        }
+    }
+}
+
+impl Error for XmlError {}
+
+impl From<quick_xml::Error> for XmlError {
+    fn from(error: quick_xml::Error) -> Self {
+        XmlError::ParseError(error)
+    }
+}
+
+pub fn is_xml_like(text: &str) -> bool {
+    text.trim_start().starts_with("<?xml")
+}
+
+/// Re-indents `text` by two spaces per level. Elements, attributes and
+/// namespace prefixes are written back exactly as they were read; only
+/// whitespace between tags changes.
+pub fn pretty_print_xml(text: &str) -> Result<String, XmlError> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            event => writer.write_event(event)?,
+        }
+    }
+    Ok(String::from_utf8_lossy(&writer.into_inner()).into_owned())
+}
+
+pub fn pretty_print_xml_safe(text: &str) -> String {
+    match pretty_print_xml(text) {
+        Ok(pretty) => pretty,
+        Err(_) => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reindents_and_keeps_names_and_attributes() {
+        let xml = "<?xml version=\"1.0\"?><soap:Envelope xmlns:soap=\"urn:s\"><soap:Body a='1'><item>x</item><empty/></soap:Body></soap:Envelope>";
+        assert_eq!(
+            pretty_print_xml(xml).unwrap(),
+            "<?xml version=\"1.0\"?>\n<soap:Envelope xmlns:soap=\"urn:s\">\n  <soap:Body a='1'>\n    <item>x</item>\n    \
+             <empty/>\n  </soap:Body>\n</soap:Envelope>"
+        );
+    }
+
+    #[test]
+    fn keeps_unparseable_bodies() {
+        let broken = "<a><b></a>";
+        assert!(matches!(pretty_print_xml(broken), Err(XmlError::ParseError(_))));
+        assert_eq!(pretty_print_xml_safe(broken), broken);
+        assert!(is_xml_like("  <?xml version=\"1.0\"?><a/>"));
+        assert!(!is_xml_like("<a/>"));
+    }
+}
@@ -0,0 +1,63 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum YamlError {
+    ParseError(serde_yaml::Error),
+}
+
+impl fmt::Display for YamlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YamlError::ParseError(e) => write!(f, "YAML parse error: {}", e),
+        }
+    }
+}
+
+impl Error for YamlError {}
+
+impl From<serde_yaml::Error> for YamlError {
+    fn from(error: serde_yaml::Error) -> Self {
+        YamlError::ParseError(error)
+    }
+}
+
+/// Re-serializes every document in `text` with consistent indentation and
+/// quoting. Key order is kept; comments are dropped.
+pub fn pretty_print_yaml(text: &str) -> Result<String, YamlError> {
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(text) {
+        let value = serde_yaml::Value::deserialize(document)?;
+        documents.push(serde_yaml::to_string(&value)?);
+    }
+    Ok(documents.join("---\n").trim_end().to_string())
+}
+
+pub fn pretty_print_yaml_safe(text: &str) -> String {
+    match pretty_print_yaml(text) {
+        Ok(pretty) => pretty,
+        Err(_) => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_documents_in_order() {
+        let yaml = "zeta:   1\nalpha: [a,   b]\nnested: {key: 'v'}\n# comment\n---\n- x\n";
+        assert_eq!(
+            pretty_print_yaml(yaml).unwrap(),
+            "zeta: 1\nalpha:\n- a\n- b\nnested:\n  key: v\n---\n- x"
+        );
+    }
+
+    #[test]
+    fn keeps_unparseable_bodies() {
+        let broken = "key: [unclosed";
+        assert!(matches!(pretty_print_yaml(broken), Err(YamlError::ParseError(_))));
+        assert_eq!(pretty_print_yaml_safe(broken), broken);
+    }
+}